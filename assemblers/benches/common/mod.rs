@@ -0,0 +1,23 @@
+use std::fmt::Write;
+
+/// Generate a program that walks a table of `lines` `.FILL` directives, through
+/// a pointer declared before the table
+pub fn fill_table(lines: u64) -> String {
+    let mut assembly = String::from(
+        ".ORIG x3000
+        LD R1, POINTER
+LOOP    LDR R0, R1, #0
+        ADD R1, R1, #1
+        BRP LOOP
+        HALT
+POINTER .BLKW 1, TABLE
+TABLE
+",
+    );
+    for line in 0..lines {
+        writeln!(assembly, "        .FILL #{}", line % 256).unwrap();
+    }
+    assembly.push_str(".END\n");
+
+    assembly
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use assemblers::lc2::{
+    incremental::{IncrementalAssembler, TextEdit},
+    Lc2AssemblerBuilder,
+};
+use common::fill_table;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+
+/// Change the operand of an operation, like while typing in an editor, and
+/// assemble the program again
+fn edit(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("edit_operand");
+
+    for lines in [1_000, 10_000, 30_000] {
+        let assembly = fill_table(lines);
+        let operand = assembly.find("#1\n").unwrap();
+        group.throughput(Throughput::Elements(lines));
+
+        group.bench_with_input(
+            BenchmarkId::new("full", lines),
+            &assembly,
+            |bencher, assembly| {
+                let assembler = Lc2AssemblerBuilder::new().build();
+                let mut assembly = assembly.clone();
+                let mut increment = 1;
+                bencher.iter(|| {
+                    increment = increment % 9 + 1;
+                    assembly.replace_range(operand..operand + 2, &format!("#{increment}"));
+                    assembler.assemble_with_diagnostic(black_box(&assembly))
+                });
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("incremental", lines),
+            &assembly,
+            |bencher, assembly| {
+                let mut assembler =
+                    IncrementalAssembler::new(Lc2AssemblerBuilder::new().build(), assembly);
+                let mut increment = 1;
+                bencher.iter(|| {
+                    increment = increment % 9 + 1;
+                    assembler
+                        .edit([TextEdit {
+                            range: operand..operand + 2,
+                            text: format!("#{increment}"),
+                        }])
+                        .is_ok()
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, edit);
+criterion_main!(benches);
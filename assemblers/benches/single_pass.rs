@@ -0,0 +1,31 @@
+mod common;
+
+use assemblers::lc2::Lc2AssemblerBuilder;
+use common::fill_table;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+
+fn assemble(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("fill_table");
+
+    for lines in [1_000, 10_000, 30_000] {
+        let assembly = fill_table(lines);
+        group.throughput(Throughput::Elements(lines));
+
+        for (name, single_pass) in [("two_passes", false), ("single_pass", true)] {
+            let assembler = Lc2AssemblerBuilder::new().single_pass(single_pass).build();
+            group.bench_with_input(
+                BenchmarkId::new(name, lines),
+                &assembly,
+                |bencher, assembly| {
+                    bencher.iter(|| assembler.assemble_with_diagnostic(black_box(assembly)));
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, assemble);
+criterion_main!(benches);
@@ -0,0 +1,73 @@
+use std::{
+    fmt::Debug,
+    path::{Component, Path, PathBuf},
+};
+
+/// Loads the files referenced by an assembly, like the ones embedded with the
+/// `.INCBIN` directive
+pub trait IncludeResolver: Debug + Send + Sync {
+    /// Return the content of the file at `path`, or `None` if it can't be read
+    fn resolve(&self, path: &str) -> Option<Vec<u8>>;
+}
+
+/// Resolve the included files by reading them from the file system, relative to
+/// a base directory.
+///
+/// The absolute paths and the paths with a `..` component are rejected, so an
+/// assembly can't read the files outside of the base directory
+#[derive(Debug, Clone)]
+pub struct FileSystemResolver {
+    base: PathBuf,
+}
+
+impl FileSystemResolver {
+    #[must_use]
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        Self { base: base.into() }
+    }
+}
+
+impl IncludeResolver for FileSystemResolver {
+    fn resolve(&self, path: &str) -> Option<Vec<u8>> {
+        let inside_base = Path::new(path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if !inside_base {
+            log::warn!(target: "lc2_assembler", "The file \"{path}\" is outside of the base directory");
+            return None;
+        }
+
+        std::fs::read(self.base.join(path))
+            .inspect_err(|error| {
+                log::warn!(target: "lc2_assembler", "Can't read the file \"{path}\": {error}");
+            })
+            .ok()
+    }
+}
+
+/// Resolve the included files from memory, useful where there isn't a file
+/// system
+#[derive(Debug, Clone, Default)]
+pub struct MemoryResolver {
+    files: std::collections::HashMap<String, Vec<u8>>,
+}
+
+impl MemoryResolver {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file that can be included with the name `path`
+    #[must_use]
+    pub fn with_file(mut self, path: impl Into<String>, content: impl Into<Vec<u8>>) -> Self {
+        self.files.insert(path.into(), content.into());
+        self
+    }
+}
+
+impl IncludeResolver for MemoryResolver {
+    fn resolve(&self, path: &str) -> Option<Vec<u8>> {
+        self.files.get(path).cloned()
+    }
+}
@@ -0,0 +1,612 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// A control-flow graph of an assembled LC2 program, built by following every
+/// path of the execution from its entry point.
+///
+/// Since the binary doesn't tell code and data apart, only the words that can
+/// be reached are decoded as operations, while the words read or written by
+/// `LD`, `LDI`, `ST` and `STI` are considered data
+#[derive(Debug, Clone)]
+pub struct ControlFlowGraph {
+    entry_point: u16,
+    nodes: BTreeMap<u16, Node>,
+    data: BTreeSet<u16>,
+    subroutines: Vec<Subroutine>,
+    diagnostics: Vec<FlowDiagnostic>,
+    labels: HashMap<u16, String>,
+}
+
+/// A reachable operation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub word: u16,
+    pub successors: Vec<Edge>,
+}
+
+/// A possible transfer of the execution from an operation to another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub target: u16,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// The execution continues to the next operation
+    Next,
+    /// A conditional or unconditional `BR`
+    Branch,
+    /// A `JMP`
+    Jump,
+    /// A `JSR`, that will return to the operation after it
+    Call,
+}
+
+/// The operations of a subroutine: the ones reachable from the target of a
+/// `JSR` without following the calls to other subroutines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subroutine {
+    pub entry: u16,
+    pub name: Option<String>,
+    pub operations: BTreeSet<u16>,
+    /// The addresses of the `RET` (or `JMPR R7, 0`) operations
+    pub returns: BTreeSet<u16>,
+    /// For every register, the first operation that overwrites it without
+    /// restoring it before a return, if any
+    pub overwritten: [Option<u16>; 8],
+}
+
+/// A probable mistake found by the analysis
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum FlowDiagnostic {
+    #[error("The execution can fall off the end of the program after {from:#06x}")]
+    FallsOffEnd { from: u16 },
+    #[error("The execution can go from {from:#06x} into the data at {to:#06x}")]
+    FallsIntoData { from: u16, to: u16 },
+    #[error("The subroutine at {subroutine:#06x} never returns")]
+    MissingReturn { subroutine: u16 },
+    #[error("R7 is overwritten at {clobbered:#06x} before the return at {ret:#06x} of the subroutine at {subroutine:#06x}")]
+    ClobberedReturnAddress {
+        subroutine: u16,
+        clobbered: u16,
+        ret: u16,
+    },
+    #[error("R{register} is read at {address:#06x} before being written")]
+    UninitializedRead { address: u16, register: usize },
+    #[error("The callee-saved R{register} is overwritten at {overwritten:#06x} by the subroutine at {subroutine:#06x}")]
+    CalleeSavedOverwritten {
+        subroutine: u16,
+        register: usize,
+        overwritten: u16,
+    },
+}
+
+/// What the registers look like at a given operation of a subroutine,
+/// compared to when it was called
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Preserved {
+    /// The address of the operation that overwrote each register, if any
+    overwritten: [Option<u16>; 8],
+    /// Where each register was stored in memory on every path, so that
+    /// loading it back from there restores it
+    saved: [Option<Slot>; 8],
+}
+
+/// The memory operand of a store or of a load: the address of `ST` and `LD`,
+/// the pointer of `STI` and `LDI`, or the base register and the offset of `STR`
+/// and `LDR` (like a stack slot)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Slot {
+    Direct(u16),
+    Indirect(u16),
+    Relative(u16),
+}
+
+impl Slot {
+    /// Return the memory operand of the operation, if it's a load or a store
+    const fn of(word: u16, address: u16) -> Option<Self> {
+        match word >> 12 {
+            0b0010 | 0b0011 => Some(Self::Direct(page_address(word, address))),
+            0b1010 | 0b1011 => Some(Self::Indirect(page_address(word, address))),
+            0b0110 | 0b0111 => Some(Self::Relative(word & 0x1ff)),
+            _ => None,
+        }
+    }
+}
+
+impl Preserved {
+    const INTACT: Self = Self {
+        overwritten: [None; 8],
+        saved: [None; 8],
+    };
+
+    /// Merge the states of two paths: a register is overwritten if it's
+    /// overwritten on any of them, but it's saved only if it's saved in the
+    /// same slot on both
+    fn merge(mut self, other: Self) -> Self {
+        for register in 0..8 {
+            self.overwritten[register] = self.overwritten[register].or(other.overwritten[register]);
+            if self.saved[register] != other.saved[register] {
+                self.saved[register] = None;
+            }
+        }
+
+        self
+    }
+}
+
+impl ControlFlowGraph {
+    /// Build the graph of the `words` loaded at `origin`, starting the
+    /// execution from the `entry_point` (or from the `origin` if it's not set)
+    #[must_use]
+    pub fn new(
+        words: &[u16],
+        origin: u16,
+        symbol_table: &HashMap<String, u16>,
+        entry_point: Option<u16>,
+    ) -> Self {
+        // Keep a label for every address, choosing the first one in
+        // alphabetical order to always get the same one
+        let mut labels = HashMap::new();
+        for (label, &address) in symbol_table {
+            labels
+                .entry(address)
+                .and_modify(|existing: &mut String| {
+                    if label < existing {
+                        existing.clone_from(label);
+                    }
+                })
+                .or_insert_with(|| label.clone());
+        }
+
+        let entry_point = entry_point.unwrap_or(origin);
+        let mut graph = Self {
+            entry_point,
+            nodes: BTreeMap::new(),
+            data: BTreeSet::new(),
+            subroutines: Vec::new(),
+            diagnostics: Vec::new(),
+            labels,
+        };
+
+        graph.explore(words, origin, entry_point);
+        graph.find_data_execution();
+        graph.find_subroutines();
+
+        graph
+    }
+
+    /// Return the reachable operations, by address
+    #[must_use]
+    pub const fn nodes(&self) -> &BTreeMap<u16, Node> {
+        &self.nodes
+    }
+
+    /// Return `true` if the operation at the address can be executed
+    #[must_use]
+    pub fn is_reachable(&self, address: u16) -> bool {
+        self.nodes.contains_key(&address)
+    }
+
+    /// Return the addresses read or written as data by the reachable
+    /// operations
+    #[must_use]
+    pub const fn data(&self) -> &BTreeSet<u16> {
+        &self.data
+    }
+
+    /// Return the subroutines called by the reachable operations
+    #[must_use]
+    pub fn subroutines(&self) -> &[Subroutine] {
+        &self.subroutines
+    }
+
+    /// Return the probable mistakes found by the analysis
+    #[must_use]
+    pub fn diagnostics(&self) -> &[FlowDiagnostic] {
+        &self.diagnostics
+    }
+
+    /// Return the registers read before being written, following the
+    /// execution from the entry point.
+    ///
+    /// The subroutines get their arguments in the registers, so their reads
+    /// are not checked, but a call is considered to write every register
+    /// written by the subroutine
+    #[must_use]
+    pub fn uninitialized_reads(&self) -> Vec<FlowDiagnostic> {
+        let writes = self.subroutine_writes();
+
+        // Propagate the registers written on every path until nothing changes.
+        // Since a merge can only remove registers, it ends
+        let mut states = BTreeMap::new();
+        let mut queue = Vec::new();
+        if self.nodes.contains_key(&self.entry_point) {
+            states.insert(self.entry_point, 0u8);
+            queue.push(self.entry_point);
+        }
+
+        while let Some(address) = queue.pop() {
+            let node = &self.nodes[&address];
+            let state = states[&address] | registers(node.word).1;
+
+            for edge in &node.successors {
+                let state = match edge.kind {
+                    // Don't follow the calls into the subroutines
+                    EdgeKind::Call => continue,
+
+                    // After a call, add the registers written by the
+                    // subroutine (any register, if it's not known)
+                    EdgeKind::Next if is_call(node.word) => state | called_writes(node, &writes),
+
+                    _ => state,
+                };
+
+                if !self.nodes.contains_key(&edge.target) {
+                    continue;
+                }
+
+                let merged = states
+                    .get(&edge.target)
+                    .map_or(state, |&existing| existing & state);
+                if states.get(&edge.target) != Some(&merged) {
+                    states.insert(edge.target, merged);
+                    queue.push(edge.target);
+                }
+            }
+        }
+
+        // Report every register read before being written
+        let mut diagnostics = Vec::new();
+        for (&address, &state) in &states {
+            let (reads, _) = registers(self.nodes[&address].word);
+            for register in 0..8 {
+                if reads & !state & (1 << register) != 0 {
+                    diagnostics.push(FlowDiagnostic::UninitializedRead { address, register });
+                }
+            }
+        }
+
+        diagnostics
+    }
+
+    /// Return the `registers` (by number) that are overwritten and not
+    /// restored by a subroutine, breaking the calling convention
+    #[must_use]
+    pub fn callee_saved_violations(&self, registers: &[usize]) -> Vec<FlowDiagnostic> {
+        self.subroutines
+            .iter()
+            .flat_map(|subroutine| {
+                registers.iter().filter_map(|&register| {
+                    Some(FlowDiagnostic::CalleeSavedOverwritten {
+                        subroutine: subroutine.entry,
+                        register,
+                        overwritten: (*subroutine.overwritten.get(register)?)?,
+                    })
+                })
+            })
+            .collect()
+    }
+
+    /// Return the registers that each subroutine can write, including the ones
+    /// written by the subroutines it calls
+    fn subroutine_writes(&self) -> HashMap<u16, u8> {
+        let mut writes: HashMap<u16, u8> = self
+            .subroutines
+            .iter()
+            .map(|subroutine| {
+                let written = subroutine.operations.iter().fold(0, |written, address| {
+                    written | registers(self.nodes[address].word).1
+                });
+                (subroutine.entry, written)
+            })
+            .collect();
+
+        // Add the registers written by the called subroutines until nothing
+        // changes
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for subroutine in &self.subroutines {
+                let called = subroutine
+                    .operations
+                    .iter()
+                    .filter(|&address| is_call(self.nodes[address].word))
+                    .fold(0, |written, address| {
+                        written | called_writes(&self.nodes[address], &writes)
+                    });
+
+                let written = writes[&subroutine.entry] | called;
+                if written != writes[&subroutine.entry] {
+                    writes.insert(subroutine.entry, written);
+                    changed = true;
+                }
+            }
+        }
+
+        writes
+    }
+
+    /// Return the label of the address, if any
+    #[must_use]
+    pub fn label(&self, address: u16) -> Option<&str> {
+        self.labels.get(&address).map(String::as_str)
+    }
+
+    /// Decode every operation reachable from the entry point
+    fn explore(&mut self, words: &[u16], origin: u16, entry_point: u16) {
+        let mut queue = vec![(None, entry_point)];
+
+        while let Some((from, address)) = queue.pop() {
+            if self.nodes.contains_key(&address) {
+                continue;
+            }
+
+            // Make sure that the address is inside of the program
+            let Some(&word) = address
+                .checked_sub(origin)
+                .and_then(|index| words.get(usize::from(index)))
+            else {
+                if let Some(from) = from {
+                    self.diagnostics.push(FlowDiagnostic::FallsOffEnd { from });
+                }
+                continue;
+            };
+
+            let (successors, data) = decode(word, address);
+
+            self.data.extend(data);
+            queue.extend(
+                successors
+                    .iter()
+                    .rev()
+                    .map(|edge| (Some(address), edge.target)),
+            );
+            self.nodes.insert(address, Node { word, successors });
+        }
+    }
+
+    /// Find the operations that execute some data
+    fn find_data_execution(&mut self) {
+        for (&from, node) in &self.nodes {
+            for edge in &node.successors {
+                if self.data.contains(&edge.target) {
+                    self.diagnostics.push(FlowDiagnostic::FallsIntoData {
+                        from,
+                        to: edge.target,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Find the boundaries of the subroutines, checking that they return with
+    /// the right return address
+    fn find_subroutines(&mut self) {
+        let entries: BTreeSet<u16> = self
+            .nodes
+            .values()
+            .flat_map(|node| &node.successors)
+            .filter(|edge| edge.kind == EdgeKind::Call)
+            .map(|edge| edge.target)
+            .filter(|target| self.nodes.contains_key(target))
+            .collect();
+
+        for entry in entries {
+            let mut states = HashMap::from([(entry, Preserved::INTACT)]);
+            let mut queue = vec![entry];
+            let mut returns = BTreeSet::new();
+
+            // Propagate the state of the registers until nothing changes. Since
+            // a state can only go from intact to overwritten and from saved to
+            // not saved, it ends
+            while let Some(address) = queue.pop() {
+                let node = &self.nodes[&address];
+                if is_return(node.word) {
+                    returns.insert(address);
+                    continue;
+                }
+
+                let state = transfer(states[&address], node.word, address);
+                for edge in &node.successors {
+                    // Don't follow the calls into other subroutines
+                    if edge.kind == EdgeKind::Call || !self.nodes.contains_key(&edge.target) {
+                        continue;
+                    }
+
+                    let merged = states
+                        .get(&edge.target)
+                        .map_or(state, |&existing| existing.merge(state));
+                    if states.get(&edge.target) != Some(&merged) {
+                        states.insert(edge.target, merged);
+                        queue.push(edge.target);
+                    }
+                }
+            }
+
+            // Check the return address at every return
+            if returns.is_empty() {
+                self.diagnostics
+                    .push(FlowDiagnostic::MissingReturn { subroutine: entry });
+            }
+            for &ret in &returns {
+                if let Some(clobbered) = states[&ret].overwritten[7] {
+                    self.diagnostics
+                        .push(FlowDiagnostic::ClobberedReturnAddress {
+                            subroutine: entry,
+                            clobbered,
+                            ret,
+                        });
+                }
+            }
+
+            let overwritten = returns
+                .iter()
+                .fold(Preserved::INTACT, |preserved, ret| {
+                    preserved.merge(states[ret])
+                })
+                .overwritten;
+
+            self.subroutines.push(Subroutine {
+                entry,
+                name: self.labels.get(&entry).cloned(),
+                operations: states.into_keys().collect(),
+                returns,
+                overwritten,
+            });
+        }
+    }
+}
+
+/// Return the address of the `pgoffset9` operand, on the same page of the
+/// operation
+const fn page_address(word: u16, address: u16) -> u16 {
+    (address & 0xfe00) | (word & 0x1ff)
+}
+
+/// Return the registers written by the subroutine called by the operation, or
+/// every register if it's not known (like for a `JSRR`)
+fn called_writes(node: &Node, writes: &HashMap<u16, u8>) -> u8 {
+    node.successors
+        .iter()
+        .find(|edge| edge.kind == EdgeKind::Call)
+        .and_then(|call| writes.get(&call.target))
+        .copied()
+        .unwrap_or(u8::MAX)
+}
+
+/// Return `true` if the operation is a `JSR` or a `JSRR`
+const fn is_call(word: u16) -> bool {
+    matches!(word >> 12, 0b0100 | 0b1100) && word & 0x0800 != 0
+}
+
+/// Return `true` if the operation is a `RET` or a `JMPR R7, 0`
+const fn is_return(word: u16) -> bool {
+    word == 0b1101_0000_0000_0000 || word == 0b1100_0001_1100_0000
+}
+
+/// Return the successors of the operation and the address of the data it
+/// accesses, if any
+fn decode(word: u16, address: u16) -> (Vec<Edge>, Option<u16>) {
+    let next = address.checked_add(1).map(|target| Edge {
+        target,
+        kind: EdgeKind::Next,
+    });
+    let target = page_address(word, address);
+
+    // RET, RTI, HALT and JMPR (whose target can't be known) have no successors
+    let opcode = word >> 12;
+    let stops = matches!(opcode, 0b1101 | 0b1000)
+        || (opcode == 0b1100 && word & 0x0800 == 0)
+        || (opcode == 0b1111 && word & 0xff == 0x25);
+
+    match opcode {
+        _ if stops => (Vec::new(), None),
+
+        // BR: a branch without conditions never jumps, while a branch with
+        // all of them always does
+        0b0000 => {
+            let branch = Edge {
+                target,
+                kind: EdgeKind::Branch,
+            };
+
+            let successors = match (word >> 9) & 0b111 {
+                0b000 => next.into_iter().collect(),
+                0b111 => vec![branch],
+                _ => next.into_iter().chain([branch]).collect(),
+            };
+            (successors, None)
+        }
+
+        // JSR and JMP
+        0b0100 if word & 0x0800 != 0 => (
+            std::iter::once(Edge {
+                target,
+                kind: EdgeKind::Call,
+            })
+            .chain(next)
+            .collect(),
+            None,
+        ),
+        0b0100 => (
+            vec![Edge {
+                target,
+                kind: EdgeKind::Jump,
+            }],
+            None,
+        ),
+
+        // LD, ST, LDI and STI access the data at their operand
+        0b0010 | 0b0011 | 0b1010 | 0b1011 => (next.into_iter().collect(), Some(target)),
+
+        _ => (next.into_iter().collect(), None),
+    }
+}
+
+/// Return the registers read and written by the operation, as bit masks
+const fn registers(word: u16) -> (u8, u8) {
+    const fn bit(register: u16) -> u8 {
+        1 << (register & 0b111)
+    }
+
+    let destination = bit(word >> 9);
+    let source1 = bit(word >> 6);
+    let link = word & 0x0800 != 0;
+
+    match word >> 12 {
+        // ADD and AND read a second register if they don't use an immediate
+        0b0001 | 0b0101 if word & 0b10_0000 == 0 => (source1 | bit(word), destination),
+        0b0001 | 0b0101 | 0b1001 | 0b0110 => (source1, destination),
+        0b0010 | 0b1010 | 0b1110 => (0, destination),
+        0b0011 | 0b1011 => (destination, 0),
+        0b0111 => (destination | source1, 0),
+        0b0100 if link => (0, bit(7)),
+        0b1100 if link => (source1, bit(7)),
+        0b1100 => (source1, 0),
+        0b1101 => (bit(7), 0),
+
+        // The traps put their return address into R7. GETC and IN read a
+        // character into R0, while OUT, PUTS and PUTSP print from R0
+        0b1111 => match word & 0xff {
+            0x20 | 0x23 => (0, bit(7) | bit(0)),
+            0x21 | 0x22 | 0x24 => (bit(0), bit(7)),
+            _ => (0, bit(7)),
+        },
+
+        _ => (0, 0),
+    }
+}
+
+/// Return the state of the registers after the operation
+fn transfer(mut state: Preserved, word: u16, address: u16) -> Preserved {
+    let opcode = word >> 12;
+    let register = ((word >> 9) & 0b111) as usize;
+    let (_, mut writes) = registers(word);
+    let slot = Slot::of(word, address);
+
+    // ST, STI and STR save a register (if it wasn't already overwritten),
+    // while LD, LDI and LDR restore it only from the slot it was saved to
+    if matches!(opcode, 0b0011 | 0b1011 | 0b0111) && state.overwritten[register].is_none() {
+        state.saved[register] = slot;
+    }
+    if matches!(opcode, 0b0010 | 0b1010 | 0b0110)
+        && state.saved[register].is_some()
+        && state.saved[register] == slot
+    {
+        state.overwritten[register] = None;
+        writes = 0;
+    }
+
+    // Keep the first operation that overwrote each register
+    let mut register = 0;
+    while register < 8 {
+        if writes & (1 << register) != 0 && state.overwritten[register].is_none() {
+            state.overwritten[register] = Some(address);
+        }
+        register += 1;
+    }
+
+    state
+}
@@ -0,0 +1,705 @@
+use super::lexer::{RelativeLabel, Token, TokenStream};
+use crate::{Diagnostic, ParseError};
+use architectures::lc2::Gpr;
+use std::{
+    collections::VecDeque,
+    fmt::{Display, Write},
+    ops::Range,
+};
+
+pub use super::{
+    lexer::{Comparison, Operation, PseudoOperation, StringLiteral, StringPart},
+    parser::LabelScope,
+};
+
+/// The syntax tree of an LC2 assembly, one `Line` for every line of the source.
+///
+/// Every node keeps the byte range of the source it was parsed from, while the
+/// nodes created by hand can use an empty span. A program prints back into an
+/// assembly with `Display`, that can be assembled with
+/// `Lc2Assembler::assemble_program()`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Program {
+    pub lines: Vec<Line>,
+}
+
+/// A line of the assembly, where every part is optional. A line with more than
+/// one statement is split into many lines with the same span
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Line {
+    pub label: Option<Label>,
+    pub statement: Option<Statement>,
+    pub comment: Option<Comment>,
+    pub span: Range<usize>,
+}
+
+/// A label declaration, with or without the trailing colon
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub kind: LabelDeclaration,
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LabelDeclaration {
+    /// `main`, that opens a new scope for the local labels
+    Global(String),
+    /// `.loop`, scoped to the previous global label
+    Local(String),
+    /// `1:`, that can be declared many times
+    Numeric(String),
+    /// `-` or `+`, referenced by the backward or forward anonymous labels
+    Anonymous { forward: bool },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Statement {
+    Instruction(Instruction),
+    Directive(Directive),
+}
+
+/// An operation, like `ADD R0, R0, #1`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub operation: Operation,
+    pub operands: Vec<Operand>,
+    pub span: Range<usize>,
+}
+
+/// A pseudo-operation, like `.FILL x3000`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Directive {
+    pub pseudo_operation: PseudoOperation,
+    pub operands: Vec<Operand>,
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    Register(Gpr),
+    /// A number or a character literal, negative if it was written with a
+    /// minus sign
+    Number(i32),
+    /// A label, with the span of the reference
+    Label(LabelReference, Range<usize>),
+    /// The unescaped content of a string literal
+    String(StringLiteral),
+    /// Two or more terms added together, like `end-1` or `label + 2`
+    Expression(Vec<(Sign, Term)>),
+    /// The operator of an `.ASSERT` condition, between two operands
+    Comparison(Comparison),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sign {
+    Plus,
+    Minus,
+}
+
+/// A term of an `Operand::Expression`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Term {
+    Number(i32),
+    /// A label, with the span of the reference
+    Label(LabelReference, Range<usize>),
+}
+
+/// A label used as an operand
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LabelReference {
+    /// `main` or `main.loop`
+    Global(String),
+    /// `.loop`, scoped to the previous global label
+    Local(String),
+    /// `1b` or `1f`, the previous or the next declaration of `1:`
+    Numeric { name: String, forward: bool },
+    /// `-`, `--`, `+` or `++`, the `distance`th previous or next anonymous
+    /// label
+    Anonymous { forward: bool, distance: usize },
+}
+
+/// A comment, with its leading `;` or `//`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    pub text: String,
+    pub span: Range<usize>,
+}
+
+/// Walks the syntax tree of a program.
+///
+/// Every method visits the children of its node by default, so an
+/// implementation can override only the nodes it's interested in and call the
+/// `walk_*` function to keep going down
+pub trait Visitor {
+    fn visit_line(&mut self, line: &Line) {
+        walk_line(self, line);
+    }
+
+    fn visit_label(&mut self, _label: &Label) {}
+
+    fn visit_instruction(&mut self, instruction: &Instruction) {
+        for operand in &instruction.operands {
+            self.visit_operand(operand);
+        }
+    }
+
+    fn visit_directive(&mut self, directive: &Directive) {
+        for operand in &directive.operands {
+            self.visit_operand(operand);
+        }
+    }
+
+    fn visit_operand(&mut self, operand: &Operand) {
+        walk_operand(self, operand);
+    }
+
+    fn visit_label_reference(&mut self, _label: &LabelReference, _span: &Range<usize>) {}
+
+    fn visit_comment(&mut self, _comment: &Comment) {}
+}
+
+/// Visit the label, the statement and the comment of the line
+pub fn walk_line<V: Visitor + ?Sized>(visitor: &mut V, line: &Line) {
+    if let Some(label) = &line.label {
+        visitor.visit_label(label);
+    }
+
+    match &line.statement {
+        Some(Statement::Instruction(instruction)) => visitor.visit_instruction(instruction),
+        Some(Statement::Directive(directive)) => visitor.visit_directive(directive),
+        None => {}
+    }
+
+    if let Some(comment) = &line.comment {
+        visitor.visit_comment(comment);
+    }
+}
+
+/// Visit the labels referenced by the operand
+pub fn walk_operand<V: Visitor + ?Sized>(visitor: &mut V, operand: &Operand) {
+    match operand {
+        Operand::Label(label, span) => visitor.visit_label_reference(label, span),
+        Operand::Expression(terms) => {
+            for (_, term) in terms {
+                if let Term::Label(label, span) = term {
+                    visitor.visit_label_reference(label, span);
+                }
+            }
+        }
+        Operand::Register(_) | Operand::Number(_) | Operand::String(_) | Operand::Comparison(_) => {
+        }
+    }
+}
+
+impl Program {
+    /// Visit every line of the program, in order
+    pub fn accept<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+        for line in &self.lines {
+            visitor.visit_line(line);
+        }
+    }
+}
+
+impl Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut printer = Printer::default();
+        printer.program(self);
+        f.write_str(&printer.text)
+    }
+}
+
+impl Display for Line {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut printer = Printer::default();
+        printer.line(self);
+        f.write_str(&printer.text)
+    }
+}
+
+impl Display for Statement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut printer = Printer::default();
+        printer.statement(self);
+        f.write_str(&printer.text)
+    }
+}
+
+impl Display for Label {
+    /// Every label is printed with a colon, since a label that looks like a
+    /// number needs it
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            LabelDeclaration::Global(name) | LabelDeclaration::Numeric(name) => {
+                write!(f, "{name}:")
+            }
+            LabelDeclaration::Local(name) => write!(f, ".{name}:"),
+            LabelDeclaration::Anonymous { forward: true } => f.write_str("+:"),
+            LabelDeclaration::Anonymous { forward: false } => f.write_str("-:"),
+        }
+    }
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut printer = Printer::default();
+        printer.operand(self);
+        f.write_str(&printer.text)
+    }
+}
+
+/// Prints the nodes of a program, remembering which byte range of the printed
+/// assembly every node with a span was printed to
+#[derive(Default)]
+struct Printer {
+    text: String,
+    /// The printed range and the span of every node, the children before their
+    /// parents
+    nodes: Vec<(Range<usize>, Range<usize>)>,
+}
+
+impl Printer {
+    fn node(&mut self, span: &Range<usize>, print: impl FnOnce(&mut Self)) {
+        let start = self.text.len();
+        print(self);
+        self.nodes.push((start..self.text.len(), span.clone()));
+    }
+
+    fn program(&mut self, program: &Program) {
+        for line in &program.lines {
+            self.line(line);
+            self.text.push('\n');
+        }
+    }
+
+    fn line(&mut self, line: &Line) {
+        self.node(&line.span, |printer| {
+            // The parts are separated by a space
+            let start = printer.text.len();
+            let separate = |printer: &mut Self| {
+                if printer.text.len() > start {
+                    printer.text.push(' ');
+                }
+            };
+
+            if let Some(label) = &line.label {
+                printer.node(&label.span, |printer| {
+                    printer.text.push_str(&label.to_string());
+                });
+            }
+            if let Some(statement) = &line.statement {
+                separate(printer);
+                printer.statement(statement);
+            }
+            if let Some(comment) = &line.comment {
+                separate(printer);
+                printer.node(&comment.span, |printer| {
+                    printer.text.push_str(&comment.text);
+                });
+            }
+        });
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        let (mnemonic, operands, span) = match statement {
+            Statement::Instruction(instruction) => (
+                instruction.operation.to_string(),
+                &instruction.operands,
+                &instruction.span,
+            ),
+            Statement::Directive(directive) => (
+                directive.pseudo_operation.to_string(),
+                &directive.operands,
+                &directive.span,
+            ),
+        };
+
+        // The operands are separated by commas, but a comparison is joined to
+        // its operands by spaces
+        self.node(span, |printer| {
+            printer.text.push_str(&mnemonic);
+            let mut separator = " ";
+            for operand in operands {
+                if matches!(operand, Operand::Comparison(_)) {
+                    separator = " ";
+                }
+                printer.text.push_str(separator);
+                printer.operand(operand);
+                separator = if matches!(operand, Operand::Comparison(_)) {
+                    " "
+                } else {
+                    ", "
+                };
+            }
+        });
+    }
+
+    fn operand(&mut self, operand: &Operand) {
+        match operand {
+            // The register is not guaranteed to be `Copy`
+            #[allow(clippy::clone_on_copy)]
+            Operand::Register(register) => {
+                _ = write!(self.text, "R{}", u8::from(register.clone()) & 0b111);
+            }
+            Operand::Number(number) => _ = write!(self.text, "#{number}"),
+            Operand::Label(label, span) => {
+                self.node(span, |printer| printer.text.push_str(&label.to_string()));
+            }
+            Operand::Comparison(comparison) => self.text.push_str(&comparison.to_string()),
+            Operand::String(string) => {
+                self.text.push('"');
+                for part in &string.0 {
+                    let character = match *part {
+                        StringPart::Character(character) => character,
+                        StringPart::Byte(byte) => {
+                            _ = write!(self.text, "\\x{byte:02X}");
+                            continue;
+                        }
+                    };
+                    match character {
+                        '"' => self.text.push_str("\\\""),
+                        '\\' => self.text.push_str("\\\\"),
+                        '\n' => self.text.push_str("\\n"),
+                        '\r' => self.text.push_str("\\r"),
+                        '\t' => self.text.push_str("\\t"),
+                        character if character.is_control() => {
+                            _ = write!(self.text, "\\u{{{:x}}}", u32::from(character));
+                        }
+                        character => self.text.push(character),
+                    }
+                }
+                self.text.push('"');
+            }
+            Operand::Expression(terms) => {
+                for (index, (sign, term)) in terms.iter().enumerate() {
+                    match (index, sign) {
+                        (0, Sign::Plus) => {}
+                        (0, Sign::Minus) => self.text.push_str("#0 - "),
+                        (_, Sign::Plus) => self.text.push_str(" + "),
+                        (_, Sign::Minus) => self.text.push_str(" - "),
+                    }
+
+                    match term {
+                        Term::Number(number) => _ = write!(self.text, "#{number}"),
+                        Term::Label(label, span) => {
+                            self.node(span, |printer| printer.text.push_str(&label.to_string()));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Maps the byte ranges of a printed program back to the spans of its nodes
+pub(crate) struct SourceMap {
+    nodes: Vec<(Range<usize>, Range<usize>)>,
+}
+
+impl SourceMap {
+    /// The span of the smallest parsed node that contains `printed`. A range
+    /// outside every parsed node, like the end of the assembly, is mapped to
+    /// the end of the last node before it
+    pub(crate) fn span(&self, printed: &Range<usize>) -> Range<usize> {
+        // The nodes created by hand have no source to point to
+        let parsed = self.nodes.iter().filter(|(_, span)| !span.is_empty());
+
+        let containing = parsed
+            .clone()
+            .filter(|(range, _)| range.start <= printed.start && printed.end <= range.end)
+            .min_by_key(|(range, _)| range.len());
+        if let Some((_, span)) = containing {
+            return span.clone();
+        }
+
+        let end = parsed
+            .filter(|(range, _)| range.end <= printed.start)
+            .max_by_key(|(range, _)| range.end)
+            .map_or(0, |(_, span)| span.end);
+        end..end
+    }
+}
+
+/// Print a program back into an assembly, with the map from the printed byte
+/// ranges to the spans of the nodes
+pub(crate) fn print(program: &Program) -> (String, SourceMap) {
+    let mut printer = Printer::default();
+    printer.program(program);
+    (
+        printer.text,
+        SourceMap {
+            nodes: printer.nodes,
+        },
+    )
+}
+
+impl Display for LabelReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Global(name) => f.write_str(name),
+            Self::Local(name) => write!(f, ".{name}"),
+            Self::Numeric { name, forward } => {
+                write!(f, "{name}{}", if *forward { 'f' } else { 'b' })
+            }
+            Self::Anonymous { forward, distance } => {
+                f.write_str(&(if *forward { "+" } else { "-" }).repeat(*distance))
+            }
+        }
+    }
+}
+
+type Spanned = (Token, Range<usize>);
+
+/// Parse an LC2 assembly into its syntax tree.
+///
+/// Only the syntax is checked, so the labels don't need to be declared and the
+/// operands don't need to match their operation. Unlike the assembler, the
+/// operands must be on the same line as their operation
+///
+/// # Errors
+///
+/// This function returns an error if the assembly contains a token that is not
+/// valid or that is out of place
+pub fn parse(assembly: &str) -> Result<Program, Diagnostic> {
+    let mut lexer = TokenStream::new(assembly, true);
+    let mut program = Program::default();
+
+    // Split the tokens into lines
+    let mut line = Vec::new();
+    let mut start = 0;
+    while let Some(token) = lexer.next() {
+        match token.map_err(|error| Diagnostic::new(assembly, error, lexer.span()))? {
+            Token::Newline => {
+                parse_line(assembly, &mut program, std::mem::take(&mut line), start)
+                    .map_err(|(error, span)| Diagnostic::new(assembly, error, span))?;
+                start = lexer.span().end;
+            }
+            token => line.push((token, lexer.span())),
+        }
+    }
+
+    // The last line is kept only if it's not empty
+    if !line.is_empty() {
+        parse_line(assembly, &mut program, line, start)
+            .map_err(|(error, span)| Diagnostic::new(assembly, error, span))?;
+    }
+
+    Ok(program)
+}
+
+/// Parse the tokens of a line, that starts at `start`, into one or more lines
+fn parse_line(
+    assembly: &str,
+    program: &mut Program,
+    mut tokens: Vec<Spanned>,
+    start: usize,
+) -> Result<(), (ParseError, Range<usize>)> {
+    let span = tokens
+        .first()
+        .zip(tokens.last())
+        .map_or(start..start, |((_, first), (_, last))| {
+            first.start..last.end
+        });
+
+    // The comment is always the last token of a line
+    let comment = match tokens.last() {
+        Some((Token::Comment(_), _)) => tokens.pop().and_then(|(token, span)| match token {
+            Token::Comment(text) => Some(Comment { text, span }),
+            _ => None,
+        }),
+        _ => None,
+    };
+    let mut tokens = tokens.into_iter().peekable();
+
+    // A label at the start of the line is always a declaration
+    let label = match tokens.next_if(|(token, _)| is_label(token)) {
+        Some((token, mut label_span)) => {
+            let kind = match token {
+                Token::Label(name) => LabelDeclaration::Global(name),
+                Token::LocalLabel(name) => LabelDeclaration::Local(name),
+                Token::NumericLabel(name) => LabelDeclaration::Numeric(name),
+                Token::RelativeLabel(RelativeLabel {
+                    forward,
+                    distance: 1,
+                    name,
+                }) if name == "+" || name == "-" => LabelDeclaration::Anonymous { forward },
+                _ => return Err((ParseError::UnexpectedToken, label_span)),
+            };
+
+            // Include the trailing colon, if there is one
+            if let Some((_, colon)) = tokens.next_if(|(token, _)| *token == Token::Colon) {
+                label_span.end = colon.end;
+            }
+
+            Some(Label {
+                kind,
+                span: label_span,
+            })
+        }
+        None => None,
+    };
+
+    // Every operation or pseudo-operation starts a new statement
+    let mut statements = Vec::new();
+    while let Some((token, mnemonic)) = tokens.next() {
+        let mut operands = VecDeque::new();
+        while let Some(operand) = tokens
+            .next_if(|(token, _)| !matches!(token, Token::Operation(_) | Token::PseudoOperation(_)))
+        {
+            operands.push_back(operand);
+        }
+        let end = operands.back().map_or(mnemonic.end, |(_, span)| span.end);
+        let span = mnemonic.start..end;
+        let operands = parse_operands(assembly, operands)?;
+
+        statements.push(match token {
+            Token::Operation(operation) => Statement::Instruction(Instruction {
+                operation,
+                operands,
+                span,
+            }),
+            Token::PseudoOperation(pseudo_operation) => Statement::Directive(Directive {
+                pseudo_operation,
+                operands,
+                span,
+            }),
+            _ => return Err((ParseError::UnexpectedToken, mnemonic)),
+        });
+    }
+
+    // The label goes on the first line and the comment on the last one
+    let count = statements.len().max(1);
+    let mut statements = statements.into_iter();
+    let mut label = label;
+    let mut comment = comment;
+    for index in 0..count {
+        program.lines.push(Line {
+            label: label.take(),
+            statement: statements.next(),
+            comment: if index + 1 == count {
+                comment.take()
+            } else {
+                None
+            },
+            span: span.clone(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Parse the tokens that follow an operation or a pseudo-operation. The commas
+/// between the operands are optional, like for the assembler
+fn parse_operands(
+    assembly: &str,
+    mut tokens: VecDeque<Spanned>,
+) -> Result<Vec<Operand>, (ParseError, Range<usize>)> {
+    let mut operands = Vec::new();
+
+    while let Some((token, span)) = tokens.pop_front() {
+        let operand = match token {
+            Token::Comma => continue,
+            Token::Register(register) => Operand::Register(register),
+            Token::String(string) => Operand::String(string),
+            Token::Comparison(comparison) => Operand::Comparison(comparison),
+            token => {
+                let anonymous = is_anonymous(&token);
+                let Some(first) = term(assembly, token, &span) else {
+                    return Err((ParseError::UnexpectedToken, span));
+                };
+
+                // Keep adding the terms joined by a `+` or a `-` (that are
+                // lexed as anonymous labels), or by a negative number right
+                // after the last term. An anonymous label can't be a term
+                let mut terms = vec![(Sign::Plus, first)];
+                let mut end = span.end;
+                loop {
+                    let sign = match (tokens.front(), tokens.get(1)) {
+                        _ if anonymous => break,
+                        (Some((Token::RelativeLabel(sign), _)), Some((next, _)))
+                            if sign.is_anonymous() && sign.distance == 1 && !is_anonymous(next) =>
+                        {
+                            let sign = if sign.forward {
+                                Sign::Plus
+                            } else {
+                                Sign::Minus
+                            };
+                            tokens.pop_front();
+                            sign
+                        }
+                        (Some((Token::Number(_), next)), _)
+                            if next.start == end && is_negative(&assembly[next.clone()]) =>
+                        {
+                            Sign::Plus
+                        }
+                        _ => break,
+                    };
+
+                    let Some((token, span)) = tokens.pop_front() else {
+                        break;
+                    };
+                    let Some(term) = term(assembly, token, &span) else {
+                        return Err((ParseError::UnexpectedToken, span));
+                    };
+                    terms.push((sign, term));
+                    end = span.end;
+                }
+
+                match <[(Sign, Term); 1]>::try_from(terms) {
+                    Ok([(_, Term::Number(number))]) => Operand::Number(number),
+                    Ok([(_, Term::Label(label, span))]) => Operand::Label(label, span),
+                    Err(terms) => Operand::Expression(terms),
+                }
+            }
+        };
+
+        operands.push(operand);
+    }
+
+    Ok(operands)
+}
+
+/// Return `true` if the token can be a label declaration
+const fn is_label(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Label(_) | Token::LocalLabel(_) | Token::NumericLabel(_) | Token::RelativeLabel(_)
+    )
+}
+
+/// Return `true` if the token is an anonymous label, like `-` or `++`
+fn is_anonymous(token: &Token) -> bool {
+    matches!(token, Token::RelativeLabel(label) if label.is_anonymous())
+}
+
+/// Return `true` if the number literal is written with a minus sign
+fn is_negative(literal: &str) -> bool {
+    literal.trim_start_matches('#').starts_with('-')
+}
+
+/// Convert a number or a label into a term, using the source of the number to
+/// get its sign
+fn term(assembly: &str, token: Token, span: &Range<usize>) -> Option<Term> {
+    Some(match token {
+        Token::Number(number) if is_negative(&assembly[span.clone()]) => {
+            Term::Number(-i32::from(number.wrapping_neg()))
+        }
+        Token::Number(number) => Term::Number(i32::from(number)),
+        Token::Label(name) => Term::Label(LabelReference::Global(name), span.clone()),
+        Token::LocalLabel(name) => Term::Label(LabelReference::Local(name), span.clone()),
+        Token::RelativeLabel(label) if label.is_anonymous() => Term::Label(
+            LabelReference::Anonymous {
+                forward: label.forward,
+                distance: label.distance,
+            },
+            span.clone(),
+        ),
+        Token::RelativeLabel(label) => Term::Label(
+            LabelReference::Numeric {
+                name: label.name,
+                forward: label.forward,
+            },
+            span.clone(),
+        ),
+        _ => return None,
+    })
+}
@@ -0,0 +1,175 @@
+use super::analysis::ControlFlowGraph;
+use std::{collections::HashMap, fmt::Write};
+
+/// A word of a disassembled program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisassembledWord {
+    pub address: u16,
+    pub word: u16,
+    /// The label declared at the address, if any
+    pub label: Option<String>,
+    /// The operation or the `.FILL` directive, that assembles back into the
+    /// same word
+    pub instruction: String,
+    /// The address referenced by a `pgoffset9` operand, if any
+    pub target: Option<u16>,
+}
+
+/// Disassemble the `words` loaded at `origin`.
+///
+/// Only the words that can be reached by the execution starting from the
+/// `entry_point` (or from the `origin`) are decoded as operations, while the
+/// other ones become `.FILL` directives. The `pgoffset9` operands are replaced
+/// by the labels of the symbol table, if there are any at their address
+#[must_use]
+#[allow(clippy::implicit_hasher)]
+pub fn disassemble(
+    words: &[u16],
+    origin: u16,
+    symbol_table: &HashMap<String, u16>,
+    entry_point: Option<u16>,
+) -> Vec<DisassembledWord> {
+    let graph = ControlFlowGraph::new(words, origin, symbol_table, entry_point);
+
+    // Use the first name of every address, skipping the numeric and the
+    // anonymous labels that can't be referenced by name
+    let mut labels: HashMap<u16, &str> = HashMap::new();
+    for (name, &address) in symbol_table {
+        if !name.contains('@') {
+            labels
+                .entry(address)
+                .and_modify(|label| *label = (*label).min(name.as_str()))
+                .or_insert(name);
+        }
+    }
+
+    (origin..)
+        .zip(words)
+        .map(|(address, &word)| {
+            let operation = graph
+                .is_reachable(address)
+                .then(|| decode(word, address))
+                .flatten();
+
+            let (instruction, target) = match operation {
+                Some((mut instruction, target)) => {
+                    if let Some(target) = target {
+                        match labels.get(&target) {
+                            Some(label) => instruction.push_str(label),
+                            None => write!(instruction, "0x{:03X}", target & 0x1ff).unwrap(),
+                        }
+                    }
+                    (instruction, target)
+                }
+                None => (format!(".FILL x{word:04X}"), None),
+            };
+
+            DisassembledWord {
+                address,
+                word,
+                label: labels.get(&address).map(|&label| label.to_owned()),
+                instruction,
+                target,
+            }
+        })
+        .collect()
+}
+
+/// Disassemble a single operation, with its `pgoffset9` operand as a number.
+/// Return `None` if the word is not a valid operation
+#[must_use]
+pub fn disassemble_word(word: u16, address: u16) -> Option<String> {
+    decode(word, address).map(|(mut instruction, target)| {
+        if let Some(target) = target {
+            write!(instruction, "0x{:03X}", target & 0x1ff).unwrap();
+        }
+        instruction
+    })
+}
+
+/// Decode an operation into its mnemonic and its operands, without the
+/// `pgoffset9` one whose address is returned instead
+fn decode(word: u16, address: u16) -> Option<(String, Option<u16>)> {
+    let register = |shift: u16| (word >> shift) & 0b111;
+    let destination = register(9);
+    let source = register(6);
+    let index6 = word & 0b11_1111;
+    let target = (address & 0xfe00) | (word & 0x1ff);
+
+    let operation = match word >> 12 {
+        opcode @ (0b0001 | 0b0101) => {
+            let mnemonic = if opcode == 0b0001 { "ADD" } else { "AND" };
+
+            // An immediate value or a register with the unused bits cleared
+            let operand = if word & 0b10_0000 != 0 {
+                let immediate = i16::from_ne_bytes(((word & 0b1_1111) << 11).to_ne_bytes()) >> 11;
+                format!("#{immediate}")
+            } else if word & 0b1_1000 == 0 {
+                format!("R{}", register(0))
+            } else {
+                return None;
+            };
+
+            format!("{mnemonic} R{destination}, R{source}, {operand}")
+        }
+
+        0b0000 => {
+            let conditions: String = [(11, 'n'), (10, 'z'), (9, 'p')]
+                .into_iter()
+                .filter(|(bit, _)| word & (1 << bit) != 0)
+                .map(|(_, flag)| flag)
+                .collect();
+
+            // A branch without conditions never jumps, but it keeps its
+            // operand to assemble back into the same word
+            if conditions.is_empty() {
+                return Some(("NOP ".to_owned(), Some(target)));
+            }
+            return Some((format!("BR{conditions} "), Some(target)));
+        }
+
+        0b0100 if word & 0x0600 == 0 => {
+            let mnemonic = if word & 0x0800 != 0 { "JSR" } else { "JMP" };
+            return Some((format!("{mnemonic} "), Some(target)));
+        }
+
+        0b1100 if word & 0x0600 == 0 => {
+            let mnemonic = if word & 0x0800 != 0 { "JSRR" } else { "JMPR" };
+            format!("{mnemonic} R{source}, #{index6}")
+        }
+
+        opcode @ (0b0010 | 0b1010 | 0b1110 | 0b0011 | 0b1011) => {
+            let mnemonic = match opcode {
+                0b0010 => "LD",
+                0b1010 => "LDI",
+                0b1110 => "LEA",
+                0b0011 => "ST",
+                _ => "STI",
+            };
+            return Some((format!("{mnemonic} R{destination}, "), Some(target)));
+        }
+
+        opcode @ (0b0110 | 0b0111) => {
+            let mnemonic = if opcode == 0b0110 { "LDR" } else { "STR" };
+            format!("{mnemonic} R{destination}, R{source}, #{index6}")
+        }
+
+        0b1001 if index6 == 0b11_1111 => format!("NOT R{destination}, R{source}"),
+        0b1101 if word.trailing_zeros() >= 12 => "RET".to_owned(),
+        0b1000 if word.trailing_zeros() >= 12 => "RTI".to_owned(),
+
+        0b1111 if word & 0x0f00 == 0 => match word & 0xff {
+            0x20 => "GETC".to_owned(),
+            0x21 => "OUT".to_owned(),
+            0x22 => "PUTS".to_owned(),
+            0x23 => "IN".to_owned(),
+            0x24 => "PUTSP".to_owned(),
+            0x25 => "HALT".to_owned(),
+            vector => format!("TRAP 0x{vector:02X}"),
+        },
+
+        _ => return None,
+    };
+
+    Some((operation, None))
+}
@@ -0,0 +1,152 @@
+use super::lexer::{StringLiteral, StringPart};
+use crate::ParseError;
+
+/// How the characters of the string pseudo-operations are converted into
+/// words
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringEncoding {
+    /// One ASCII character per word, returning an error for any other
+    /// character
+    Ascii,
+    /// One ISO-8859-1 character per word, returning an error for any other
+    /// character
+    Latin1,
+    /// One UTF-8 byte per word, so a non-ASCII character takes more than one
+    /// word
+    Utf8,
+    /// One UTF-16 code unit per word, so a character outside of the Basic
+    /// Multilingual Plane takes two words
+    Utf16,
+}
+
+impl StringEncoding {
+    /// Convert the string into its code units
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if a character can't be represented in the
+    /// encoding
+    pub fn encode(self, string: &str) -> Result<Vec<u16>, ParseError> {
+        Ok(match self {
+            Self::Ascii | Self::Latin1 => {
+                let max = if self == Self::Ascii { 0x7f } else { 0xff };
+
+                string
+                    .chars()
+                    .map(|character| {
+                        u16::try_from(u32::from(character))
+                            .ok()
+                            .filter(|&code| code <= max)
+                            .ok_or(ParseError::CharacterNotEncodable)
+                    })
+                    .collect::<Result<_, _>>()?
+            }
+            Self::Utf8 => string.bytes().map(u16::from).collect(),
+            Self::Utf16 => string.encode_utf16().collect(),
+        })
+    }
+
+    /// Convert the string literal into its code units. The bytes written as
+    /// escape sequences (like `\xFF`) aren't converted, they are a code unit
+    /// each in any encoding
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if a character can't be represented in the
+    /// encoding
+    pub fn encode_literal(self, literal: &StringLiteral) -> Result<Vec<u16>, ParseError> {
+        let mut words = Vec::with_capacity(literal.0.len());
+        for part in &literal.0 {
+            match *part {
+                StringPart::Character(character) => {
+                    words.extend(self.encode(character.encode_utf8(&mut [0; 4]))?);
+                }
+                StringPart::Byte(byte) => words.push(u16::from(byte)),
+            }
+        }
+
+        Ok(words)
+    }
+
+    /// Convert the string literal into bytes, for the packed strings
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if a character can't be represented in the
+    /// encoding or if the encoding doesn't use bytes
+    pub fn encode_bytes(self, literal: &StringLiteral) -> Result<Vec<u8>, ParseError> {
+        if self == Self::Utf16 {
+            return Err(ParseError::EncodingNotPackable);
+        }
+
+        Ok(self
+            .encode_literal(literal)?
+            .into_iter()
+            .map(|code| code.to_le_bytes()[0])
+            .collect())
+    }
+}
+
+impl TryFrom<&str> for StringEncoding {
+    type Error = ParseError;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        Ok(match name.to_uppercase().as_str() {
+            "ASCII" => Self::Ascii,
+            "LATIN1" => Self::Latin1,
+            "UTF8" => Self::Utf8,
+            "UTF16" => Self::Utf16,
+
+            _ => return Err(ParseError::UnknownEncoding),
+        })
+    }
+}
+
+/// How the bytes of a binary file are converted into words
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BytePacking {
+    /// Two bytes per word, the first one in the high byte
+    BigEndian,
+    /// Two bytes per word, the first one in the low byte (like the packed
+    /// strings)
+    LittleEndian,
+    /// One byte per word
+    OnePerWord,
+}
+
+impl BytePacking {
+    /// Convert the bytes into words. If the bytes are odd and they're packed
+    /// two per word, the last word is padded with a null byte
+    #[must_use]
+    pub fn pack(self, bytes: &[u8]) -> Vec<u16> {
+        match self {
+            Self::BigEndian | Self::LittleEndian => bytes
+                .chunks(2)
+                .map(|bytes| {
+                    let pair = [bytes[0], bytes.get(1).copied().unwrap_or(0)];
+
+                    if self == Self::BigEndian {
+                        u16::from_be_bytes(pair)
+                    } else {
+                        u16::from_le_bytes(pair)
+                    }
+                })
+                .collect(),
+            Self::OnePerWord => bytes.iter().copied().map(u16::from).collect(),
+        }
+    }
+}
+
+impl TryFrom<&str> for BytePacking {
+    type Error = ParseError;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        Ok(match name.to_uppercase().replace('-', "_").as_str() {
+            "BIG_ENDIAN" => Self::BigEndian,
+            "LITTLE_ENDIAN" => Self::LittleEndian,
+            "ONE_PER_WORD" => Self::OnePerWord,
+
+            _ => return Err(ParseError::UnknownBytePacking),
+        })
+    }
+}
@@ -0,0 +1,315 @@
+use super::lexer::{parse_number_literal, Token, TokenStream};
+use crate::Diagnostic;
+use std::ops::Range;
+
+/// Re-prints an LC2 assembly with its labels, mnemonics, operands and comments
+/// aligned into columns.
+///
+/// Only the whitespace, the case of the mnemonics and of the registers, the
+/// prefixes of the number literals and the colons after the labels are
+/// changed: every statement stays on its line and the comments are kept as
+/// they are, so the formatted assembly assembles into the same binary.
+/// Formatting an already formatted assembly doesn't change it
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, derive_builder::Builder)]
+#[builder(build_fn(private, name = "fallible_build"))]
+pub struct Lc2Formatter {
+    #[builder(default = "8")]
+    mnemonic_column: usize,
+
+    #[builder(default = "16")]
+    operand_column: usize,
+
+    #[builder(default = "40")]
+    comment_column: usize,
+
+    #[builder(default = "Case::Upper")]
+    mnemonic_case: Case,
+
+    #[builder(default = "Case::Upper")]
+    register_case: Case,
+
+    #[builder(default = "Case::Upper")]
+    hex_digit_case: Case,
+
+    #[builder(default = "HexPrefix::X")]
+    hex_prefix: HexPrefix,
+
+    #[builder(default = "DecimalPrefix::Hash")]
+    decimal_prefix: DecimalPrefix,
+
+    #[builder(default = "BinaryPrefix::B")]
+    binary_prefix: BinaryPrefix,
+
+    #[builder(default = "false")]
+    label_colons: bool,
+}
+
+// https://github.com/colin-kiegel/rust-derive-builder/issues/56#issuecomment-1043671602
+impl Lc2FormatterBuilder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Panics
+    ///
+    /// This method panics if if any fields have been added to `Lc2Formatter`
+    /// that lack defaults
+    #[must_use]
+    pub fn build(&mut self) -> Lc2Formatter {
+        self.fallible_build()
+            .expect("All required fields set at initialization")
+    }
+}
+
+/// The case of the mnemonics, of the registers or of the hexadecimal digits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    Upper,
+    Lower,
+}
+
+impl Case {
+    fn apply(self, string: &str) -> String {
+        match self {
+            Self::Upper => string.to_uppercase(),
+            Self::Lower => string.to_lowercase(),
+        }
+    }
+}
+
+/// The prefix of the hexadecimal number literals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexPrefix {
+    /// `x3000`
+    X,
+    /// `0x3000`
+    ZeroX,
+    /// `$3000`
+    Dollar,
+}
+
+/// The prefix of the decimal number literals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecimalPrefix {
+    /// `10`
+    None,
+    /// `#10`
+    Hash,
+}
+
+/// The prefix of the binary number literals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryPrefix {
+    /// `b1010`
+    B,
+    /// `0b1010`
+    ZeroB,
+    /// `%1010`
+    Percent,
+}
+
+/// A token with its span and with `true` if it's in the position of a label
+/// operand, as tracked by the lexer
+type Spanned = (Token, Range<usize>, bool);
+
+impl Lc2Formatter {
+    /// Format the assembly, ending it with a single newline
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the assembly contains a token that is
+    /// not valid
+    pub fn format(&self, assembly: &str) -> Result<String, Diagnostic> {
+        // Split the tokens into lines
+        let mut lexer = TokenStream::new(assembly, true);
+        let mut lines = Vec::new();
+        let mut line = Vec::new();
+        while let Some(token) = lexer.next() {
+            match token.map_err(|error| Diagnostic::new(assembly, error, lexer.span()))? {
+                Token::Newline => lines.push(std::mem::take(&mut line)),
+                token => line.push((token, lexer.span(), lexer.at_label_operand())),
+            }
+        }
+        lines.push(line);
+
+        // Print every line, removing the empty ones at the end
+        let mut formatted: Vec<String> = lines
+            .iter()
+            .map(|line| self.format_line(assembly, line))
+            .collect();
+        while formatted.last().is_some_and(String::is_empty) {
+            formatted.pop();
+        }
+
+        let mut formatted = formatted.join("\n");
+        formatted.push('\n');
+        Ok(formatted)
+    }
+
+    /// Format the tokens of a single line
+    fn format_line(&self, assembly: &str, mut tokens: &[Spanned]) -> String {
+        let mut line = String::new();
+
+        // The comment is always the last token of a line
+        let comment = match tokens {
+            [rest @ .., (Token::Comment(comment), span, _)] => {
+                tokens = rest;
+                Some((comment, span.start))
+            }
+            _ => None,
+        };
+
+        // A label at the start of the line is a declaration if it's followed by
+        // a colon, by a mnemonic or by nothing at all
+        if let [(
+            label @ (Token::Label(_)
+            | Token::LocalLabel(_)
+            | Token::NumericLabel(_)
+            | Token::RelativeLabel(_)),
+            span,
+            _,
+        ), rest @ ..] = tokens
+        {
+            let colon = matches!(rest.first(), Some((Token::Colon, ..)));
+            let rest = if colon { &rest[1..] } else { rest };
+
+            if colon
+                || matches!(
+                    rest.first(),
+                    None | Some((Token::Operation(_) | Token::PseudoOperation(_), ..))
+                )
+            {
+                line.push_str(&assembly[span.clone()]);
+
+                // The numeric labels include their colon, while a label that
+                // looks like a number needs one if nothing follows it
+                let name = &assembly[span.clone()];
+                if !matches!(label, Token::NumericLabel(_))
+                    && (self.label_colons
+                        || (rest.is_empty() && parse_number_literal(name).is_some()))
+                {
+                    line.push(':');
+                }
+
+                tokens = rest;
+            }
+        }
+
+        // The mnemonic, if any
+        if let [(Token::Operation(_) | Token::PseudoOperation(_), span, _), rest @ ..] = tokens {
+            pad(&mut line, self.mnemonic_column);
+            line.push_str(&self.mnemonic_case.apply(&assembly[span.clone()]));
+            tokens = rest;
+        }
+
+        // The operands, separated by a space after the commas. The tokens that
+        // were adjacent (like `end-1`) are kept together, since splitting them
+        // would change their meaning
+        if !tokens.is_empty() {
+            pad(&mut line, self.operand_column);
+        }
+        let mut previous: Option<&Spanned> = None;
+        for spanned @ (token, span, label_operand) in tokens {
+            let adjacent = previous.is_some_and(|(previous, previous_span, _)| {
+                *previous != Token::Comma && previous_span.end == span.start
+            });
+            match previous {
+                Some((Token::Comma, ..)) => line.push(' '),
+                Some(_) if !adjacent && *token != Token::Comma => line.push(' '),
+                _ => {}
+            }
+
+            let slice = &assembly[span.clone()];
+            match token {
+                Token::Operation(_) | Token::PseudoOperation(_) => {
+                    line.push_str(&self.mnemonic_case.apply(slice));
+                }
+                Token::Register(_) => line.push_str(&self.register_case.apply(slice)),
+                Token::Number(_) => line.push_str(&self.number(slice, *label_operand, adjacent)),
+                _ => line.push_str(slice),
+            }
+
+            previous = Some(spanned);
+        }
+
+        // The comment is aligned if it follows some code or if it was indented,
+        // otherwise it stays at the start of the line
+        if let Some((comment, start)) = comment {
+            if !line.is_empty() {
+                pad(&mut line, self.comment_column);
+            } else if !assembly[..start].ends_with('\n') && start != 0 {
+                pad(&mut line, self.mnemonic_column);
+            }
+            line.push_str(comment);
+        }
+
+        line
+    }
+
+    /// Print the number literal with the configured prefix. The character
+    /// literals are kept as they are
+    fn number(&self, slice: &str, label_operand: bool, adjacent: bool) -> String {
+        if slice.contains('\'') {
+            return slice.to_owned();
+        }
+
+        // Split the literal into its sign, its base and its digits
+        let unsigned = slice.trim_start_matches('#');
+        let (sign, unsigned) = unsigned
+            .strip_prefix('-')
+            .map_or(("", unsigned), |unsigned| ("-", unsigned));
+        let lowercase = unsigned.to_lowercase();
+
+        let hex = ["0x", "x", "$"]
+            .into_iter()
+            .find_map(|prefix| lowercase.strip_prefix(prefix));
+        let binary = ["0b", "b", "%"]
+            .into_iter()
+            .find_map(|prefix| lowercase.strip_prefix(prefix));
+
+        // A literal like `x10` or `b10` used where the lexer expects a label
+        // would be read as one, so it gets the `0x` or `0b` prefix instead
+        match (hex, binary) {
+            (Some(digits), _) => {
+                let prefix = match self.hex_prefix {
+                    HexPrefix::X if label_operand => "0x",
+                    HexPrefix::X => "x",
+                    HexPrefix::ZeroX => "0x",
+                    HexPrefix::Dollar => "$",
+                };
+                format!("{prefix}{}", self.hex_digit_case.apply(digits))
+            }
+            (None, Some(digits)) => {
+                let prefix = match self.binary_prefix {
+                    BinaryPrefix::B if label_operand => "0b",
+                    BinaryPrefix::B => "b",
+                    BinaryPrefix::ZeroB => "0b",
+                    BinaryPrefix::Percent => "%",
+                };
+                format!("{prefix}{digits}")
+            }
+            (None, None) => {
+                // A negative number right after a term (like `3-1`) is kept
+                // without the hash, that would look out of place
+                let prefix = match self.decimal_prefix {
+                    DecimalPrefix::Hash if !adjacent => "#",
+                    DecimalPrefix::Hash | DecimalPrefix::None => "",
+                };
+                format!("{prefix}{sign}{unsigned}")
+            }
+        }
+    }
+}
+
+/// Pad the line with spaces up to the column, leaving at least one space after
+/// the existing text
+fn pad(line: &mut String, column: usize) {
+    let width = line.chars().count();
+    if width > 0 && width >= column {
+        line.push(' ');
+    }
+
+    line.push_str(&" ".repeat(column.saturating_sub(width)));
+}
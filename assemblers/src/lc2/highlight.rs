@@ -0,0 +1,145 @@
+//! Syntax highlighting of the LC2 assembly.
+//!
+//! `classify` splits an assembly into highlighted tokens, while the
+//! `TextMate` grammar and the tree-sitter highlight query are generated from
+//! the regexes of the lexer, so the editors highlight the tokens the same way
+//! the assembler reads them.
+
+pub use super::lexer::{classify, ClassifiedToken, TokenKind};
+use std::fmt::Write;
+
+/// The classes in the order the `TextMate` grammar tries them: where many
+/// patterns match at the same position, the first one wins
+const TEXTMATE_ORDER: [TokenKind; 8] = [
+    TokenKind::Comment,
+    TokenKind::String,
+    TokenKind::LabelDefinition,
+    TokenKind::Directive,
+    TokenKind::Mnemonic,
+    TokenKind::Register,
+    TokenKind::Number,
+    TokenKind::LabelReference,
+];
+
+/// The `TextMate` scope of a class
+const fn scope(kind: TokenKind) -> &'static str {
+    match kind {
+        TokenKind::Mnemonic => "keyword.other.mnemonic.lc2",
+        TokenKind::Register => "variable.language.register.lc2",
+        TokenKind::Directive => "keyword.control.directive.lc2",
+        TokenKind::LabelDefinition => "entity.name.label.lc2",
+        TokenKind::LabelReference => "variable.other.label.lc2",
+        TokenKind::Number => "constant.numeric.lc2",
+        TokenKind::String => "string.quoted.double.lc2",
+        TokenKind::Comment => "comment.line.lc2",
+    }
+}
+
+/// The tree-sitter capture of a class
+const fn capture(kind: TokenKind) -> &'static str {
+    match kind {
+        TokenKind::Mnemonic => "keyword",
+        TokenKind::Register => "variable.builtin",
+        TokenKind::Directive => "keyword.directive",
+        TokenKind::LabelDefinition | TokenKind::LabelReference => "label",
+        TokenKind::Number => "number",
+        TokenKind::String => "string",
+        TokenKind::Comment => "comment",
+    }
+}
+
+/// Join the patterns of a class into a single regex
+fn alternation(kind: TokenKind) -> String {
+    kind.patterns()
+        .iter()
+        .map(|pattern| format!("(?:{pattern})"))
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+/// The regex matching the tokens of a class anywhere in a line.
+///
+/// The lexer always takes the longest token, while a `TextMate` grammar
+/// takes the first pattern that matches, so the tokens are required not to be part of
+/// a longer word (like `ADD` in `ADDRESS`)
+fn textmate_regex(kind: TokenKind) -> String {
+    match kind {
+        TokenKind::Comment | TokenKind::String => alternation(kind),
+
+        // A label definition is the first token of its line, and it can't be
+        // named after a reserved word
+        TokenKind::LabelDefinition => {
+            let reserved = [
+                TokenKind::Mnemonic,
+                TokenKind::Register,
+                TokenKind::Directive,
+            ]
+            .map(alternation)
+            .join("|");
+            format!(
+                r"^[^\S\n]*((?!(?:{reserved})(?![\w.]))(?:{}))(?![\w.])",
+                alternation(kind)
+            )
+        }
+
+        kind => format!(r"(?<![\w.])(?:{})(?![\w.])", alternation(kind)),
+    }
+}
+
+/// Escape a string to be put into a JSON document
+fn json_string(string: &str) -> String {
+    let mut escaped = String::from("\"");
+    for character in string.chars() {
+        match character {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            character => escaped.push(character),
+        }
+    }
+    escaped.push('"');
+
+    escaped
+}
+
+/// Generate a `TextMate` grammar for the LC2 assembly, in JSON, with the
+/// `source.lc2` scope
+#[must_use]
+pub fn textmate_grammar() -> String {
+    let patterns = TEXTMATE_ORDER
+        .iter()
+        .map(|&kind| {
+            let regex = json_string(&textmate_regex(kind));
+            let name = json_string(scope(kind));
+
+            // Only the label is highlighted, not the indentation before it
+            if kind == TokenKind::LabelDefinition {
+                format!(
+                    "    {{ \"match\": {regex}, \"captures\": {{ \"1\": {{ \"name\": {name} }} }} }}"
+                )
+            } else {
+                format!("    {{ \"match\": {regex}, \"name\": {name} }}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",\n");
+
+    format!(
+        "{{\n  \"name\": \"LC2 Assembly\",\n  \"scopeName\": \"source.lc2\",\n  \"fileTypes\": [\"asm\", \"lc2\"],\n  \"patterns\": [\n{patterns}\n  ]\n}}\n"
+    )
+}
+
+/// Generate a tree-sitter highlight query (`highlights.scm`).
+///
+/// The query expects the nodes of the grammar to be named after the classes,
+/// like `(label_definition)`, and their tokens to be defined by
+/// `TokenKind::patterns()`
+#[must_use]
+pub fn tree_sitter_query() -> String {
+    TokenKind::ALL
+        .iter()
+        .fold(String::new(), |mut query, &kind| {
+            let _ = writeln!(query, "({}) @{}", kind.name(), capture(kind));
+            query
+        })
+}
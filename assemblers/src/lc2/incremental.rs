@@ -0,0 +1,266 @@
+use super::{
+    lexer::{LexedLine, Operation, Token, TokenStream},
+    Lc2Assembler, Lc2Output,
+};
+use crate::Diagnostic;
+use std::{collections::HashMap, ops::Range};
+
+/// A change of the source: the byte range to replace and its replacement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+/// What the last assembly reused from the previous one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Reuse {
+    /// The number of lines that were lexed again
+    pub relexed_lines: usize,
+    /// `true` if the previous symbol table was reused, skipping the first pass
+    pub symbol_table: bool,
+}
+
+/// An assembly that is assembled again after every change of its source, like
+/// an open document of an editor.
+///
+/// The tokens of every line are cached, so an edit lexes again only the lines
+/// it touches, and the assembler reads the cached tokens in place. The tokens
+/// go through the same passes and lints of
+/// `Lc2Assembler::assemble_with_diagnostic()`, so the output is always the
+/// same one. The only difference is that, with two passes, if the edits
+/// changed only the operands of some operations the labels can't have moved,
+/// and the previous symbol table is reused, skipping the first pass
+pub struct IncrementalAssembler {
+    assembler: Lc2Assembler,
+    source: String,
+    lines: Vec<LexedLine>,
+    /// The symbol table built by the first pass, if it succeeded
+    symbol_table: Option<HashMap<String, u16>>,
+    output: Result<Lc2Output, Diagnostic>,
+    reuse: Reuse,
+}
+
+impl IncrementalAssembler {
+    /// Assemble the source for the first time
+    #[must_use]
+    pub fn new(assembler: Lc2Assembler, source: impl Into<String>) -> Self {
+        let source = source.into();
+        let lines = lex(&source);
+        let relexed_lines = lines.len();
+
+        let mut symbol_table = None;
+        let (output, _) = assemble(&assembler, &source, &lines, &mut symbol_table, false);
+
+        Self {
+            assembler,
+            source,
+            lines,
+            symbol_table,
+            output,
+            reuse: Reuse {
+                relexed_lines,
+                symbol_table: false,
+            },
+        }
+    }
+
+    #[must_use]
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The result of the last assembly
+    pub const fn output(&self) -> &Result<Lc2Output, Diagnostic> {
+        &self.output
+    }
+
+    /// What the last assembly reused from the previous one
+    #[must_use]
+    pub const fn reuse(&self) -> Reuse {
+        self.reuse
+    }
+
+    /// Apply the edits in order, each one to the source left by the previous
+    /// ones (like the changes sent by a language client), and assemble the
+    /// new source
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the range of an edit is out of the source, or if
+    /// it doesn't fall on a character boundary
+    pub fn edit(
+        &mut self,
+        edits: impl IntoIterator<Item = TextEdit>,
+    ) -> &Result<Lc2Output, Diagnostic> {
+        let mut relexed_lines = 0;
+        let mut same_layout = true;
+        for edit in edits {
+            let (relexed, same) = self.apply(edit);
+            relexed_lines += relexed;
+            same_layout &= same;
+        }
+
+        let (output, symbol_table) = assemble(
+            &self.assembler,
+            &self.source,
+            &self.lines,
+            &mut self.symbol_table,
+            same_layout,
+        );
+        self.output = output;
+        self.reuse = Reuse {
+            relexed_lines,
+            symbol_table,
+        };
+
+        &self.output
+    }
+
+    /// Apply an edit to the source and lex again the lines it touched,
+    /// returning how many lines were lexed and whether their layout is the
+    /// same
+    fn apply(&mut self, edit: TextEdit) -> (usize, bool) {
+        let TextEdit { range, text } = edit;
+
+        // Get the lines from the one containing the start of the edit to the
+        // one containing its end
+        let starts: Vec<usize> = self
+            .lines
+            .iter()
+            .scan(0, |start, line| {
+                let line_start = *start;
+                *start += line.length;
+                Some(line_start)
+            })
+            .collect();
+        let first = starts
+            .partition_point(|&start| start <= range.start)
+            .saturating_sub(1);
+        let mut last = starts
+            .partition_point(|&start| start < range.end)
+            .max(first + 1)
+            .min(self.lines.len());
+        let region_start = starts.get(first).copied().unwrap_or(0);
+        let region_end = starts.get(last).copied().unwrap_or(self.source.len());
+
+        self.source.replace_range(range.clone(), &text);
+        let mut region_end = region_end - range.len() + text.len();
+
+        // Without a trailing newline the lines join the next one
+        while last < self.lines.len() && !self.source[region_start..region_end].ends_with('\n') {
+            region_end += self.lines[last].length;
+            last += 1;
+        }
+
+        let lines = lex(&self.source[region_start..region_end]);
+        let same = same_layout(&self.lines[first..last], &lines);
+        let relexed = lines.len();
+        self.lines.splice(first..last, lines);
+
+        (relexed, same)
+    }
+}
+
+/// Split the source into its lines and lex them
+fn lex(source: &str) -> Vec<LexedLine> {
+    source.split_inclusive('\n').map(LexedLine::lex).collect()
+}
+
+/// Assemble the cached tokens, reusing the symbol table of the two passes if
+/// `reuse` is set or updating it otherwise. Return whether the symbol table
+/// was reused
+fn assemble(
+    assembler: &Lc2Assembler,
+    source: &str,
+    lines: &[LexedLine],
+    symbol_table: &mut Option<HashMap<String, u16>>,
+    reuse: bool,
+) -> (Result<Lc2Output, Diagnostic>, bool) {
+    // A string literal that spans many lines can't be read from the tokens of
+    // its lines, so the source is lexed again as a whole
+    let open_string = lines.iter().any(|line| line.open_string);
+    let lexer = if open_string {
+        TokenStream::new(source, assembler.strict_lines)
+    } else {
+        TokenStream::with_lines(source, lines, assembler.strict_lines)
+    };
+
+    // The single pass has no symbol table to reuse
+    if assembler.single_pass {
+        *symbol_table = None;
+        return (assembler.assemble_tokens(source, lexer), false);
+    }
+
+    // If the second pass fails the error could come from the first one, that
+    // would have found it earlier
+    if let Some(symbol_table) = symbol_table.as_ref().filter(|_| reuse && !open_string) {
+        log::debug!(target: "lc2_assembler", "Reusing the symbol table...");
+        if let Ok(output) =
+            assembler.assemble_second_pass(source, lexer.clone(), symbol_table.clone())
+        {
+            return (Ok(output), true);
+        }
+    }
+
+    *symbol_table = None;
+    let output = assembler
+        .symbol_table(source, lexer.clone())
+        .and_then(|table| {
+            *symbol_table = Some(table.clone());
+            assembler.assemble_second_pass(source, lexer, table)
+        });
+
+    (output, false)
+}
+
+/// Return `true` if the lines declare the same labels and take the same space
+/// in the binary, because they differ only in the registers, the numbers and
+/// the labels used as operands of their operations.
+///
+/// Since the previous lines were valid for the first pass, the operands of an
+/// operation are read in the same way, while a label is surely an operand only
+/// after a comma or a branch. The operands of a pseudo-operation can change its
+/// size, so they must stay the same
+fn same_layout(old: &[LexedLine], new: &[LexedLine]) -> bool {
+    old.len() == new.len()
+        && old.iter().zip(new).all(|(old, new)| {
+            // Whether the tokens follow an operation or a pseudo-operation
+            let mut operands = false;
+
+            old.tokens.len() == new.tokens.len()
+                && old.tokens.iter().zip(&new.tokens).enumerate().all(
+                    |(index, ((old_token, _), (new_token, _)))| {
+                        let same = old_token == new_token
+                            || (operands
+                                && same_operand(old_token, new_token, &old.tokens[index - 1].0));
+
+                        match old_token {
+                            Ok(Token::Operation(_)) => operands = true,
+                            Ok(Token::PseudoOperation(_)) => operands = false,
+                            _ => {}
+                        }
+                        same
+                    },
+                )
+        })
+}
+
+/// Return `true` if two tokens after an operation can be swapped without
+/// changing the symbol table
+const fn same_operand(
+    old: &Result<Token, ()>,
+    new: &Result<Token, ()>,
+    previous: &Result<Token, ()>,
+) -> bool {
+    match (old, new) {
+        (Ok(Token::Register(_)), Ok(Token::Register(_)))
+        | (Ok(Token::Number(_)), Ok(Token::Number(_))) => true,
+        (Ok(Token::Label(_)), Ok(Token::Label(_)))
+        | (Ok(Token::LocalLabel(_)), Ok(Token::LocalLabel(_))) => matches!(
+            previous,
+            Ok(Token::Comma | Token::Operation(Operation::Branch(..) | Operation::Jump(_)))
+        ),
+        _ => false,
+    }
+}
@@ -1,43 +1,602 @@
 use crate::ParseError;
 use architectures::lc2::Gpr;
-use logos::{Lexer, Logos};
+use logos::{Lexer, Logos, SpannedIter};
+use std::{fmt::Display, iter::Peekable, ops::Range};
 
+/// Declare the `Token` lexer, where a regex can be named (`#[regex(NAME =
+/// r"...")]`) to declare it also as a constant, shared by
+/// `TokenKind::patterns()`
+macro_rules! lexer {
+    (#[$($derive:tt)*] #[$($logos:tt)*] pub enum $name:ident { $($variants:tt)* }) => {
+        lexer!(@munch [#[$($derive)*] #[$($logos)*] pub enum $name] [] [] $($variants)*);
+    };
+
+    (@munch [$($header:tt)*] [$($variants:tt)*] [$($constants:tt)*]) => {
+        $($constants)*
+
+        $($header)* { $($variants)* }
+    };
+
+    (
+        @munch [$($header:tt)*] [$($variants:tt)*] [$($constants:tt)*]
+        #[regex($constant:ident = $regex:tt $(, $($callback:tt)*)?)] $($rest:tt)*
+    ) => {
+        lexer!(
+            @munch [$($header)*]
+            [$($variants)* #[regex($regex $(, $($callback)*)?)]]
+            [$($constants)* const $constant: &str = $regex;]
+            $($rest)*
+        );
+    };
+
+    (
+        @munch [$($header:tt)*] [$($variants:tt)*] [$($constants:tt)*]
+        #[$($attribute:tt)*] $($rest:tt)*
+    ) => {
+        lexer!(@munch [$($header)*] [$($variants)* #[$($attribute)*]] [$($constants)*] $($rest)*);
+    };
+
+    (
+        @munch [$($header:tt)*] [$($variants:tt)*] [$($constants:tt)*]
+        $next:tt $($rest:tt)*
+    ) => {
+        lexer!(@munch [$($header)*] [$($variants)* $next] [$($constants)*] $($rest)*);
+    };
+}
+
+lexer! {
 #[derive(Clone, Logos, Debug, PartialEq, Eq)]
-#[logos(skip r"[\s]+")]
+#[logos(skip r"[^\S\n]+")]
 pub enum Token {
+    #[token("\n")]
+    Newline,
+
     #[token(",")]
     Comma,
 
     #[token(":")]
     Colon,
 
-    #[regex(r"(;|//).*", |lex| lex.slice().to_string())]
+    #[token("==", |_| Comparison::Equal)]
+    #[token("!=", |_| Comparison::NotEqual)]
+    #[token("<", |_| Comparison::Less)]
+    #[token("<=", |_| Comparison::LessOrEqual)]
+    #[token(">", |_| Comparison::Greater)]
+    #[token(">=", |_| Comparison::GreaterOrEqual)]
+    Comparison(Comparison),
+
+    #[regex(COMMENT = r"(;|//).*", |lex| lex.slice().to_string())]
     Comment(String),
 
-    #[regex(r"[rR][0-7]", parse_register)]
+    #[regex(REGISTER = r"[rR][0-7]", parse_register)]
     Register(Gpr),
 
-    #[regex(r"[_a-zA-Z0-9]+", |lex| lex.slice().to_owned(), priority = 1)]
+    // A global label, optionally followed by the name of one of its local
+    // labels (`main.loop`)
+    #[regex(LABEL = r"[_a-zA-Z0-9]+(\.[_a-zA-Z][_a-zA-Z0-9]*)?", |lex| lex.slice().to_owned(), priority = 1)]
     Label(String),
 
-    #[regex(r"(?i)(0?b|%)[01]+", |lex| parse_number(lex, 2))]
-    #[regex(r"#?-?\d+", |lex| parse_number(lex, 10))]
-    #[regex(r"(?i)(0?x|\$)[a-f0-9]+", |lex| parse_number(lex, 16))]
+    // A label scoped to the previous global label, like `.loop`
+    #[regex(LOCAL_LABEL = r"\.[_a-zA-Z][_a-zA-Z0-9]*", |lex| lex.slice()[1..].to_owned())]
+    LocalLabel(String),
+
+    // A numeric label declaration, like `1:`, that can be declared many times
+    #[regex(NUMERIC_LABEL = r"\d+:", |lex| lex.slice().trim_end_matches(':').to_owned())]
+    NumericLabel(String),
+
+    // A reference to the previous (`1b`) or the next (`1f`) numeric label, or
+    // an anonymous label: `-` and `+` declare a backward and a forward label,
+    // while `--` or `++` reference the second previous or next one
+    #[regex(NUMERIC_LABEL_REFERENCE = r"\d+[bBfF]", parse_numeric_label_reference)]
+    #[regex(ANONYMOUS_LABEL = r"-+|\++", parse_anonymous_label)]
+    RelativeLabel(RelativeLabel),
+
+    #[regex(BINARY = r"(?i)(0?b|%)[01]+", |lex| parse_number(lex, 2))]
+    #[regex(DECIMAL = r"#?-?\d+", |lex| parse_number(lex, 10))]
+    #[regex(HEXADECIMAL = r"(?i)(0?x|\$)[a-f0-9]+", |lex| parse_number(lex, 16))]
+    #[regex(CHARACTER = r"#?-?'([^'\\\n]|\\[^\n])+'", parse_character)]
     Number(u16),
 
     // Inspired by https://logos.maciej.codes/examples/json.html
-    #[regex(r#""([^"\\]|\\["\\0nrt])*""#, parse_string)]
-    String(String),
+    #[regex(STRING = r#""([^"\\]|\\.)*""#, |lex| parse_string(lex.slice()).ok())]
+    String(StringLiteral),
 
-    #[regex(r"(?i)\.(ORIG|FILL|STRINGZ|BLKW|END)", |lex| PseudoOperation::try_from(lex.slice()).ok())]
-    #[regex(r"(?i)\.(STRINGZP)", |lex| PseudoOperation::try_from(lex.slice()).ok())]
+    #[regex(STANDARD_DIRECTIVES = r"(?i)\.(ORIG|FILL|STRINGZ|BLKW|END)", |lex| PseudoOperation::try_from(lex.slice()).ok())]
+    #[regex(STRINGZP_DIRECTIVE = r"(?i)\.(STRINGZP)", |lex| PseudoOperation::try_from(lex.slice()).ok())]
+    #[regex(DATA_DIRECTIVES = r"(?i)\.(STRING|ASCII|PSTRING|WORD|BYTES|INCBIN|ALIGN|PAGE|ENTRY)", |lex| PseudoOperation::try_from(lex.slice()).ok())]
+    #[regex(TEST_DIRECTIVES = r"(?i)\.(TEST|SETREG|INPUT|EXPECT|EXPECT_OUTPUT)", |lex| PseudoOperation::try_from(lex.slice()).ok())]
+    #[regex(CHECK_DIRECTIVES = r"(?i)\.(ASSERT|ERROR|WARNING)", |lex| PseudoOperation::try_from(lex.slice()).ok())]
     PseudoOperation(PseudoOperation),
 
-    #[regex(r"(?i)ADD|AND|JSRR?|JMPR?|LD[IR]?|LEA|NOT|RET|RTI|ST[IR]?", |lex| Operation::try_from(lex.slice()).ok())]
-    #[regex(r"(?i)BRn?z?p?|BR[gl][te]|BR(eq|ne)|(BR)?nop", |lex| Operation::try_from(lex.slice()).ok())]
-    #[regex(r"(?i)TRAP|GETC|OUT|PUTSP?|IN|HALT", |lex| Operation::try_from(lex.slice()).ok())]
+    #[regex(OPERATIONS = r"(?i)ADD|AND|JSRR?|JMPR?|LD[IR]?|LEA|NOT|RET|RTI|ST[IR]?", |lex| Operation::try_from(lex.slice()).ok())]
+    #[regex(BRANCHES = r"(?i)BRn?z?p?|BR[gl][te]|BR(eq|ne)|(BR)?nop", |lex| Operation::try_from(lex.slice()).ok())]
+    #[regex(TRAPS = r"(?i)TRAP|GETC|OUT|PUTSP?|IN|HALT", |lex| Operation::try_from(lex.slice()).ok())]
     Operation(Operation),
 }
+}
+
+/// Iterator over the tokens of an assembly.
+///
+/// Since the `Token` lexer classifies the tokens using only their regexes, a
+/// label named `xadd` would be read as a number and a label named `IN` as an
+/// operation. This iterator looks at the context of every token in order to:
+///   - Read a leading identifier followed by a colon, or by an operation on the
+///     same line, as a label declaration;
+///   - Read an identifier used as the `pgoffset9` operand of an operation as a
+///     label;
+///   - Return a `ReservedLabelName` error if an operation, a register or a
+///     pseudo-operation is used as the name of a label.
+///
+/// The `Newline` tokens are returned only if `newlines` is set, and the span of
+/// the last returned (or peeked) token is kept to locate the errors.
+#[derive(Clone)]
+pub struct TokenStream<'source> {
+    source: &'source str,
+    tokens: Peekable<RawTokens<'source>>,
+    newlines: bool,
+    last_end: usize,
+    label_operand: LabelOperand,
+    at_label_operand: bool,
+    peeked: Option<Spanned>,
+    span: Range<usize>,
+    consumed_end: usize,
+    wrapped_numbers: Vec<Range<usize>>,
+}
+
+type Spanned = (Result<Token, ParseError>, Range<usize>);
+
+/// A token returned by the `Token` lexer, before looking at its context
+pub type RawToken = (Result<Token, ()>, Range<usize>);
+
+/// A line of an assembly lexed in advance, like the ones cached by the
+/// incremental assembler
+pub struct LexedLine {
+    /// The length of the line, including its trailing newline
+    pub length: usize,
+    /// The raw tokens, with their spans relative to the start of the line
+    pub tokens: Vec<RawToken>,
+    /// `true` if a string literal is not closed on the line, so it could
+    /// continue on the next ones
+    pub open_string: bool,
+}
+
+impl LexedLine {
+    pub fn lex(line: &str) -> Self {
+        let tokens: Vec<RawToken> = Token::lexer(line).spanned().collect();
+        let open_string = tokens
+            .iter()
+            .any(|(token, span)| token.is_err() && line[span.clone()].starts_with('"'));
+
+        Self {
+            length: line.len(),
+            tokens,
+            open_string,
+        }
+    }
+}
+
+/// The raw tokens of an assembly, lexed while they are read or lexed in
+/// advance
+#[derive(Clone)]
+enum RawTokens<'source> {
+    Lexer(SpannedIter<'source, Token>),
+    Lines {
+        lines: std::slice::Iter<'source, LexedLine>,
+        tokens: std::slice::Iter<'source, RawToken>,
+        /// The start of the line of `tokens`
+        start: usize,
+        /// The start of the next line
+        end: usize,
+    },
+}
+
+impl Iterator for RawTokens<'_> {
+    type Item = RawToken;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Lexer(tokens) => tokens.next(),
+            Self::Lines {
+                lines,
+                tokens,
+                start,
+                end,
+            } => loop {
+                // Move the spans from the line to the source
+                if let Some((token, span)) = tokens.next() {
+                    return Some((token.clone(), span.start + *start..span.end + *start));
+                }
+
+                let line = lines.next()?;
+                *tokens = line.tokens.iter();
+                *start = *end;
+                *end += line.length;
+            },
+        }
+    }
+}
+
+/// The position of the next `pgoffset9` label operand, if any
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LabelOperand {
+    None,
+    AfterRegister,
+    Next,
+}
+
+impl<'source> TokenStream<'source> {
+    pub fn new(source: &'source str, newlines: bool) -> Self {
+        Self::from_raw(
+            source,
+            RawTokens::Lexer(Token::lexer(source).spanned()),
+            newlines,
+        )
+    }
+
+    /// Read the tokens of the lines of the source that were already lexed
+    pub fn with_lines(source: &'source str, lines: &'source [LexedLine], newlines: bool) -> Self {
+        Self::from_raw(
+            source,
+            RawTokens::Lines {
+                lines: lines.iter(),
+                tokens: [].iter(),
+                start: 0,
+                end: 0,
+            },
+            newlines,
+        )
+    }
+
+    fn from_raw(source: &'source str, tokens: RawTokens<'source>, newlines: bool) -> Self {
+        Self {
+            source,
+            tokens: tokens.peekable(),
+            newlines,
+            last_end: 0,
+            label_operand: LabelOperand::None,
+            at_label_operand: false,
+            peeked: None,
+            span: 0..0,
+            consumed_end: 0,
+            wrapped_numbers: Vec::new(),
+        }
+    }
+
+    /// Return a reference to the next token without consuming it
+    pub fn peek(&mut self) -> Option<&Result<Token, ParseError>> {
+        if self.peeked.is_none() {
+            self.peeked = self.next_spanned();
+        }
+
+        let Some((token, span)) = &self.peeked else {
+            self.span = self.source.len()..self.source.len();
+            return None;
+        };
+        self.span.clone_from(span);
+
+        Some(token)
+    }
+
+    /// Return `true` if the `Newline` tokens are returned
+    pub const fn newlines(&self) -> bool {
+        self.newlines
+    }
+
+    /// Return the span of the last returned (or peeked) token. If the assembly
+    /// is over, return an empty span at its end
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// Return the spans of the negative number literals lexed so far that don't
+    /// fit into 16 bits, like `-40000` that wraps around to `25536`
+    pub fn wrapped_numbers(&self) -> &[Range<usize>] {
+        &self.wrapped_numbers
+    }
+
+    /// Return `true` if the last returned (or peeked) token is in the position
+    /// of a `pgoffset9` label operand, like the `x10` of `BR x10` that is a
+    /// label and not a number if it's declared
+    pub const fn at_label_operand(&self) -> bool {
+        self.at_label_operand
+    }
+
+    /// Return the source of the last returned (or peeked) token
+    pub fn slice(&self) -> &'source str {
+        &self.source[self.span.clone()]
+    }
+
+    /// Return `true` if the next token is on the same line as the last
+    /// returned one
+    pub fn next_on_same_line(&mut self) -> bool {
+        self.gap().is_some_and(|gap| !gap.contains('\n'))
+    }
+
+    /// Return the source between the last returned token and the next one, if
+    /// there is a next token
+    fn gap(&mut self) -> Option<&'source str> {
+        self.peek()?;
+        Some(&self.source[self.consumed_end..self.span.start])
+    }
+
+    /// Return `true` if the token is in a label declaration position: at the
+    /// start of a line and followed by a colon (or by an operation on the same
+    /// line if `operation` is set)
+    fn declares_label(&mut self, start: usize, end: usize, operation: bool) -> bool {
+        // Check that the token is the first one on its line
+        if start != 0 && !self.source[self.last_end..start].contains('\n') {
+            return false;
+        }
+
+        match self.tokens.peek() {
+            Some((Ok(Token::Colon), _)) => true,
+            Some((Ok(Token::Operation(_) | Token::PseudoOperation(_)), next)) => {
+                operation && !self.source[end..next.start].contains('\n')
+            }
+            _ => false,
+        }
+    }
+
+    fn next_spanned(&mut self) -> Option<Spanned> {
+        let (token, span) = loop {
+            match self.tokens.next()? {
+                // A newline ends any operand. Skip it if it's not needed
+                (Ok(Token::Newline), span) => {
+                    self.label_operand = LabelOperand::None;
+                    if self.newlines {
+                        self.at_label_operand = false;
+                        return Some((Ok(Token::Newline), span));
+                    }
+                }
+                (Ok(token), span) => break (token, span),
+                (Err(()), span) => {
+                    return Some((Err(lexing_error(&self.source[span.clone()])), span));
+                }
+            }
+        };
+        let slice = &self.source[span.clone()];
+        self.at_label_operand = self.label_operand == LabelOperand::Next;
+
+        let token = match token {
+            // A number that is also a valid identifier (like `xadd` or `b101`)
+            // is a label if it's declared as one or if it's used as a label
+            // operand
+            Token::Number(_)
+                if is_identifier(slice)
+                    && (self.at_label_operand
+                        || self.declares_label(span.start, span.end, true)) =>
+            {
+                Token::Label(slice.to_owned())
+            }
+
+            // Operations, registers and pseudo-operations can't be labels
+            Token::Register(_) if self.declares_label(span.start, span.end, true) => {
+                return Some((Err(ParseError::ReservedLabelName), span));
+            }
+            // With the newlines, an operation can't be followed by another one
+            // on the same line, so `OUT HALT` is a label named `OUT`
+            Token::Operation(_) | Token::PseudoOperation(_)
+                if self.declares_label(span.start, span.end, self.newlines) =>
+            {
+                return Some((Err(ParseError::ReservedLabelName), span));
+            }
+
+            token => token,
+        };
+
+        // Keep track of where the next `pgoffset9` label operand will be
+        self.label_operand = match (&token, self.label_operand) {
+            (Token::Operation(Operation::Branch(..) | Operation::Jump(_)), _)
+            | (Token::Register(_), LabelOperand::AfterRegister)
+            | (Token::Comma, LabelOperand::Next) => LabelOperand::Next,
+            (
+                Token::Operation(
+                    Operation::Load
+                    | Operation::LoadIndirect
+                    | Operation::LoadEffectiveAddress
+                    | Operation::Store
+                    | Operation::StoreIndirect,
+                ),
+                _,
+            ) => LabelOperand::AfterRegister,
+            _ => LabelOperand::None,
+        };
+
+        if matches!(token, Token::Number(_)) && negation_wraps(slice) {
+            self.wrapped_numbers.push(span.clone());
+        }
+
+        self.last_end = span.end;
+        Some((Ok(token), span))
+    }
+}
+
+impl Iterator for TokenStream<'_> {
+    type Item = Result<Token, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Keep the span of the token, or an empty span at the end of the
+        // assembly if there are no more tokens
+        let Some((token, span)) = self.peeked.take().or_else(|| self.next_spanned()) else {
+            self.span = self.source.len()..self.source.len();
+            return None;
+        };
+        self.consumed_end = span.end;
+        self.span = span;
+
+        Some(token)
+    }
+}
+
+/// Return `true` if the string could be the name of a label
+fn is_identifier(string: &str) -> bool {
+    string.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && string
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Return `true` if the string is a negative decimal number literal whose
+/// absolute value doesn't fit into 16 bits two's complement
+fn negation_wraps(string: &str) -> bool {
+    string
+        .trim_start_matches('#')
+        .strip_prefix('-')
+        .and_then(|digits| digits.parse::<u32>().ok())
+        .is_some_and(|number| number > 0x8000)
+}
+
+/// Return the number represented by the whole string, if any
+pub fn parse_number_literal(string: &str) -> Option<u16> {
+    let mut lexer = Token::lexer(string);
+
+    match (lexer.next(), lexer.next()) {
+        (Some(Ok(Token::Number(number))), None) => Some(number),
+        _ => None,
+    }
+}
+
+/// The class of a token for the syntax highlighting
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Mnemonic,
+    Register,
+    Directive,
+    LabelDefinition,
+    LabelReference,
+    Number,
+    String,
+    Comment,
+}
+
+impl TokenKind {
+    pub const ALL: [Self; 8] = [
+        Self::Mnemonic,
+        Self::Register,
+        Self::Directive,
+        Self::LabelDefinition,
+        Self::LabelReference,
+        Self::Number,
+        Self::String,
+        Self::Comment,
+    ];
+
+    /// The name of the class in snake case, like `label_definition`
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Mnemonic => "mnemonic",
+            Self::Register => "register",
+            Self::Directive => "directive",
+            Self::LabelDefinition => "label_definition",
+            Self::LabelReference => "label_reference",
+            Self::Number => "number",
+            Self::String => "string",
+            Self::Comment => "comment",
+        }
+    }
+
+    /// The regexes of the `Token` variants in the class, the same ones used by
+    /// the lexer. A label definition is a label at the start of its line
+    #[must_use]
+    pub const fn patterns(self) -> &'static [&'static str] {
+        match self {
+            Self::Mnemonic => &[OPERATIONS, BRANCHES, TRAPS],
+            Self::Register => &[REGISTER],
+            Self::Directive => &[
+                STANDARD_DIRECTIVES,
+                STRINGZP_DIRECTIVE,
+                DATA_DIRECTIVES,
+                TEST_DIRECTIVES,
+                CHECK_DIRECTIVES,
+            ],
+            Self::LabelDefinition => &[NUMERIC_LABEL, LABEL, LOCAL_LABEL, ANONYMOUS_LABEL],
+            Self::LabelReference => &[NUMERIC_LABEL_REFERENCE, LABEL, LOCAL_LABEL, ANONYMOUS_LABEL],
+            Self::Number => &[BINARY, DECIMAL, HEXADECIMAL, CHARACTER],
+            Self::String => &[STRING],
+            Self::Comment => &[COMMENT],
+        }
+    }
+}
+
+/// A token of an assembly with its highlighting class
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClassifiedToken {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+}
+
+/// Split an assembly into its highlighted tokens.
+///
+/// The tokens are read in their context like the assembler does, so `xadd`
+/// used as a label operand is a label reference and not a number. The commas,
+/// the colons, the `+` and `-` joining the terms of an expression, the
+/// comparison operators and the tokens that can't be lexed are left out
+#[must_use]
+pub fn classify(source: &str) -> Vec<ClassifiedToken> {
+    let mut tokens = TokenStream::new(source, true);
+    let mut classified = Vec::new();
+    let mut line_start = true;
+    let mut after_term = false;
+
+    while let Some(token) = tokens.next() {
+        let span = tokens.span();
+
+        // A reserved name used as a label is still highlighted by its class
+        let token = match token {
+            Ok(token) => token,
+            Err(_) => match Token::lexer(&source[span.clone()]).next() {
+                Some(Ok(token)) => token,
+                _ => continue,
+            },
+        };
+
+        let kind = match &token {
+            Token::Newline => {
+                line_start = true;
+                after_term = false;
+                continue;
+            }
+            Token::Comma | Token::Colon | Token::Comparison(_) => None,
+            Token::Comment(_) => Some(TokenKind::Comment),
+            Token::Register(_) => Some(TokenKind::Register),
+            Token::Label(_)
+            | Token::LocalLabel(_)
+            | Token::NumericLabel(_)
+            | Token::RelativeLabel(_)
+                if line_start =>
+            {
+                Some(TokenKind::LabelDefinition)
+            }
+            Token::RelativeLabel(label)
+                if after_term && label.is_anonymous() && label.distance == 1 =>
+            {
+                None
+            }
+            Token::Label(_)
+            | Token::LocalLabel(_)
+            | Token::NumericLabel(_)
+            | Token::RelativeLabel(_) => Some(TokenKind::LabelReference),
+            Token::Number(_) => Some(TokenKind::Number),
+            Token::String(_) => Some(TokenKind::String),
+            Token::PseudoOperation(_) => Some(TokenKind::Directive),
+            Token::Operation(_) => Some(TokenKind::Mnemonic),
+        };
+
+        after_term = match &token {
+            Token::Number(_) | Token::Label(_) | Token::LocalLabel(_) => !line_start,
+            Token::RelativeLabel(label) => !line_start && !label.is_anonymous(),
+            _ => false,
+        };
+        line_start = false;
+
+        if let Some(kind) = kind {
+            classified.push(ClassifiedToken { kind, span });
+        }
+    }
+
+    classified
+}
 
 fn parse_register(lex: &Lexer<Token>) -> Option<Gpr> {
     // Convert the second char of the register into a usize
@@ -47,22 +606,213 @@ fn parse_register(lex: &Lexer<Token>) -> Option<Gpr> {
     Gpr::try_from(register_number).ok()
 }
 
-fn parse_string(lex: &Lexer<Token>) -> Option<String> {
+fn parse_numeric_label_reference(lex: &Lexer<Token>) -> RelativeLabel {
+    // Split the label from the direction suffix
+    let (name, direction) = lex.slice().split_at(lex.slice().len() - 1);
+
+    RelativeLabel {
+        name: name.to_owned(),
+        forward: direction.eq_ignore_ascii_case("f"),
+        distance: 1,
+    }
+}
+
+fn parse_anonymous_label(lex: &Lexer<Token>) -> RelativeLabel {
+    // The direction is given by the sign and the distance by the number of
+    // signs
+    let forward = lex.slice().starts_with('+');
+
+    RelativeLabel {
+        name: (if forward { "+" } else { "-" }).to_owned(),
+        forward,
+        distance: lex.slice().len(),
+    }
+}
+
+fn parse_string(slice: &str) -> Result<StringLiteral, ParseError> {
     // Remove the starting and ending double quotes and replace all the escaped
     // characters
-    Some(
-        lex.slice()
-            .strip_prefix('"')?
-            .strip_suffix('"')?
-            .replace("\\\"", "\"")
-            .replace("\\\\", "\\")
-            .replace("\\0", "\0")
-            .replace("\\n", "\n")
-            .replace("\\r", "\r")
-            .replace("\\t", "\t"),
+    unescape(
+        slice
+            .strip_prefix('"')
+            .and_then(|string| string.strip_suffix('"'))
+            .ok_or(ParseError::NonValidToken)?,
     )
 }
 
+fn parse_character(lex: &Lexer<Token>) -> Option<u16> {
+    parse_character_literal(lex.slice()).ok()
+}
+
+/// Convert a character literal, like `'a'`, `'\n'` or `#-'0'`, into its code
+fn parse_character_literal(slice: &str) -> Result<u16, ParseError> {
+    // Check if the character is negative
+    let literal = slice.trim_start_matches('#');
+    let (negative, literal) = literal
+        .strip_prefix('-')
+        .map_or((false, literal), |literal| (true, literal));
+
+    // Remove the quotes and replace the escaped characters
+    let string = unescape(
+        literal
+            .strip_prefix('\'')
+            .and_then(|string| string.strip_suffix('\''))
+            .ok_or(ParseError::NonValidToken)?,
+    )?;
+
+    // Make sure that there is exactly one character (or byte) that fits into a
+    // word
+    let mut num = match string.0.as_slice() {
+        [StringPart::Character(character)] => {
+            u16::try_from(u32::from(*character)).map_err(|_| ParseError::InvalidCharacterLiteral)?
+        }
+        [StringPart::Byte(byte)] => u16::from(*byte),
+        _ => return Err(ParseError::InvalidCharacterLiteral),
+    };
+
+    // If the number is negative do the two's complement
+    if negative {
+        num = (!num).wrapping_add(1);
+    }
+
+    Ok(num)
+}
+
+/// The content of a string literal. The bytes written as escape sequences (like
+/// `\xFF` or `\377`) are kept as bytes, so the string encoding doesn't convert
+/// them like the characters
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StringLiteral(pub Vec<StringPart>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringPart {
+    Character(char),
+    /// A byte written as an octal or hexadecimal escape sequence
+    Byte(u8),
+}
+
+impl From<&str> for StringLiteral {
+    fn from(string: &str) -> Self {
+        Self(string.chars().map(StringPart::Character).collect())
+    }
+}
+
+/// Print the characters of the string, with the bytes as their ISO-8859-1
+/// characters
+impl Display for StringLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for part in &self.0 {
+            match *part {
+                StringPart::Character(character) => write!(f, "{character}")?,
+                StringPart::Byte(byte) => write!(f, "{}", char::from(byte))?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Replace the escape sequences of a string or character literal:
+///   - `\"`, `\'`, `\\`, `\n`, `\r`, `\t`, `\a`, `\b` and `\e`;
+///   - `\0` to `\377` for the octal codes, that are bytes;
+///   - `\xHH` for the hexadecimal codes, that are bytes;
+///   - `\uHHHH` and `\u{H...}` for the Unicode code points.
+fn unescape(string: &str) -> Result<StringLiteral, ParseError> {
+    let mut unescaped = Vec::with_capacity(string.len());
+    let mut chars = string.chars().peekable();
+
+    while let Some(character) = chars.next() {
+        if character != '\\' {
+            unescaped.push(StringPart::Character(character));
+            continue;
+        }
+
+        let escaped = match chars.next().ok_or(ParseError::InvalidEscapeSequence)? {
+            '"' => '"',
+            '\'' => '\'',
+            '\\' => '\\',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'a' => '\x07',
+            'b' => '\x08',
+            'e' => '\x1b',
+
+            // Up to three octal digits, up to `\377`
+            digit @ '0'..='7' => {
+                let code = digit.to_digit(8).unwrap_or_default();
+                let code = take_digits(&mut chars, 8, 2)
+                    .map_or(code, |(digits, count)| code * 8_u32.pow(count) + digits);
+
+                let byte = u8::try_from(code).map_err(|_| ParseError::InvalidEscapeSequence)?;
+                unescaped.push(StringPart::Byte(byte));
+                continue;
+            }
+
+            // One or two hexadecimal digits
+            'x' => {
+                let (code, _) =
+                    take_digits(&mut chars, 16, 2).ok_or(ParseError::InvalidEscapeSequence)?;
+                let byte = u8::try_from(code).map_err(|_| ParseError::InvalidEscapeSequence)?;
+                unescaped.push(StringPart::Byte(byte));
+                continue;
+            }
+
+            // Four hexadecimal digits, or up to six of them between braces
+            'u' => {
+                let code = if chars.next_if_eq(&'{').is_some() {
+                    take_digits(&mut chars, 16, 6).filter(|_| chars.next() == Some('}'))
+                } else {
+                    take_digits(&mut chars, 16, 4).filter(|&(_, count)| count == 4)
+                };
+
+                code.and_then(|(code, _)| char::from_u32(code))
+                    .ok_or(ParseError::InvalidEscapeSequence)?
+            }
+
+            _ => return Err(ParseError::InvalidEscapeSequence),
+        };
+
+        unescaped.push(StringPart::Character(escaped));
+    }
+
+    Ok(StringLiteral(unescaped))
+}
+
+/// Consume up to `max` digits in the given base, returning their value and how
+/// many they were
+fn take_digits(
+    chars: &mut Peekable<impl Iterator<Item = char>>,
+    radix: u32,
+    max: u32,
+) -> Option<(u32, u32)> {
+    let mut value = None;
+    let mut count = 0;
+
+    while count < max {
+        let Some(digit) = chars.peek().and_then(|digit| digit.to_digit(radix)) else {
+            break;
+        };
+        value = Some(value.unwrap_or(0) * radix + digit);
+        count += 1;
+        chars.next();
+    }
+
+    value.map(|value| (value, count))
+}
+
+/// Return the reason why the lexer refused a token
+fn lexing_error(slice: &str) -> ParseError {
+    if slice.starts_with('"') {
+        parse_string(slice).err()
+    } else if slice.contains('\'') {
+        parse_character_literal(slice).err()
+    } else {
+        None
+    }
+    .unwrap_or(ParseError::NonValidToken)
+}
+
 fn parse_number(lex: &Lexer<Token>, base: u32) -> Option<u16> {
     // Get the possible prefixes for any given base
     let prefixes: &[char] = match base {
@@ -103,6 +853,22 @@ fn parse_number(lex: &Lexer<Token>, base: u32) -> Option<u16> {
     Some(num)
 }
 
+/// A label resolved relatively to the position where it's used: numeric labels
+/// (`1b`, `1f`) and anonymous labels (`-`, `++`)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RelativeLabel {
+    pub name: String,
+    pub forward: bool,
+    pub distance: usize,
+}
+
+impl RelativeLabel {
+    /// Return `true` if the label is an anonymous one (`+` or `-`)
+    pub fn is_anonymous(&self) -> bool {
+        self.name == "+" || self.name == "-"
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Operation {
     Add,
@@ -123,45 +889,101 @@ pub enum Operation {
     Trap(Option<u8>),
 }
 
+impl Operation {
+    /// Every mnemonic accepted by the assembler, in any case, with its
+    /// operation
+    pub const MNEMONICS: [(&'static str, Self); 39] = [
+        ("ADD", Self::Add),
+        ("AND", Self::And),
+        ("BRn", Self::Branch(true, false, false)),
+        ("BRz", Self::Branch(false, true, false)),
+        ("BRp", Self::Branch(false, false, true)),
+        ("BRnz", Self::Branch(true, true, false)),
+        ("BRnp", Self::Branch(true, false, true)),
+        ("BRzp", Self::Branch(false, true, true)),
+        ("BRnzp", Self::Branch(true, true, true)),
+        ("BR", Self::Branch(true, true, true)),
+        ("BRLT", Self::Branch(true, false, false)),
+        ("BREQ", Self::Branch(false, true, false)),
+        ("BRGT", Self::Branch(false, false, true)),
+        ("BRLE", Self::Branch(true, true, false)),
+        ("BRNE", Self::Branch(true, false, true)),
+        ("BRGE", Self::Branch(false, true, true)),
+        ("NOP", Self::Branch(false, false, false)),
+        ("BRnop", Self::Branch(false, false, false)),
+        ("JSR", Self::Jump(true)),
+        ("JMP", Self::Jump(false)),
+        ("JSRR", Self::JumpRegister(true)),
+        ("JMPR", Self::JumpRegister(false)),
+        ("LD", Self::Load),
+        ("LDI", Self::LoadIndirect),
+        ("LDR", Self::LoadRegister),
+        ("LEA", Self::LoadEffectiveAddress),
+        ("NOT", Self::Not),
+        ("RET", Self::Return),
+        ("RTI", Self::ReturnInterrupt),
+        ("ST", Self::Store),
+        ("STI", Self::StoreIndirect),
+        ("STR", Self::StoreRegister),
+        ("TRAP", Self::Trap(None)),
+        ("GETC", Self::Trap(Some(0x20))),
+        ("OUT", Self::Trap(Some(0x21))),
+        ("PUTS", Self::Trap(Some(0x22))),
+        ("IN", Self::Trap(Some(0x23))),
+        ("PUTSP", Self::Trap(Some(0x24))),
+        ("HALT", Self::Trap(Some(0x25))),
+    ];
+}
+
 impl TryFrom<&str> for Operation {
     type Error = ParseError;
 
     fn try_from(token: &str) -> Result<Self, Self::Error> {
-        Ok(match token.to_uppercase().as_str() {
-            "ADD" => Self::Add,
-            "AND" => Self::And,
-            "BRN" | "BRLT" => Self::Branch(true, false, false),
-            "BRZ" | "BREQ" => Self::Branch(false, true, false),
-            "BRP" | "BRGT" => Self::Branch(false, false, true),
-            "BRNZ" | "BRLE" => Self::Branch(true, true, false),
-            "BRNP" | "BRNE" => Self::Branch(true, false, true),
-            "BRZP" | "BRGE" => Self::Branch(false, true, true),
-            "BRNZP" | "BR" => Self::Branch(true, true, true),
-            "BRNOP" | "NOP" => Self::Branch(false, false, false),
-            "JSR" => Self::Jump(true),
-            "JMP" => Self::Jump(false),
-            "JSRR" => Self::JumpRegister(true),
-            "JMPR" => Self::JumpRegister(false),
-            "LD" => Self::Load,
-            "LDI" => Self::LoadIndirect,
-            "LDR" => Self::LoadRegister,
-            "LEA" => Self::LoadEffectiveAddress,
-            "NOT" => Self::Not,
-            "RET" => Self::Return,
-            "RTI" => Self::ReturnInterrupt,
-            "ST" => Self::Store,
-            "STI" => Self::StoreIndirect,
-            "STR" => Self::StoreRegister,
-
-            "TRAP" => Self::Trap(None),
-            "GETC" => Self::Trap(Some(0x20)),
-            "OUT" => Self::Trap(Some(0x21)),
-            "PUTS" => Self::Trap(Some(0x22)),
-            "IN" => Self::Trap(Some(0x23)),
-            "PUTSP" => Self::Trap(Some(0x24)),
-            "HALT" => Self::Trap(Some(0x25)),
-
-            _ => return Err(ParseError::NonValidToken),
+        Self::MNEMONICS
+            .iter()
+            .find(|(mnemonic, _)| mnemonic.eq_ignore_ascii_case(token))
+            .map(|(_, operation)| operation.clone())
+            .ok_or(ParseError::NonValidToken)
+    }
+}
+
+impl Display for Operation {
+    /// Print the canonical mnemonic of the operation, like `BRnz` or `HALT`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Add => "ADD",
+            Self::And => "AND",
+            Self::Branch(false, false, false) => "NOP",
+            Self::Branch(n, z, p) => {
+                f.write_str("BR")?;
+                for (condition, flag) in [(n, "n"), (z, "z"), (p, "p")] {
+                    if *condition {
+                        f.write_str(flag)?;
+                    }
+                }
+                return Ok(());
+            }
+            Self::Jump(true) => "JSR",
+            Self::Jump(false) => "JMP",
+            Self::JumpRegister(true) => "JSRR",
+            Self::JumpRegister(false) => "JMPR",
+            Self::Load => "LD",
+            Self::LoadIndirect => "LDI",
+            Self::LoadRegister => "LDR",
+            Self::LoadEffectiveAddress => "LEA",
+            Self::Not => "NOT",
+            Self::Return => "RET",
+            Self::ReturnInterrupt => "RTI",
+            Self::Store => "ST",
+            Self::StoreIndirect => "STI",
+            Self::StoreRegister => "STR",
+            Self::Trap(Some(0x20)) => "GETC",
+            Self::Trap(Some(0x21)) => "OUT",
+            Self::Trap(Some(0x22)) => "PUTS",
+            Self::Trap(Some(0x23)) => "IN",
+            Self::Trap(Some(0x24)) => "PUTSP",
+            Self::Trap(Some(0x25)) => "HALT",
+            Self::Trap(_) => "TRAP",
         })
     }
 }
@@ -176,22 +998,161 @@ pub enum PseudoOperation {
 
     // Custom pseudo-op: Create a null-terminated packed string
     Stringzp,
+
+    // Custom pseudo-ops: Create a string without the null terminator, a string
+    // prefixed by its length, a list of words and a packed list of bytes
+    String,
+    Pstring,
+    Word,
+    Bytes,
+
+    // Custom pseudo-op: Embed the bytes of a binary file
+    Incbin,
+
+    // Custom pseudo-ops: Pad the binary to a multiple of a number of words or
+    // to the next LC2 page
+    Align,
+    Page,
+
+    // Custom pseudo-op: Set the entry point of the program
+    Entry,
+
+    // Custom pseudo-ops: Declare a test case, its initial registers and
+    // console input, and the registers and console output it expects
+    Test,
+    Setreg,
+    Input,
+    Expect,
+    ExpectOutput,
+
+    // Custom pseudo-ops: Fail the assembly if a condition doesn't hold, or
+    // unconditionally with an error, or report a warning
+    Assert,
+    Error,
+    Warning,
 }
 
 impl TryFrom<&str> for PseudoOperation {
     type Error = ParseError;
 
-    fn try_from(token: &str) -> Result<Self, Self::Error> {
-        Ok(match token.to_uppercase().as_str() {
-            ".ORIG" => Self::Orig,
-            ".FILL" => Self::Fill,
-            ".STRINGZ" => Self::Stringz,
-            ".BLKW" => Self::Blkw,
-            ".END" => Self::End,
+    // `Self::Error` would be ambiguous with the `.error` pseudo-operation
+    fn try_from(token: &str) -> Result<Self, ParseError> {
+        Self::MNEMONICS
+            .iter()
+            .find(|(mnemonic, _)| mnemonic.eq_ignore_ascii_case(token))
+            .map(|(_, pseudo_operation)| pseudo_operation.clone())
+            .ok_or(ParseError::NonValidToken)
+    }
+}
+
+impl PseudoOperation {
+    /// Every pseudo-operation accepted by the assembler, in any case
+    pub const MNEMONICS: [(&'static str, Self); 23] = [
+        (".ORIG", Self::Orig),
+        (".FILL", Self::Fill),
+        (".STRINGZ", Self::Stringz),
+        (".BLKW", Self::Blkw),
+        (".END", Self::End),
+        (".STRINGZP", Self::Stringzp),
+        (".STRING", Self::String),
+        (".ASCII", Self::String),
+        (".PSTRING", Self::Pstring),
+        (".WORD", Self::Word),
+        (".BYTES", Self::Bytes),
+        (".INCBIN", Self::Incbin),
+        (".ALIGN", Self::Align),
+        (".PAGE", Self::Page),
+        (".ENTRY", Self::Entry),
+        (".TEST", Self::Test),
+        (".SETREG", Self::Setreg),
+        (".INPUT", Self::Input),
+        (".EXPECT", Self::Expect),
+        (".EXPECT_OUTPUT", Self::ExpectOutput),
+        (".ASSERT", Self::Assert),
+        (".ERROR", Self::Error),
+        (".WARNING", Self::Warning),
+    ];
+
+    /// `true` for the directives that declare the test cases
+    #[must_use]
+    pub const fn is_test(&self) -> bool {
+        matches!(
+            self,
+            Self::Test | Self::Setreg | Self::Input | Self::Expect | Self::ExpectOutput
+        )
+    }
 
-            ".STRINGZP" => Self::Stringzp,
+    /// `true` for the directives that check the assembly
+    #[must_use]
+    pub const fn is_check(&self) -> bool {
+        matches!(self, Self::Assert | Self::Error | Self::Warning)
+    }
+}
+
+impl Display for PseudoOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Orig => ".ORIG",
+            Self::Fill => ".FILL",
+            Self::Stringz => ".STRINGZ",
+            Self::Blkw => ".BLKW",
+            Self::End => ".END",
+            Self::Stringzp => ".STRINGZP",
+            Self::String => ".STRING",
+            Self::Pstring => ".PSTRING",
+            Self::Word => ".WORD",
+            Self::Bytes => ".BYTES",
+            Self::Incbin => ".INCBIN",
+            Self::Align => ".ALIGN",
+            Self::Page => ".PAGE",
+            Self::Entry => ".ENTRY",
+            Self::Test => ".TEST",
+            Self::Setreg => ".SETREG",
+            Self::Input => ".INPUT",
+            Self::Expect => ".EXPECT",
+            Self::ExpectOutput => ".EXPECT_OUTPUT",
+            Self::Assert => ".ASSERT",
+            Self::Error => ".ERROR",
+            Self::Warning => ".WARNING",
+        })
+    }
+}
+
+/// The operator of an `.ASSERT` condition, comparing two unsigned words
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    /// Compare the two words as unsigned numbers
+    #[must_use]
+    pub const fn holds(self, left: u16, right: u16) -> bool {
+        match self {
+            Self::Equal => left == right,
+            Self::NotEqual => left != right,
+            Self::Less => left < right,
+            Self::LessOrEqual => left <= right,
+            Self::Greater => left > right,
+            Self::GreaterOrEqual => left >= right,
+        }
+    }
+}
 
-            _ => return Err(ParseError::NonValidToken),
+impl Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Equal => "==",
+            Self::NotEqual => "!=",
+            Self::Less => "<",
+            Self::LessOrEqual => "<=",
+            Self::Greater => ">",
+            Self::GreaterOrEqual => ">=",
         })
     }
 }
@@ -0,0 +1,270 @@
+use super::{
+    analysis::{ControlFlowGraph, FlowDiagnostic},
+    lexer::Operation,
+};
+use crate::lint::Lint;
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+/// Looks for the mistakes described by the `Lint`s while the binary is
+/// assembled, one statement at a time.
+///
+/// It keeps track only of the straight-line flow between two labels: a label
+/// could be the target of any jump, so it resets what is known about the
+/// execution
+#[derive(Debug)]
+pub struct Linter<'source> {
+    source: &'source str,
+    flow: Flow,
+    negations: [Option<Negation>; 8],
+    labels: Vec<(String, Range<usize>)>,
+    operations: HashMap<u16, Range<usize>>,
+    triggered: Vec<(Lint, Range<usize>)>,
+}
+
+/// What happens after the last statement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flow {
+    /// Nothing is known, like after a label or some data
+    Unknown,
+    /// The last operation continues to the next statement
+    FallsThrough,
+    /// The last operation never continues to the next statement
+    Unreachable,
+    /// The last operation was already reported as unreachable
+    Reported,
+}
+
+/// A register that contains the bitwise not (`complete` is `false`) or the
+/// two's complement (`complete` is `true`) of the `source` register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Negation {
+    source: usize,
+    complete: bool,
+}
+
+impl<'source> Linter<'source> {
+    pub fn new(source: &'source str) -> Self {
+        Self {
+            source,
+            flow: Flow::Unknown,
+            negations: [None; 8],
+            labels: Vec::new(),
+            operations: HashMap::new(),
+            triggered: Vec::new(),
+        }
+    }
+
+    /// A new `.orig` directive starts an unrelated block of code
+    pub const fn origin(&mut self) {
+        self.flow = Flow::Unknown;
+        self.negations = [None; 8];
+    }
+
+    /// A label makes the next statement reachable, but it doesn't stop the
+    /// execution from falling through it. Only the `named` ones are checked for
+    /// references, since numeric and anonymous labels are meant to be
+    /// throwaway
+    pub fn label(&mut self, name: String, span: Range<usize>, named: bool) {
+        if named {
+            self.labels.push((name, span));
+        }
+
+        if self.flow != Flow::FallsThrough {
+            self.flow = Flow::Unknown;
+        }
+        self.negations = [None; 8];
+    }
+
+    /// Some data that is not meant to be executed
+    pub fn data(&mut self, span: Range<usize>) {
+        if self.flow == Flow::FallsThrough {
+            self.triggered.push((Lint::DataFallthrough, span));
+        }
+
+        self.origin();
+    }
+
+    /// An operation assembled into `word` at `address`
+    pub fn operation(
+        &mut self,
+        operation: &Operation,
+        word: u16,
+        address: u16,
+        span: Range<usize>,
+    ) {
+        // Report only the first operation of an unreachable sequence
+        let reachable = !matches!(self.flow, Flow::Unreachable | Flow::Reported);
+        if self.flow == Flow::Unreachable {
+            self.triggered.push((Lint::UnreachableCode, span.clone()));
+        }
+
+        // A branch without conditions never jumps (unless it's spelled as a
+        // `NOP` on purpose), and a branch to the next operation doesn't change
+        // anything
+        if let Operation::Branch(n, z, p) = *operation {
+            let target = (address & 0xfe00) | (word & 0x1ff);
+            let conditional = n || z || p;
+
+            if (!conditional && !self.source[span.clone()].eq_ignore_ascii_case("NOP"))
+                || (conditional && target == address.wrapping_add(1))
+            {
+                self.triggered.push((Lint::NopBranch, span.clone()));
+            }
+        }
+
+        self.operations.insert(address, span.clone());
+        self.track_negations(operation, word, span);
+
+        self.flow = match *operation {
+            _ if !reachable => Flow::Reported,
+            Operation::Branch(true, true, true)
+            | Operation::Jump(false)
+            | Operation::JumpRegister(false)
+            | Operation::Return
+            | Operation::ReturnInterrupt => Flow::Unreachable,
+            Operation::Trap(_) if word == 0xf025 => Flow::Unreachable,
+            _ => Flow::FallsThrough,
+        };
+    }
+
+    /// Keep track of the registers that contain the negation of another one,
+    /// in order to find the ones added back to it: `NOT R1, R0`,
+    /// `ADD R1, R1, #1` and `ADD R1, R1, R0` always give zero
+    fn track_negations(&mut self, operation: &Operation, word: u16, span: Range<usize>) {
+        let destination = usize::from((word >> 9) & 0b111);
+        let source1 = usize::from((word >> 6) & 0b111);
+
+        let negation = match operation {
+            // `NOT dr, sr` holds the bitwise not of `sr`
+            Operation::Not if destination != source1 => Some(Negation {
+                source: source1,
+                complete: false,
+            }),
+
+            // `ADD dr, sr, #1` completes the negation held by `sr`
+            Operation::Add if word & 0b10_0000 != 0 => match self.negations[source1] {
+                Some(Negation {
+                    source,
+                    complete: false,
+                }) if word & 0b1_1111 == 1 && source != destination => Some(Negation {
+                    source,
+                    complete: true,
+                }),
+                _ => None,
+            },
+
+            // `ADD dr, sr1, sr2` compares the registers if one is the
+            // negation of the other
+            Operation::Add => {
+                let source2 = usize::from(word & 0b111);
+                let negates = |register: usize, source: usize| {
+                    self.negations[register]
+                        == Some(Negation {
+                            source,
+                            complete: true,
+                        })
+                };
+
+                if negates(source1, source2) || negates(source2, source1) {
+                    self.triggered.push((Lint::SelfComparison, span));
+                }
+
+                None
+            }
+
+            // The subroutines and the traps can change any register
+            Operation::Jump(true) | Operation::JumpRegister(true) | Operation::Trap(_) => {
+                self.negations = [None; 8];
+                return;
+            }
+
+            // The other operations that write a register
+            Operation::And
+            | Operation::Not
+            | Operation::Load
+            | Operation::LoadIndirect
+            | Operation::LoadRegister
+            | Operation::LoadEffectiveAddress => None,
+
+            // The operations that don't write any register
+            _ => return,
+        };
+
+        // The destination register was overwritten, so forget everything about
+        // its old value
+        for tracked in &mut self.negations {
+            if tracked.is_some_and(|tracked| tracked.source == destination) {
+                *tracked = None;
+            }
+        }
+        self.negations[destination] = negation;
+    }
+
+    /// A negative number literal that doesn't fit into 16 bits
+    pub fn wrapped_negation(&mut self, span: Range<usize>) {
+        self.triggered.push((Lint::WrappedNegation, span));
+    }
+
+    /// Follow the data flow of the whole program, looking for the registers
+    /// read before being written and for the return address and the
+    /// `callee_saved` registers (a bit mask) overwritten by the subroutines
+    pub fn program(
+        &mut self,
+        words: &[u16],
+        origin: u16,
+        symbol_table: &HashMap<String, u16>,
+        entry_point: Option<u16>,
+        callee_saved: u8,
+    ) {
+        let graph = ControlFlowGraph::new(words, origin, symbol_table, entry_point);
+        let callee_saved: Vec<usize> = (0..8)
+            .filter(|register| callee_saved & (1 << register) != 0)
+            .collect();
+
+        let diagnostics = graph
+            .uninitialized_reads()
+            .into_iter()
+            .chain(graph.diagnostics().iter().cloned())
+            .chain(graph.callee_saved_violations(&callee_saved));
+
+        for diagnostic in diagnostics {
+            let (lint, address) = match diagnostic {
+                FlowDiagnostic::UninitializedRead { address, .. } => {
+                    (Lint::UninitializedRegister, address)
+                }
+                FlowDiagnostic::ClobberedReturnAddress { clobbered, .. } => {
+                    (Lint::ClobberedReturnAddress, clobbered)
+                }
+                FlowDiagnostic::CalleeSavedOverwritten { overwritten, .. } => {
+                    (Lint::CalleeSavedRegister, overwritten)
+                }
+                _ => continue,
+            };
+
+            // Point at the operation, unless it was some data executed by
+            // mistake
+            if let Some(span) = self.operations.get(&address) {
+                self.triggered.push((lint, span.clone()));
+            }
+        }
+    }
+
+    /// Check which of the labels declared so far were never referenced
+    pub fn references(&mut self, referenced: &HashSet<String>) {
+        for (label, span) in self.labels.drain(..) {
+            if !referenced.contains(&label) {
+                self.triggered.push((Lint::UnusedLabel, span));
+            }
+        }
+    }
+
+    /// Return the triggered lints, in the order of the assembly
+    pub fn into_lints(mut self) -> Vec<(Lint, Range<usize>)> {
+        self.triggered.sort_by_key(|(_, span)| span.start);
+        self.triggered.dedup();
+        self.triggered
+    }
+}
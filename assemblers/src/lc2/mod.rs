@@ -1,12 +1,27 @@
 #[cfg(test)]
 mod tests;
 
+pub mod analysis;
+pub mod ast;
+pub mod disassembler;
+mod encoding;
+pub mod formatter;
+pub mod highlight;
+pub mod incremental;
 mod lexer;
+mod linter;
 mod parser;
+pub mod testing;
 
-use crate::ParseError;
-use logos::Logos;
-use std::collections::HashMap;
+pub use encoding::{BytePacking, StringEncoding};
+
+use crate::{
+    include::IncludeResolver,
+    lint::{Lint, LintLevel, Warning},
+    Diagnostic, ParseError,
+};
+use architectures::lc2::Gpr;
+use std::{collections::HashMap, ops::Range, sync::Arc};
 
 #[allow(clippy::module_name_repetitions)]
 #[allow(clippy::struct_excessive_bools)]
@@ -30,6 +45,30 @@ pub struct Lc2Assembler {
 
     #[builder(default = "true")]
     prepend_start_address: bool,
+
+    #[builder(default = "false")]
+    strict_lines: bool,
+
+    #[builder(default = "false")]
+    single_pass: bool,
+
+    #[builder(default = "StringEncoding::Utf8")]
+    string_encoding: StringEncoding,
+
+    #[builder(default = "None", setter(strip_option))]
+    include_resolver: Option<Arc<dyn IncludeResolver>>,
+
+    #[builder(default = "BytePacking::BigEndian")]
+    incbin_packing: BytePacking,
+
+    #[builder(default = "0")]
+    blkw_fill: u16,
+
+    #[builder(default, setter(custom))]
+    lints: HashMap<Lint, LintLevel>,
+
+    #[builder(default = "0", setter(custom))]
+    callee_saved_registers: u8,
 }
 
 // https://github.com/colin-kiegel/rust-derive-builder/issues/56#issuecomment-1043671602
@@ -48,25 +87,363 @@ impl Lc2AssemblerBuilder {
         self.fallible_build()
             .expect("All required fields set at initialization")
     }
+
+    /// Set the registers that the subroutines must save and restore if they
+    /// overwrite them, checked by the `callee_saved_register` lint
+    pub fn callee_saved_registers(
+        &mut self,
+        registers: impl IntoIterator<Item = Gpr>,
+    ) -> &mut Self {
+        self.callee_saved_registers = Some(
+            registers
+                .into_iter()
+                .fold(0, |mask, register| mask | 1 << (u8::from(register) & 0b111)),
+        );
+        self
+    }
+
+    /// Set the level of a lint, overriding its default one
+    pub fn lint(&mut self, lint: Lint, level: LintLevel) -> &mut Self {
+        self.lints
+            .get_or_insert_with(HashMap::new)
+            .insert(lint, level);
+        self
+    }
 }
 
-impl crate::Assembler for Lc2Assembler {
-    type Address = u16;
+/// The result of an assembly
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lc2Output {
+    pub binary: Vec<u8>,
+    /// The address of the first word of the program, after the start address
+    /// prepended to the binary
+    pub origin: u16,
+    pub symbol_table: HashMap<String, u16>,
+    /// The address set by the `.entry` directive or by the operand of the
+    /// `.end` directive, if any
+    pub entry_point: Option<u16>,
+    /// The lints triggered with the `LintLevel::Warn` level, including the
+    /// `.warning` directives
+    pub warnings: Vec<Warning>,
+    /// Every assembled operation, in the order of the assembly
+    pub operations: Vec<AssembledOperation>,
+    /// The words of every data directive, in the order of the assembly
+    pub data: Vec<AssembledData>,
+    /// The test cases declared by the `.test` directives
+    pub tests: Vec<testing::TestCase>,
+}
 
-    fn assemble(
-        &self,
-        assembly: &str,
-    ) -> Result<(Vec<u8>, HashMap<String, Self::Address>), ParseError> {
+/// An operation with its address and its encoding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssembledOperation {
+    pub address: u16,
+    pub word: u16,
+    /// The byte range of the mnemonic
+    pub span: Range<usize>,
+}
+
+/// The words emitted by a pseudo-operation, like `.FILL`, `.STRINGZ` or `.BLKW`,
+/// starting from its address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssembledData {
+    pub address: u16,
+    pub words: Vec<u16>,
+    /// The byte range of the pseudo-operation
+    pub span: Range<usize>,
+}
+
+// The options, so that the bindings can start from the defaults of the builder
+impl Lc2Assembler {
+    #[must_use]
+    pub const fn optional_starting_orig(&self) -> bool {
+        self.optional_starting_orig
+    }
+
+    #[must_use]
+    pub const fn multiple_origs(&self) -> bool {
+        self.multiple_origs
+    }
+
+    #[must_use]
+    pub const fn optional_end(&self) -> bool {
+        self.optional_end
+    }
+
+    #[must_use]
+    pub const fn nothing_after_end(&self) -> bool {
+        self.nothing_after_end
+    }
+
+    #[must_use]
+    pub const fn enable_stringzp(&self) -> bool {
+        self.enable_stringzp
+    }
+
+    #[must_use]
+    pub const fn prepend_start_address(&self) -> bool {
+        self.prepend_start_address
+    }
+
+    #[must_use]
+    pub const fn strict_lines(&self) -> bool {
+        self.strict_lines
+    }
+
+    #[must_use]
+    pub const fn single_pass(&self) -> bool {
+        self.single_pass
+    }
+
+    #[must_use]
+    pub const fn string_encoding(&self) -> StringEncoding {
+        self.string_encoding
+    }
+
+    #[must_use]
+    pub const fn incbin_packing(&self) -> BytePacking {
+        self.incbin_packing
+    }
+
+    #[must_use]
+    pub const fn blkw_fill(&self) -> u16 {
+        self.blkw_fill
+    }
+
+    /// The registers that the subroutines must save and restore
+    pub fn callee_saved_registers(&self) -> impl Iterator<Item = Gpr> {
+        let mask = self.callee_saved_registers;
+        (0..8_usize)
+            .filter(move |register| mask & (1 << register) != 0)
+            .filter_map(|register| Gpr::try_from(register).ok())
+    }
+}
+
+impl Lc2Assembler {
+    /// Return the level of the lint
+    #[must_use]
+    pub fn lint_level(&self, lint: Lint) -> LintLevel {
+        self.lints.get(&lint).copied().unwrap_or_default()
+    }
+
+    /// Return `true` if the data flow of the program must be checked: only if
+    /// one of its lints is enabled and if the program has an entry point or
+    /// some callee-saved registers, since otherwise the analysis would start
+    /// from the origin, which could be data
+    fn data_flow(&self, entry_point: Option<u16>) -> bool {
+        (entry_point.is_some() || self.callee_saved_registers != 0)
+            && [
+                Lint::UninitializedRegister,
+                Lint::ClobberedReturnAddress,
+                Lint::CalleeSavedRegister,
+            ]
+            .into_iter()
+            .any(|lint| self.lint_level(lint) != LintLevel::Allow)
+    }
+
+    /// Assemble like `Assembler::assemble()`, but on error return the line and
+    /// the span of the token that caused it
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the assembly is not valid
+    pub fn assemble_with_diagnostic(&self, assembly: &str) -> Result<Lc2Output, Diagnostic> {
         // Lexer
         log::info!(target: "lc2_assembler", "Tokenizing the assembly...");
-        let mut lexer = lexer::Token::lexer(assembly).peekable();
+        let lexer = lexer::TokenStream::new(assembly, self.strict_lines);
 
-        // Parser
+        self.assemble_tokens(assembly, lexer)
+    }
+
+    /// Parse the tokens in a single pass or in two passes, following the
+    /// `single_pass` option
+    fn assemble_tokens(
+        &self,
+        assembly: &str,
+        lexer: lexer::TokenStream<'_>,
+    ) -> Result<Lc2Output, Diagnostic> {
+        if self.single_pass {
+            return self.assemble_single_pass(assembly, lexer);
+        }
+        let symbol_table = self.symbol_table(assembly, lexer.clone())?;
+        self.assemble_second_pass(assembly, lexer, symbol_table)
+    }
+
+    /// Parse every statement only once, patching the labels used before their
+    /// declaration at the end, then report the checks and the lints
+    fn assemble_single_pass(
+        &self,
+        assembly: &str,
+        mut lexer: lexer::TokenStream<'_>,
+    ) -> Result<Lc2Output, Diagnostic> {
+        log::info!(target: "lc2_assembler", "Assembling the binary in a single pass...");
+        let mut linter = linter::Linter::new(assembly);
+        let (symbol_table, parsed) = parser::assemble_single_pass(self, &mut lexer, &mut linter)
+            .map_err(|(error, span)| Diagnostic::new(assembly, error, span))?;
+
+        self.report(assembly, parsed, symbol_table, linter)
+    }
+
+    /// Run the first pass over the tokens, building the symbol table
+    fn symbol_table(
+        &self,
+        assembly: &str,
+        mut lexer: lexer::TokenStream<'_>,
+    ) -> Result<HashMap<String, u16>, Diagnostic> {
         log::info!(target: "lc2_assembler", "Creating the symbol table...");
-        let symbol_table = parser::build_symbol_table(self, &mut lexer.clone())?;
+        parser::build_symbol_table(self, &mut lexer)
+            .map_err(|error| Diagnostic::new(assembly, error, lexer.span()))
+    }
+
+    /// Run the second pass over the tokens with the symbol table of the first
+    /// one, then report the checks and the lints
+    fn assemble_second_pass(
+        &self,
+        assembly: &str,
+        mut lexer: lexer::TokenStream<'_>,
+        symbol_table: HashMap<String, u16>,
+    ) -> Result<Lc2Output, Diagnostic> {
         log::info!(target: "lc2_assembler", "Assembling the binary...");
-        let binary = parser::assemble(self, &mut lexer, &symbol_table)?;
+        let mut linter = linter::Linter::new(assembly);
+        let parsed = parser::assemble(self, &mut lexer, &symbol_table, &mut linter)
+            .map_err(|error| Diagnostic::new(assembly, error, lexer.span()))?;
+
+        self.report(assembly, parsed, symbol_table, linter)
+    }
+
+    /// Collect the test cases, then report the diagnostics raised by the
+    /// checks and the triggered lints
+    fn report(
+        &self,
+        assembly: &str,
+        parsed: parser::Assembly,
+        symbol_table: HashMap<String, u16>,
+        linter: linter::Linter<'_>,
+    ) -> Result<Lc2Output, Diagnostic> {
+        let parser::Assembly {
+            binary,
+            origin,
+            entry_point,
+            operations,
+            data,
+            tests,
+            diagnostics,
+        } = parsed;
+        let tests = testing::collect(assembly, tests)?;
+
+        // Fail on the first error raised by the checks, then report their
+        // warnings alongside the triggered lints
+        let mut warnings = Vec::new();
+        for (diagnostic, span) in diagnostics {
+            match (diagnostic, self.lint_level(Lint::UserWarning)) {
+                (parser::UserDiagnostic::Error(error), _) => {
+                    return Err(Diagnostic::new(assembly, error, span));
+                }
+                (parser::UserDiagnostic::Warning(_), LintLevel::Allow) => {}
+                (parser::UserDiagnostic::Warning(message), LintLevel::Warn) => {
+                    let warning = Warning {
+                        message: Some(message),
+                        ..Warning::new(assembly, Lint::UserWarning, span)
+                    };
+                    log::warn!(target: "lc2_assembler", "{warning}");
+                    warnings.push(warning);
+                }
+                (parser::UserDiagnostic::Warning(message), LintLevel::Deny) => {
+                    return Err(Diagnostic::new(
+                        assembly,
+                        ParseError::UserError(message),
+                        span,
+                    ));
+                }
+            }
+        }
+
+        // Report the triggered lints, failing on the denied ones
+        for (lint, span) in linter.into_lints() {
+            match self.lint_level(lint) {
+                LintLevel::Allow => {}
+                LintLevel::Warn => {
+                    let warning = Warning::new(assembly, lint, span);
+                    log::warn!(target: "lc2_assembler", "{warning}");
+                    warnings.push(warning);
+                }
+                LintLevel::Deny => {
+                    return Err(Diagnostic::new(
+                        assembly,
+                        ParseError::DeniedLint(lint),
+                        span,
+                    ));
+                }
+            }
+        }
+
+        Ok(Lc2Output {
+            binary,
+            origin,
+            symbol_table,
+            entry_point,
+            warnings,
+            operations,
+            data,
+            tests,
+        })
+    }
+}
+
+impl Lc2Assembler {
+    /// Assemble a syntax tree, like one returned by `ast::parse()` and then
+    /// transformed. The tree is printed back into an assembly, and every span
+    /// of the output is mapped back to the span of the smallest node it falls
+    /// in, while the lines count the lines of the program
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the assembly is not valid
+    pub fn assemble_program(&self, program: &ast::Program) -> Result<Lc2Output, Diagnostic> {
+        let (assembly, source_map) = ast::print(program);
+
+        let mut output = self
+            .assemble_with_diagnostic(&assembly)
+            .map_err(|diagnostic| Diagnostic {
+                span: source_map.span(&diagnostic.span),
+                ..diagnostic
+            })?;
+
+        let spans = output
+            .warnings
+            .iter_mut()
+            .map(|warning| &mut warning.span)
+            .chain(
+                output
+                    .operations
+                    .iter_mut()
+                    .map(|operation| &mut operation.span),
+            )
+            .chain(output.data.iter_mut().map(|data| &mut data.span))
+            .chain(output.tests.iter_mut().flat_map(|test| {
+                std::iter::once(&mut test.span).chain(
+                    test.expectations
+                        .iter_mut()
+                        .map(|expectation| &mut expectation.span),
+                )
+            }));
+        for span in spans {
+            *span = source_map.span(span);
+        }
+
+        Ok(output)
+    }
+}
 
-        Ok((binary, symbol_table))
+impl crate::Assembler for Lc2Assembler {
+    type Address = u16;
+
+    fn assemble(
+        &self,
+        assembly: &str,
+    ) -> Result<(Vec<u8>, HashMap<String, Self::Address>), ParseError> {
+        self.assemble_with_diagnostic(assembly)
+            .map(|output| (output.binary, output.symbol_table))
+            .map_err(|diagnostic| diagnostic.error)
     }
 }
@@ -0,0 +1,147 @@
+use super::{token_operations::ParseResult, RelativeLabel, Token};
+use crate::{
+    lc2::ast::{LabelDeclaration, LabelReference},
+    ParseError,
+};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+};
+
+/// Keeps track of the labels declared so far, in order to give the local,
+/// numeric and anonymous labels the same qualified name in both passes.
+///
+/// The qualified names are the ones stored into the symbol table:
+///   - Global labels keep their name (`main`);
+///   - Local labels are prefixed by the previous global label (`main.loop`);
+///   - Numeric and anonymous labels are suffixed by their declaration index
+///     (`1@0`, `1@1`, `-@0`, `+@0`).
+///
+/// It also keeps the qualified names of the labels used as operands, to find
+/// the ones that are never referenced. The labels of a syntax tree can be
+/// qualified in the same way, visiting the declarations and the references in
+/// the order of the program
+#[derive(Debug, Default, Clone)]
+pub struct LabelScope {
+    global: Option<String>,
+    declarations: HashMap<String, usize>,
+    referenced: RefCell<HashSet<String>>,
+}
+
+impl LabelScope {
+    /// Declare the label of a syntax tree, returning its qualified name
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if a local label is declared before any
+    /// global label
+    pub fn declaration(&mut self, label: &LabelDeclaration) -> Result<String, ParseError> {
+        self.declare(match label {
+            LabelDeclaration::Global(name) => Token::Label(name.clone()),
+            LabelDeclaration::Local(name) => Token::LocalLabel(name.clone()),
+            LabelDeclaration::Numeric(name) => Token::NumericLabel(name.clone()),
+            LabelDeclaration::Anonymous { forward } => Token::RelativeLabel(RelativeLabel {
+                name: (if *forward { "+" } else { "-" }).to_owned(),
+                forward: *forward,
+                distance: 1,
+            }),
+        })
+    }
+
+    /// Return the qualified name of a label referenced by a syntax tree
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if a local label is used before any global
+    /// label, or if a backward label was not declared
+    pub fn reference(&self, label: &LabelReference) -> Result<String, ParseError> {
+        self.resolve(match label {
+            LabelReference::Global(name) => Token::Label(name.clone()),
+            LabelReference::Local(name) => Token::LocalLabel(name.clone()),
+            LabelReference::Numeric { name, forward } => Token::RelativeLabel(RelativeLabel {
+                name: name.clone(),
+                forward: *forward,
+                distance: 1,
+            }),
+            LabelReference::Anonymous { forward, distance } => {
+                Token::RelativeLabel(RelativeLabel {
+                    name: (if *forward { "+" } else { "-" }).to_owned(),
+                    forward: *forward,
+                    distance: *distance,
+                })
+            }
+        })
+    }
+
+    /// Declare a new label, returning its qualified name
+    pub(crate) fn declare(&mut self, token: Token) -> ParseResult<String> {
+        Ok(match token {
+            // A global label opens a new scope for the local labels
+            Token::Label(label) => {
+                self.global = Some(label.clone());
+                label
+            }
+
+            Token::LocalLabel(label) => self.qualify_local(&label)?,
+
+            // Numeric and anonymous labels get the index of their declaration
+            Token::NumericLabel(name) => self.next_declaration(&name),
+            Token::RelativeLabel(label) if label.is_anonymous() && label.distance == 1 => {
+                self.next_declaration(&label.name)
+            }
+
+            _ => return Err(ParseError::UnexpectedToken),
+        })
+    }
+
+    /// Return the qualified name of a label used as an operand
+    pub(crate) fn resolve(&self, token: Token) -> ParseResult<String> {
+        let label = match token {
+            Token::Label(label) => label,
+            Token::LocalLabel(label) => self.qualify_local(&label)?,
+            Token::RelativeLabel(label) => self.qualify_relative(&label)?,
+            _ => return Err(ParseError::UnexpectedToken),
+        };
+
+        self.referenced.borrow_mut().insert(label.clone());
+        Ok(label)
+    }
+
+    /// Return the qualified names of the labels resolved so far
+    pub(crate) fn referenced(&self) -> HashSet<String> {
+        self.referenced.borrow().clone()
+    }
+
+    fn qualify_local(&self, label: &str) -> ParseResult<String> {
+        let global = self
+            .global
+            .as_ref()
+            .ok_or(ParseError::LocalLabelWithoutScope)?;
+
+        Ok(format!("{global}.{label}"))
+    }
+
+    fn qualify_relative(&self, label: &RelativeLabel) -> ParseResult<String> {
+        // Get how many labels with the same name were declared so far
+        let declared = self.declarations.get(&label.name).copied().unwrap_or(0);
+
+        // Count forward from the next declaration or backward from the last one
+        let index = if label.forward {
+            declared + label.distance - 1
+        } else {
+            declared
+                .checked_sub(label.distance)
+                .ok_or(ParseError::LabelNotDeclared)?
+        };
+
+        Ok(format!("{}@{index}", label.name))
+    }
+
+    fn next_declaration(&mut self, name: &str) -> String {
+        let index = self.declarations.entry(name.to_owned()).or_insert(0);
+        let label = format!("{name}@{index}");
+        *index += 1;
+
+        label
+    }
+}
@@ -1,15 +1,77 @@
+mod label_scope;
+mod single_pass;
 mod token_helpers;
 mod token_operations;
 
+pub use label_scope::LabelScope;
+pub use single_pass::assemble_single_pass;
+
 use super::{
-    lexer::{Operation, PseudoOperation, Token},
-    Lc2Assembler,
+    lexer::{
+        parse_number_literal, Comparison, Operation, PseudoOperation, RelativeLabel, StringLiteral,
+        Token, TokenStream,
+    },
+    linter::Linter,
+    testing::{ExpectationKind, TestDirective},
+    AssembledData, AssembledOperation, BytePacking, Lc2Assembler, StringEncoding,
 };
 use crate::ParseError;
-use logos::Lexer;
-use std::{collections::HashMap, iter::Peekable};
+use std::{collections::HashMap, ops::Range};
+use token_helpers::Symbols;
 use token_operations::{ParseResult, TokenOperations};
 
+/// The result of `assemble()` and `assemble_single_pass()`
+pub struct Assembly {
+    pub binary: Vec<u8>,
+    pub origin: u16,
+    pub entry_point: Option<u16>,
+    pub operations: Vec<AssembledOperation>,
+    pub data: Vec<AssembledData>,
+    /// The test directives, in the order of the assembly
+    pub tests: Vec<(TestDirective, Range<usize>)>,
+    /// The diagnostics raised by the checks, in the order of the assembly
+    pub diagnostics: Vec<(UserDiagnostic, Range<usize>)>,
+}
+
+/// A diagnostic raised by an `.assert`, `.error` or `.warning` directive
+pub enum UserDiagnostic {
+    Error(ParseError),
+    Warning(String),
+}
+
+/// An `.assert`, `.error` or `.warning` directive, with the values of its
+/// expressions
+pub enum Check {
+    Assert {
+        left: u16,
+        comparison: Option<(Comparison, u16)>,
+        message: Option<String>,
+    },
+    Error(String),
+    Warning(String),
+}
+
+impl Check {
+    /// Return the diagnostic raised by the check, if any. The expressions of an
+    /// assertion are compared as unsigned words
+    fn diagnostic(self) -> Option<UserDiagnostic> {
+        match self {
+            Self::Assert {
+                left,
+                comparison,
+                message,
+            } => {
+                let holds = comparison.map_or(left != 0, |(comparison, right)| {
+                    comparison.holds(left, right)
+                });
+                (!holds).then_some(UserDiagnostic::Error(ParseError::AssertionFailed(message)))
+            }
+            Self::Error(message) => Some(UserDiagnostic::Error(ParseError::UserError(message))),
+            Self::Warning(message) => Some(UserDiagnostic::Warning(message)),
+        }
+    }
+}
+
 /// The main purpose of this function is to scan the whole assembly in search
 /// of labels declarations, saving them into a `HashMap` alongside the address
 /// they're pointing to.
@@ -26,36 +88,45 @@ use token_operations::{ParseResult, TokenOperations};
 ///     `options.optional_end` is set to `true`);
 ///   - There aren't any more tokens after the `.end` directive (unless
 ///     `options.nothing_after_end` is set to `false`);
+///   - There is only one entry point, set by an `.entry` directive or by the
+///     operand of the `.end` directive;
 ///   - The binary doesn't exceed the maximum size.
 ///
 /// The `assemble()` function assumes that those check are done
 ///
 /// This function consumes the lexer
+#[allow(clippy::too_many_lines)]
 pub fn build_symbol_table(
     options: &Lc2Assembler,
-    lexer: &mut Peekable<Lexer<'_, Token>>,
+    lexer: &mut TokenStream<'_>,
 ) -> ParseResult<HashMap<String, u16>> {
     // Create an empty symbol table
     let mut symbol_table = HashMap::new();
+    let mut scope = LabelScope::default();
 
     // Get the start_address
     log::trace!(target: "lc2_assembler", "Getting the start address...");
-    let mut address = lexer.parse_start_address(options.optional_starting_orig)?;
+    let mut address = lexer.parse_start_address(options)?;
     log::debug!(target: "lc2_assembler", "Start address is {:#06x}!", address);
 
     // For every token...
     let mut end = false;
+    let mut entry_point = false;
     while let Some(token) = lexer.next() {
-        let token = token.map_err(|()| ParseError::NonValidToken)?;
+        let token = token?;
         log::trace!(target: "lc2_assembler", "Got a new token: {:?}!", token);
 
         // Get the lenght of the instruction
         let instruction_lenght: u16 = match token {
-            // Skip the comments
-            Token::Comment(_) => continue,
+            // Skip the comments and the newlines
+            Token::Comment(_) | Token::Newline => continue,
 
             // Add the labels declaration into the symbol table
-            Token::Label(label) => {
+            label @ (Token::Label(_)
+            | Token::LocalLabel(_)
+            | Token::NumericLabel(_)
+            | Token::RelativeLabel(_)) => {
+                let label = scope.declare(label)?;
                 log::debug!(target: "lc2_assembler",
                     "Adding the label \"{}\" at address {:#06x} to the symbol table...",
                     label,
@@ -70,13 +141,14 @@ pub fn build_symbol_table(
                 // Skip the trailing colon, if there is one, and skip to the
                 // next token
                 lexer.skip_token(Token::Colon)?;
+                lexer.parse_end_of_line(true)?;
                 continue;
             }
 
             // In the LC2 architecture every instruction is exactly 1 word, so
             // add 1 to the address
             Token::Operation(x) => {
-                lexer.parse_operation(x, None)?;
+                lexer.parse_operation(x, Symbols::Unavailable, address)?;
                 1
             }
 
@@ -87,30 +159,84 @@ pub fn build_symbol_table(
                     return Err(ParseError::TooManyOrig);
                 }
 
-                lexer.parse_pseudo_operation(PseudoOperation::Orig)?[0]
+                lexer.parse_pseudo_operation(PseudoOperation::Orig, options)?[0]
                     .checked_sub(address)
                     .ok_or(ParseError::OutOfOrderOrigs)?
             }
 
             // If there is a `.end` directive, exit from the loop
             #[allow(unused_assignments)]
-            Token::PseudoOperation(PseudoOperation::End) => {
+            Token::PseudoOperation(x @ PseudoOperation::End) => {
+                if lexer.parse_entry_point(x, Symbols::Unavailable)?.is_some() && entry_point {
+                    return Err(ParseError::TooManyEntryPoints);
+                }
+
                 end = true;
                 break;
             }
 
+            // The entry point doesn't take any space, but there can be only
+            // one
+            Token::PseudoOperation(x @ PseudoOperation::Entry) => {
+                lexer.parse_entry_point(x, Symbols::Unavailable)?;
+                if entry_point {
+                    return Err(ParseError::TooManyEntryPoints);
+                }
+
+                entry_point = true;
+                0
+            }
+
+            // The test directives don't take any space
+            Token::PseudoOperation(x) if x.is_test() => {
+                lexer.parse_test_directive(x, Symbols::Unavailable)?;
+                0
+            }
+
+            // The checks don't take any space either, and they are evaluated
+            // only once the symbol table is complete
+            Token::PseudoOperation(x) if x.is_check() => {
+                lexer.parse_check(x, Symbols::Unavailable)?;
+                0
+            }
+
+            // Pad the binary until the address is aligned
+            Token::PseudoOperation(x @ (PseudoOperation::Align | PseudoOperation::Page)) => {
+                u16::try_from(lexer.parse_padding(x, address, Symbols::Unavailable)?.len())
+                    .map_err(|_| ParseError::BinaryTooBig)?
+            }
+
+            // Get the size of the block, which can depend on the labels
+            // declared so far
+            Token::PseudoOperation(PseudoOperation::Blkw) => u16::try_from(
+                lexer
+                    .parse_block(
+                        options,
+                        (&symbol_table, &scope),
+                        Symbols::Unavailable,
+                        address,
+                    )?
+                    .len(),
+            )
+            .map_err(|_| ParseError::BinaryTooBig)?,
+
             // If the `.stringzp` directive is not enabled return an error
             Token::PseudoOperation(PseudoOperation::Stringzp) if !options.enable_stringzp => {
                 return Err(ParseError::NonValidToken);
             }
 
             // Get pseudo-operations lenght
-            Token::PseudoOperation(x) => u16::try_from(lexer.parse_pseudo_operation(x)?.len())
-                .map_err(|_| ParseError::BinaryTooBig)?,
+            Token::PseudoOperation(x) => {
+                u16::try_from(lexer.parse_pseudo_operation(x, options)?.len())
+                    .map_err(|_| ParseError::BinaryTooBig)?
+            }
 
             _ => return Err(ParseError::UnexpectedToken),
         };
 
+        // Make sure that there is only a statement on the line
+        lexer.parse_end_of_line(false)?;
+
         log::trace!(target: "lc2_assembler",
             "Incrementing the addess by {} cell{}...",
             instruction_lenght,
@@ -135,7 +261,7 @@ pub fn build_symbol_table(
     // `options.nothing_after_end` isn't set then return an error
     if !options.optional_end
         && options.nothing_after_end
-        && lexer.any(|x| !matches!(x, Ok(Token::Comment(_))))
+        && lexer.any(|x| !matches!(x, Ok(Token::Comment(_) | Token::Newline)))
     {
         return Err(ParseError::EndNotLast);
     }
@@ -145,20 +271,24 @@ pub fn build_symbol_table(
 }
 
 /// This function takes the assembly and the symbol table and converts them into
-/// the final binary.
+/// the final binary, returning it alongside its origin, the entry point (if
+/// any), the test directives and the diagnostics raised by the checks.
 ///
 /// It doesn't perform any kind of check except the essential ones, so the
 /// `build_symbol_table()` function needs to be run first
 ///
 /// This function consumes the lexer
+#[allow(clippy::too_many_lines)]
 pub fn assemble(
     options: &Lc2Assembler,
-    lexer: &mut Peekable<Lexer<'_, Token>>,
+    lexer: &mut TokenStream<'_>,
     symbol_table: &HashMap<String, u16>,
-) -> ParseResult<Vec<u8>> {
+    linter: &mut Linter<'_>,
+) -> ParseResult<Assembly> {
     // Get the start_address
     log::trace!(target: "lc2_assembler", "Getting the start address...");
-    let mut address = lexer.parse_start_address(options.optional_starting_orig)?;
+    let mut address = lexer.parse_start_address(options)?;
+    let mut scope = LabelScope::default();
     log::trace!(target: "lc2_assembler", "Start address is {:#06x}!", address);
 
     // Create a new binary and put the start address into it if
@@ -168,17 +298,32 @@ pub fn assemble(
         log::debug!(target: "lc2_assembler", "Putting the start address ({:#x}) into the binary!", address);
         binary.push(address);
     }
+    let (origin, header) = (address, binary.len());
 
     // For every token...
+    let mut entry_point = None;
+    let mut operations = Vec::new();
+    let mut data_words = Vec::new();
+    let mut tests = Vec::new();
+    let mut diagnostics = Vec::new();
     while let Some(token) = lexer.next() {
-        let token = token.map_err(|()| ParseError::NonValidToken)?;
+        let token = token?;
+        let span = lexer.span();
         log::trace!(target: "lc2_assembler", "Got a new token: {:?}!", token);
 
+        // Every pseudo-operation but `.orig` that takes some space is data
+        let data = matches!(&token, Token::PseudoOperation(x) if *x != PseudoOperation::Orig);
+
         // Get the binary representation of the instruction
         let instruction: Vec<u16> = match token {
-            // Skip comments and labels
-            Token::Comment(_) => continue,
-            Token::Label(_) => {
+            // Skip comments, newlines and labels, keeping track of their scope
+            Token::Comment(_) | Token::Newline => continue,
+            label @ (Token::Label(_)
+            | Token::LocalLabel(_)
+            | Token::NumericLabel(_)
+            | Token::RelativeLabel(_)) => {
+                let named = matches!(label, Token::Label(_) | Token::LocalLabel(_));
+                linter.label(scope.declare(label)?, span, named);
                 lexer.skip_token(Token::Colon)?;
                 continue;
             }
@@ -186,28 +331,91 @@ pub fn assemble(
             // Parse operations
             Token::Operation(x) => {
                 log::debug!(target: "lc2_assembler", "Got a new operation: {:02x?}!", x);
-                lexer.parse_operation(x, Some((symbol_table, address)))?
+                let instruction = lexer.parse_operation(
+                    x.clone(),
+                    Symbols::Complete(symbol_table, &scope),
+                    address,
+                )?;
+                linter.operation(&x, instruction[0], address, span.clone());
+                operations.push(AssembledOperation {
+                    address,
+                    word: instruction[0],
+                    span: span.clone(),
+                });
+                instruction
             }
 
             // If there is a new `.orig` directive, add new empty cells until
             // the new address is reached
             Token::PseudoOperation(x @ PseudoOperation::Orig) => {
                 log::debug!(target: "lc2_assembler", "Got a new pseudo-operation: {:?}!", x);
-                let new_address = lexer.parse_pseudo_operation(x)?[0];
+                let new_address = lexer.parse_pseudo_operation(x, options)?[0];
+                linter.origin();
                 vec![0; usize::from(new_address - address)]
             }
 
+            // Pad the binary until the address is aligned, like it was done in
+            // the `build_symbol_table()` function
+            Token::PseudoOperation(x @ (PseudoOperation::Align | PseudoOperation::Page)) => {
+                log::debug!(target: "lc2_assembler", "Got a new pseudo-operation: {x:?}!");
+                lexer.parse_padding(x, address, Symbols::Complete(symbol_table, &scope))?
+            }
+            Token::PseudoOperation(x @ PseudoOperation::Entry) => {
+                log::debug!(target: "lc2_assembler", "Got a new pseudo-operation: {x:?}!");
+                entry_point =
+                    lexer.parse_entry_point(x, Symbols::Complete(symbol_table, &scope))?;
+                Vec::new()
+            }
+            Token::PseudoOperation(x @ PseudoOperation::Blkw) => {
+                log::debug!(target: "lc2_assembler", "Got a new pseudo-operation: {x:?}!");
+                lexer.parse_block(
+                    options,
+                    (symbol_table, &scope),
+                    Symbols::Complete(symbol_table, &scope),
+                    address,
+                )?
+            }
+            Token::PseudoOperation(x) if x.is_test() => {
+                log::debug!(target: "lc2_assembler", "Got a new pseudo-operation: {x:?}!");
+                let directive =
+                    lexer.parse_test_directive(x, Symbols::Complete(symbol_table, &scope))?;
+                tests.push((directive, span.clone()));
+                Vec::new()
+            }
+            Token::PseudoOperation(x) if x.is_check() => {
+                log::debug!(target: "lc2_assembler", "Got a new pseudo-operation: {x:?}!");
+                let check = lexer.parse_check(x, Symbols::Complete(symbol_table, &scope))?;
+                if let Some(diagnostic) = check.diagnostic() {
+                    diagnostics.push((diagnostic, span.clone()));
+                }
+                Vec::new()
+            }
+
             // Parse pseudo-operations. Exit the loop if it encounters a `.end`
             // directive
-            Token::PseudoOperation(PseudoOperation::End) => break,
+            Token::PseudoOperation(x @ PseudoOperation::End) => {
+                let end_entry_point =
+                    lexer.parse_entry_point(x, Symbols::Complete(symbol_table, &scope))?;
+                entry_point = entry_point.or(end_entry_point);
+                break;
+            }
             Token::PseudoOperation(x) => {
                 log::debug!(target: "lc2_assembler", "Got a new pseudo-operation: {:?}!", x);
-                lexer.parse_pseudo_operation(x)?
+                lexer.parse_pseudo_operation(x, options)?
             }
 
             _ => return Err(ParseError::UnexpectedToken),
         };
 
+        if data && !instruction.is_empty() {
+            linter.data(span.clone());
+            data_words.push(AssembledData {
+                address,
+                words: instruction.clone(),
+                span,
+            });
+        }
+
         log::trace!(target: "lc2_assembler",
             "Incrementing the binary by {} cell{}...",
             instruction.len(),
@@ -221,6 +429,30 @@ pub fn assemble(
         log::trace!(target: "lc2_assembler", "The new binary is {} bytes long!", binary.len() * 2);
     }
 
+    // Check the labels that were never referenced, the number literals that
+    // wrapped around and the data flow of the registers
+    linter.references(&scope.referenced());
+    for span in lexer.wrapped_numbers() {
+        linter.wrapped_negation(span.clone());
+    }
+    if options.data_flow(entry_point) {
+        linter.program(
+            &binary[header..],
+            origin,
+            symbol_table,
+            entry_point,
+            options.callee_saved_registers,
+        );
+    }
+
     // Convert the vector of words into a vector of bytes and return it
-    Ok(binary.iter().flat_map(|x| x.to_be_bytes()).collect())
+    Ok(Assembly {
+        binary: binary.iter().flat_map(|x| x.to_be_bytes()).collect(),
+        origin,
+        entry_point,
+        operations,
+        data: data_words,
+        tests,
+        diagnostics,
+    })
 }
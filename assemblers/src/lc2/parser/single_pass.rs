@@ -0,0 +1,550 @@
+use super::{
+    label_scope::LabelScope,
+    token_helpers::{offset9, page_offset, Symbols},
+    token_operations::{ParseResult, TokenOperations},
+    AssembledData, AssembledOperation, Assembly, Check, ExpectationKind, Lc2Assembler, Linter,
+    Operation, PseudoOperation, TestDirective, Token, TokenStream,
+};
+use crate::ParseError;
+use std::{cell::RefCell, collections::HashMap, ops::Range};
+
+/// A result whose error is located at a span of the source
+pub type LocatedResult<T> = Result<T, (ParseError, Range<usize>)>;
+
+/// The labels not declared yet, recorded by the parser while reading a
+/// statement with a partial symbol table
+#[derive(Debug, Default)]
+pub struct Fixups {
+    /// The forward references of every expression of the statement, in order
+    pub expressions: Vec<Expression>,
+    /// The forward reference of the pgoffset9 operand of an operation
+    pub offset: Option<Offset>,
+    /// The errors found resolving the labels
+    pub errors: Vec<(ParseError, Range<usize>)>,
+}
+
+impl Fixups {
+    /// Record the error of a label resolution, if any, resolving it as 0. Like
+    /// the errors of the second pass, it's reported only if the rest of the
+    /// assembly is valid
+    pub fn defer(&mut self, value: ParseResult<u16>, span: Range<usize>) -> u16 {
+        value.unwrap_or_else(|error| {
+            self.errors.push((error, span));
+            0
+        })
+    }
+}
+
+/// A label used before its declaration, with its qualified name
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub label: String,
+    pub span: Range<usize>,
+}
+
+/// The labels of an expression that were not declared yet, left out of its
+/// value
+#[derive(Debug, Default)]
+pub struct Expression {
+    pub added: Vec<Reference>,
+    pub subtracted: Vec<Reference>,
+}
+
+impl Expression {
+    const fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.subtracted.is_empty()
+    }
+
+    /// Return the value that is missing from the expression, or the error of
+    /// its first label that was never declared
+    fn resolve(&self, symbol_table: &HashMap<String, u16>) -> LocatedResult<u16> {
+        if let Some(reference) = self
+            .added
+            .iter()
+            .chain(&self.subtracted)
+            .filter(|reference| !symbol_table.contains_key(&reference.label))
+            .min_by_key(|reference| reference.span.start)
+        {
+            return Err((ParseError::LabelNotDeclared, reference.span.clone()));
+        }
+
+        let address = |reference: &Reference| symbol_table[&reference.label];
+        let added = self.added.iter().map(address).fold(0, u16::wrapping_add);
+        Ok(self
+            .subtracted
+            .iter()
+            .map(address)
+            .fold(added, u16::wrapping_sub))
+    }
+}
+
+/// A pgoffset9 operand that uses a label not declared yet
+#[derive(Debug)]
+pub struct Offset {
+    pub reference: Reference,
+    /// The value of the label if it's also a number literal (like `x10`), used
+    /// if it's never declared
+    pub literal: Option<u16>,
+    /// The address of the operation
+    pub address: u16,
+}
+
+impl Offset {
+    /// Return the lower 9 bits of the operand
+    fn resolve(&self, symbol_table: &HashMap<String, u16>) -> LocatedResult<u16> {
+        match (symbol_table.get(&self.reference.label), self.literal) {
+            (Some(&label_address), _) => page_offset(label_address, self.address),
+            (None, Some(literal)) => offset9(literal),
+            (None, None) => Err(ParseError::LabelNotDeclared),
+        }
+        .map_err(|error| (error, self.reference.span.clone()))
+    }
+}
+
+/// Where the value of an expression with forward references goes
+#[derive(Debug, Clone)]
+enum Target {
+    /// The words of the binary filled by a `.blkw`, `.align` or `.page`
+    Words(Range<usize>),
+    EntryPoint,
+    /// The value of a test directive
+    Test(usize),
+    /// An assertion
+    Check(usize),
+}
+
+/// An expression with forward references, to be added to the value parsed
+/// without them
+#[derive(Debug)]
+struct Fixup {
+    target: Target,
+    /// The index of the expression in its statement, like 0 for the left side
+    /// of an assertion and 1 for its right side
+    operand: usize,
+    expression: Expression,
+}
+
+/// What the linter needs to know about a statement, told once the binary is
+/// patched
+#[derive(Debug)]
+enum Event {
+    Label(String, Range<usize>, bool),
+    Operation(Operation, u16, Range<usize>),
+    Origin,
+    /// The span, the address and the number of words of a data directive
+    Data(Range<usize>, u16, u16),
+}
+
+/// The assembly read by `parse()`, with the forward references still to be
+/// patched
+struct Parsed {
+    symbol_table: HashMap<String, u16>,
+    scope: LabelScope,
+    binary: Vec<u16>,
+    origin: u16,
+    /// The number of words before the origin, like the start address
+    header: usize,
+    entry_point: Option<u16>,
+    tests: Vec<(TestDirective, Range<usize>)>,
+    checks: Vec<(Check, Range<usize>)>,
+    events: Vec<Event>,
+    fixups: Vec<Fixup>,
+    offsets: Vec<Offset>,
+    errors: Vec<(ParseError, Range<usize>)>,
+    wrapped_numbers: Vec<Range<usize>>,
+}
+
+/// Assemble the tokens in a single pass, reading every statement only once.
+/// The labels used before their declaration are resolved as 0 and recorded as
+/// fixups, which are patched once the symbol table is complete.
+///
+/// It validates the assembly like `build_symbol_table()`, and it returns the
+/// same symbol table and the same `Assembly` of `assemble()`. Like them, it
+/// returns the first error of the assembly, reporting the errors found
+/// resolving the labels only if the rest of the assembly is valid
+///
+/// This function consumes the lexer
+pub fn assemble_single_pass(
+    options: &Lc2Assembler,
+    lexer: &mut TokenStream<'_>,
+    linter: &mut Linter<'_>,
+) -> LocatedResult<(HashMap<String, u16>, Assembly)> {
+    let parsed = parse(options, lexer).map_err(|error| (error, lexer.span()))?;
+    parsed.patch(options, linter)
+}
+
+/// Read the statements, building the symbol table and the binary with the
+/// labels declared so far
+#[allow(clippy::too_many_lines)]
+fn parse(options: &Lc2Assembler, lexer: &mut TokenStream<'_>) -> ParseResult<Parsed> {
+    // Get the start_address
+    log::trace!(target: "lc2_assembler", "Getting the start address...");
+    let mut address = lexer.parse_start_address(options)?;
+    log::debug!(target: "lc2_assembler", "Start address is {:#06x}!", address);
+
+    // Create a new binary and put the start address into it if
+    // `options.prepend_start_address` is set
+    let mut binary = Vec::new();
+    if !options.optional_starting_orig && options.prepend_start_address {
+        binary.push(address);
+    }
+
+    let mut parsed = Parsed {
+        symbol_table: HashMap::new(),
+        scope: LabelScope::default(),
+        header: binary.len(),
+        binary,
+        origin: address,
+        entry_point: None,
+        tests: Vec::new(),
+        checks: Vec::new(),
+        events: Vec::new(),
+        fixups: Vec::new(),
+        offsets: Vec::new(),
+        errors: Vec::new(),
+        wrapped_numbers: Vec::new(),
+    };
+    let fixups = RefCell::new(Fixups::default());
+
+    // For every token...
+    let mut end = false;
+    while let Some(token) = lexer.next() {
+        let token = token?;
+        let span = lexer.span();
+        let symbols = Symbols::Partial(&parsed.symbol_table, &parsed.scope, &fixups);
+        log::trace!(target: "lc2_assembler", "Got a new token: {:?}!", token);
+
+        // Every pseudo-operation but `.orig` that takes some space is data
+        let data = matches!(&token, Token::PseudoOperation(x) if *x != PseudoOperation::Orig);
+
+        // Where the values of the forward references of the statement go
+        let mut target = None;
+
+        let words: Vec<u16> = match token {
+            // Skip the comments and the newlines
+            Token::Comment(_) | Token::Newline => continue,
+
+            // Add the labels declaration into the symbol table
+            label @ (Token::Label(_)
+            | Token::LocalLabel(_)
+            | Token::NumericLabel(_)
+            | Token::RelativeLabel(_)) => {
+                let named = matches!(label, Token::Label(_) | Token::LocalLabel(_));
+                let label = parsed.scope.declare(label)?;
+                log::debug!(target: "lc2_assembler",
+                    "Adding the label \"{}\" at address {:#06x} to the symbol table...",
+                    label,
+                    address
+                );
+
+                // Return an error if the label was already defined
+                if parsed.symbol_table.insert(label.clone(), address).is_some() {
+                    return Err(ParseError::LabelRedeclaration);
+                }
+                parsed.events.push(Event::Label(label, span, named));
+
+                // Skip the trailing colon, if there is one, and skip to the
+                // next token
+                lexer.skip_token(Token::Colon)?;
+                lexer.parse_end_of_line(true)?;
+                continue;
+            }
+
+            Token::Operation(x) => {
+                log::debug!(target: "lc2_assembler", "Got a new operation: {:02x?}!", x);
+                let words = lexer.parse_operation(x.clone(), symbols, address)?;
+                parsed
+                    .events
+                    .push(Event::Operation(x, address, span.clone()));
+                words
+            }
+
+            // If there is another `.orig` directive and the
+            // `options.multiple_origs` is set then add new empty cells until
+            // the new address is reached
+            Token::PseudoOperation(x @ PseudoOperation::Orig) => {
+                if !options.multiple_origs {
+                    return Err(ParseError::TooManyOrig);
+                }
+
+                let padding = lexer.parse_pseudo_operation(x, options)?[0]
+                    .checked_sub(address)
+                    .ok_or(ParseError::OutOfOrderOrigs)?;
+                parsed.events.push(Event::Origin);
+                vec![0; usize::from(padding)]
+            }
+
+            // There can be only one entry point, set by an `.entry` directive
+            // or by the operand of the `.end` directive, which ends the loop
+            Token::PseudoOperation(x @ (PseudoOperation::Entry | PseudoOperation::End)) => {
+                end = x == PseudoOperation::End;
+                let entry_point = lexer.parse_entry_point(x, symbols)?;
+                if entry_point.is_some() {
+                    if parsed.entry_point.is_some() {
+                        return Err(ParseError::TooManyEntryPoints);
+                    }
+
+                    parsed.entry_point = entry_point;
+                    target = Some(Target::EntryPoint);
+                }
+
+                Vec::new()
+            }
+
+            // The test directives and the checks don't take any space
+            Token::PseudoOperation(x) if x.is_test() => {
+                let directive = lexer.parse_test_directive(x, symbols)?;
+                target = Some(Target::Test(parsed.tests.len()));
+                parsed.tests.push((directive, span.clone()));
+                Vec::new()
+            }
+            Token::PseudoOperation(x) if x.is_check() => {
+                let check = lexer.parse_check(x, symbols)?;
+                target = Some(Target::Check(parsed.checks.len()));
+                parsed.checks.push((check, span.clone()));
+                Vec::new()
+            }
+
+            // Pad the binary until the address is aligned
+            Token::PseudoOperation(x @ (PseudoOperation::Align | PseudoOperation::Page)) => {
+                let words = lexer.parse_padding(x, address, symbols)?;
+                let start = parsed.binary.len();
+                target = Some(Target::Words(start..start + words.len()));
+                words
+            }
+
+            // Get the size of the block, which can depend on the labels
+            // declared so far
+            Token::PseudoOperation(PseudoOperation::Blkw) => {
+                let words = lexer.parse_block(
+                    options,
+                    (&parsed.symbol_table, &parsed.scope),
+                    symbols,
+                    address,
+                )?;
+                let start = parsed.binary.len();
+                target = Some(Target::Words(start..start + words.len()));
+                words
+            }
+
+            // If the `.stringz` directive is not enabled return an error
+            Token::PseudoOperation(PseudoOperation::Stringzp) if !options.enable_stringzp => {
+                return Err(ParseError::NonValidToken);
+            }
+
+            Token::PseudoOperation(x) => {
+                log::debug!(target: "lc2_assembler", "Got a new pseudo-operation: {:?}!", x);
+                lexer.parse_pseudo_operation(x, options)?
+            }
+
+            _ => return Err(ParseError::UnexpectedToken),
+        };
+
+        // Keep the forward references of the statement and the errors found
+        // resolving its labels
+        let Fixups {
+            expressions,
+            offset,
+            errors,
+        } = fixups.take();
+        parsed.errors.extend(errors);
+        parsed.offsets.extend(offset);
+        if let Some(target) = target {
+            parsed.fixups.extend(
+                expressions
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, expression)| !expression.is_empty())
+                    .map(|(operand, expression)| Fixup {
+                        target: target.clone(),
+                        operand,
+                        expression,
+                    }),
+            );
+        }
+
+        if end {
+            break;
+        }
+
+        // Make sure that there is only a statement on the line
+        let length = u16::try_from(words.len()).map_err(|_| ParseError::BinaryTooBig)?;
+        lexer.parse_end_of_line(false)?;
+
+        if data && !words.is_empty() {
+            parsed.events.push(Event::Data(span, address, length));
+        }
+
+        // Update the address and extend the binary
+        address = address
+            .checked_add(length)
+            .ok_or(ParseError::BinaryTooBig)?;
+        parsed.binary.extend(words);
+    }
+
+    // The number literals after the end of the program are not assembled
+    parsed.wrapped_numbers = lexer.wrapped_numbers().to_vec();
+
+    // If there wasn't any `.end` directive and the `options.optional_end` isn't
+    // set then return an error
+    if !end && !options.optional_end {
+        return Err(ParseError::NoEnd);
+    }
+
+    // If there was an `.end` directive but it was't the last token and the
+    // `options.nothing_after_end` isn't set then return an error
+    if !options.optional_end
+        && options.nothing_after_end
+        && lexer.any(|x| !matches!(x, Ok(Token::Comment(_) | Token::Newline)))
+    {
+        return Err(ParseError::EndNotLast);
+    }
+
+    Ok(parsed)
+}
+
+impl Parsed {
+    /// Patch the forward references with the complete symbol table, then
+    /// tell the statements to the linter
+    #[allow(clippy::too_many_lines)]
+    fn patch(
+        self,
+        options: &Lc2Assembler,
+        linter: &mut Linter<'_>,
+    ) -> LocatedResult<(HashMap<String, u16>, Assembly)> {
+        let Self {
+            symbol_table,
+            scope,
+            mut binary,
+            origin,
+            header,
+            mut entry_point,
+            mut tests,
+            mut checks,
+            events,
+            fixups,
+            offsets,
+            mut errors,
+            wrapped_numbers,
+        } = self;
+        let index = |address: u16| header + usize::from(address - origin);
+
+        // Add the addresses of the forward references to the values parsed
+        // without them
+        log::trace!(target: "lc2_assembler", "Patching {} fixups...", fixups.len() + offsets.len());
+        for Fixup {
+            target,
+            operand,
+            expression,
+        } in fixups
+        {
+            let value = match expression.resolve(&symbol_table) {
+                Ok(value) => value,
+                Err(error) => {
+                    errors.push(error);
+                    continue;
+                }
+            };
+
+            let word = match target {
+                Target::Words(range) => {
+                    for word in &mut binary[range] {
+                        *word = word.wrapping_add(value);
+                    }
+                    continue;
+                }
+                Target::EntryPoint => entry_point.as_mut(),
+                Target::Test(index) => match &mut tests[index].0 {
+                    TestDirective::SetRegister(_, word)
+                    | TestDirective::Expect(ExpectationKind::Register(_, word)) => Some(word),
+                    _ => None,
+                },
+                Target::Check(index) => match &mut checks[index].0 {
+                    Check::Assert { left, .. } if operand == 0 => Some(left),
+                    Check::Assert {
+                        comparison: Some((_, right)),
+                        ..
+                    } => Some(right),
+                    _ => None,
+                },
+            };
+
+            if let Some(word) = word {
+                *word = word.wrapping_add(value);
+            }
+        }
+        for offset in &offsets {
+            match offset.resolve(&symbol_table) {
+                Ok(bits) => binary[index(offset.address)] |= bits,
+                Err(error) => errors.push(error),
+            }
+        }
+
+        // Return the first error, like the second pass would do
+        if let Some(error) = errors.into_iter().min_by_key(|(_, span)| span.start) {
+            return Err(error);
+        }
+
+        // Lint the statements in the order of the assembly
+        let mut operations = Vec::new();
+        let mut data = Vec::new();
+        for event in events {
+            match event {
+                Event::Label(label, span, named) => linter.label(label, span, named),
+                Event::Operation(operation, address, span) => {
+                    let word = binary[index(address)];
+                    linter.operation(&operation, word, address, span.clone());
+                    operations.push(AssembledOperation {
+                        address,
+                        word,
+                        span,
+                    });
+                }
+                Event::Origin => linter.origin(),
+                Event::Data(span, address, length) => {
+                    linter.data(span.clone());
+                    let start = index(address);
+                    data.push(AssembledData {
+                        address,
+                        words: binary[start..start + usize::from(length)].to_vec(),
+                        span,
+                    });
+                }
+            }
+        }
+
+        // Check the labels that were never referenced, the number literals that
+        // wrapped around and the data flow of the registers
+        linter.references(&scope.referenced());
+        for span in wrapped_numbers {
+            linter.wrapped_negation(span);
+        }
+        if options.data_flow(entry_point) {
+            linter.program(
+                &binary[header..],
+                origin,
+                &symbol_table,
+                entry_point,
+                options.callee_saved_registers,
+            );
+        }
+
+        let diagnostics = checks
+            .into_iter()
+            .filter_map(|(check, span)| Some((check.diagnostic()?, span)))
+            .collect();
+
+        Ok((
+            symbol_table,
+            Assembly {
+                binary: binary.iter().flat_map(|x| x.to_be_bytes()).collect(),
+                origin,
+                entry_point,
+                operations,
+                data,
+                tests,
+                diagnostics,
+            },
+        ))
+    }
+}
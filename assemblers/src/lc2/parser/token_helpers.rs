@@ -1,7 +1,29 @@
-use super::{ParseResult, Token};
+use super::{
+    label_scope::LabelScope,
+    parse_number_literal,
+    single_pass::{Expression, Fixups, Offset, Reference},
+    ParseResult, RelativeLabel, StringEncoding, StringLiteral, Token, TokenStream,
+};
 use crate::ParseError;
-use logos::Lexer;
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::HashMap};
+
+/// The symbol table and the scope used to resolve the labels of an expression
+#[derive(Clone, Copy)]
+pub enum Symbols<'a> {
+    /// The symbol table is not available, like while building it, so every
+    /// label is resolved as 0
+    Unavailable,
+    /// Every label must be in the symbol table
+    Complete(&'a HashMap<String, u16>, &'a LabelScope),
+    /// The symbol table contains only the labels declared so far, like while
+    /// assembling in a single pass. The other labels are resolved as 0 and
+    /// recorded into the `Fixups`, alongside the errors found resolving them
+    Partial(
+        &'a HashMap<String, u16>,
+        &'a LabelScope,
+        &'a RefCell<Fixups>,
+    ),
+}
 
 pub trait TokenHelpers {
     fn peek_next_token(&mut self) -> ParseResult<&Token>;
@@ -10,28 +32,40 @@ pub trait TokenHelpers {
     fn get_register(&mut self) -> ParseResult<u16>;
     fn get_register_skip_comma(&mut self) -> ParseResult<u16>;
     fn get_index6(&mut self) -> ParseResult<u16>;
+    fn get_string(
+        &mut self,
+        encoding: StringEncoding,
+    ) -> ParseResult<(StringLiteral, StringEncoding)>;
+    fn get_number_list(&mut self) -> ParseResult<Vec<u16>>;
+    fn get_expression(&mut self, symbol_table: Symbols) -> ParseResult<u16>;
+    fn get_term(&mut self, symbol_table: Symbols, forward: &mut Vec<Reference>)
+        -> ParseResult<u16>;
+    fn get_fill_word(&mut self, symbol_table: Symbols, comma: bool) -> ParseResult<Option<u16>>;
     fn get_pgoffset9(
         &mut self,
-        symbol_table: Option<(&HashMap<String, u16>, u16)>,
+        symbol_table: Symbols,
+        address: u16,
         skip_comma: bool,
     ) -> ParseResult<u16>;
 }
 
-impl TokenHelpers for std::iter::Peekable<Lexer<'_, Token>> {
+impl TokenHelpers for TokenStream<'_> {
     /// Return a reference to the next token without consuming it
     fn peek_next_token(&mut self) -> ParseResult<&Token> {
         match self.peek() {
             Some(Ok(token)) => Ok(token),
-            Some(Err(_error)) => Err(ParseError::NonValidToken),
+            Some(Err(error)) => Err(error.clone()),
             None => Err(ParseError::NoMoreTokens),
         }
     }
 
-    /// Consume the next token
+    /// Consume the next token. Since it's always called to get an operand, a
+    /// newline means that the operand is missing
     fn next_token(&mut self) -> ParseResult<Token> {
         match self.next() {
+            Some(Ok(Token::Newline)) => Err(ParseError::MissingOperand),
             Some(Ok(token)) => Ok(token),
-            Some(Err(_error)) => Err(ParseError::NonValidToken),
+            Some(Err(error)) => Err(error),
             None => Err(ParseError::NoMoreTokens),
         }
     }
@@ -83,11 +117,174 @@ impl TokenHelpers for std::iter::Peekable<Lexer<'_, Token>> {
         })
     }
 
+    /// Return the next string alongside its encoding: the `encoding` one, unless
+    /// it's followed by a comma and the name of another encoding
+    fn get_string(
+        &mut self,
+        encoding: StringEncoding,
+    ) -> ParseResult<(StringLiteral, StringEncoding)> {
+        let Token::String(string) = self.next_token()? else {
+            return Err(ParseError::UnexpectedToken);
+        };
+
+        // Check if there is an encoding override
+        if self.peek_next_token() != Ok(&Token::Comma) {
+            return Ok((string, encoding));
+        }
+        self.next_token()?;
+
+        match self.next_token()? {
+            Token::Label(name) => Ok((string, StringEncoding::try_from(name.as_str())?)),
+            _ => Err(ParseError::UnexpectedToken),
+        }
+    }
+
+    /// Return the next comma separated numbers
+    fn get_number_list(&mut self) -> ParseResult<Vec<u16>> {
+        let mut numbers = Vec::new();
+
+        loop {
+            let Token::Number(number) = self.next_token()? else {
+                return Err(ParseError::UnexpectedToken);
+            };
+            numbers.push(number);
+
+            // Stop if there isn't another number
+            if self.peek_next_token() != Ok(&Token::Comma) {
+                return Ok(numbers);
+            }
+            self.next_token()?;
+        }
+    }
+
+    /// Return the value of the next expression: a sequence of numbers and
+    /// labels, added or subtracted from each other (like `end - start + 1`).
+    ///
+    /// The arithmetic wraps around like the 16 bit registers do, and the
+    /// expression ends at the end of the line. With a partial symbol table the
+    /// labels not declared yet are left out of the value, and the expression
+    /// is recorded with them even if there are none
+    fn get_expression(&mut self, symbol_table: Symbols) -> ParseResult<u16> {
+        let mut forward = Expression::default();
+        let mut value = self.get_term(symbol_table, &mut forward.added)?;
+
+        while self.next_on_same_line() {
+            // A negative number after a term (like `end -1` or `end-1`) is lexed
+            // as a single token, and it's subtracted like `end - 1`
+            let negative_number = self.slice().trim_start_matches('#').starts_with('-');
+
+            value = match self.peek() {
+                // The `+` and `-` are lexed as anonymous labels
+                Some(Ok(Token::RelativeLabel(label)))
+                    if label.is_anonymous() && label.distance == 1 =>
+                {
+                    let forward_label = label.forward;
+                    self.next_token()?;
+
+                    if forward_label {
+                        value.wrapping_add(self.get_term(symbol_table, &mut forward.added)?)
+                    } else {
+                        value.wrapping_sub(self.get_term(symbol_table, &mut forward.subtracted)?)
+                    }
+                }
+
+                Some(Ok(Token::Number(_))) if negative_number => {
+                    value.wrapping_add(self.get_term(symbol_table, &mut forward.added)?)
+                }
+
+                _ => break,
+            };
+        }
+
+        if let Symbols::Partial(.., fixups) = symbol_table {
+            fixups.borrow_mut().expressions.push(forward);
+        }
+
+        Ok(value)
+    }
+
+    /// Return the value of the next number or label. With a partial symbol
+    /// table, a label not declared yet is pushed into `forward` and resolved
+    /// as 0
+    fn get_term(
+        &mut self,
+        symbol_table: Symbols,
+        forward: &mut Vec<Reference>,
+    ) -> ParseResult<u16> {
+        match self.next_token()? {
+            Token::Number(number) => Ok(number),
+
+            label @ (Token::Label(_) | Token::LocalLabel(_) | Token::RelativeLabel(_)) => {
+                // The anonymous labels are operators, not operands
+                if matches!(&label, Token::RelativeLabel(label) if label.is_anonymous()) {
+                    return Err(ParseError::UnexpectedToken);
+                }
+
+                match symbol_table {
+                    // If the symbol table is not available return 0
+                    Symbols::Unavailable => Ok(0),
+
+                    Symbols::Complete(symbol_table, scope) => symbol_table
+                        .get(&scope.resolve(label)?)
+                        .copied()
+                        .ok_or(ParseError::LabelNotDeclared),
+
+                    Symbols::Partial(symbol_table, scope, fixups) => {
+                        let span = self.span();
+                        let value = scope.resolve(label).map(|label| {
+                            symbol_table.get(&label).copied().unwrap_or_else(|| {
+                                forward.push(Reference {
+                                    label,
+                                    span: span.clone(),
+                                });
+                                0
+                            })
+                        });
+
+                        Ok(fixups.borrow_mut().defer(value, span))
+                    }
+                }
+            }
+
+            _ => Err(ParseError::UnexpectedToken),
+        }
+    }
+
+    /// Return the optional expression used to fill a block of memory, which
+    /// can be preceded by a comma. If `comma` is set the comma is required,
+    /// since the word follows another operand (`.blkw 2, -1` is not `.blkw
+    /// 2 -1`)
+    fn get_fill_word(&mut self, symbol_table: Symbols, comma: bool) -> ParseResult<Option<u16>> {
+        // Without a comma the word must be on the same line, or it could be the
+        // label of the next statement
+        if !self.next_on_same_line() {
+            return Ok(None);
+        }
+
+        match self.peek() {
+            // If the next token is a comma, it must be followed by a word
+            Some(Ok(Token::Comma)) => {
+                self.next_token()?;
+                self.get_expression(symbol_table).map(Some)
+            }
+
+            Some(Ok(
+                Token::Number(_)
+                | Token::Label(_)
+                | Token::LocalLabel(_)
+                | Token::RelativeLabel(RelativeLabel { distance: 1, .. }),
+            )) if !comma => self.get_expression(symbol_table).map(Some),
+
+            _ => Ok(None),
+        }
+    }
+
     /// Return the next 9 bit positive integer or the lower 9 bits of the next
-    /// label
+    /// label, which must be on the same page of `address`
     fn get_pgoffset9(
         &mut self,
-        symbol_table: Option<(&HashMap<String, u16>, u16)>,
+        symbol_table: Symbols,
+        address: u16,
         skip_comma: bool,
     ) -> ParseResult<u16> {
         // Get the next token
@@ -97,50 +294,89 @@ impl TokenHelpers for std::iter::Peekable<Lexer<'_, Token>> {
             self.next_token()?
         };
 
+        // If the label was never declared but it's also a number literal (like
+        // `x10`) then read it as a number
+        let token = match (token, symbol_table) {
+            (Token::Label(label), Symbols::Complete(symbol_table, _))
+                if !symbol_table.contains_key(&label) =>
+            {
+                parse_number_literal(&label).map_or(Token::Label(label), Token::Number)
+            }
+            (token, _) => token,
+        };
+
         // Check if it is a number or a label
-        Ok(match token {
-            // If it is a number...
-            Token::Number(number) => {
-                // Check if it's negative
-                if number >> 15 == 1 {
-                    return Err(ParseError::NumberLiteralIsNegative);
-                }
-                // Check if it's bigger than 9 bit
-                else if number >> 9 != 0 {
-                    return Err(ParseError::NumberLiteralTooBig);
-                }
+        match token {
+            Token::Number(number) => offset9(number),
 
-                number
-            }
+            label @ (Token::Label(_) | Token::LocalLabel(_) | Token::RelativeLabel(_)) => {
+                match symbol_table {
+                    // Check if we are in the assembly phase. If the symbol
+                    // table is not available return 0
+                    Symbols::Unavailable => Ok(0),
 
-            // If it is a label...
-            Token::Label(label) => {
-                // Check if we are in the assembly phase. If the symbol table is not
-                // available return 0
-                let Some((symbol_table, address)) = symbol_table else {
-                    return Ok(0);
-                };
-
-                // Check if the label is present in the symbol table
-                match symbol_table.get(&label) {
-                    // If it's not present return an error
-                    None => return Err(ParseError::LabelNotDeclared),
-
-                    // Else if it's present...
-                    Some(label_address) => {
-                        // Check if the label is on the same memory page as the
-                        // instruction
-                        if label_address & 0xfe00 != address & 0xfe00 {
-                            return Err(ParseError::LabelNotOnSamePage);
-                        }
-
-                        // Return the last 9 bits of the label address
-                        label_address & 0x1ff
+                    // Get the qualified name of the label, as stored in the
+                    // symbol table, and check if the label is present in it
+                    Symbols::Complete(symbol_table, scope) => symbol_table
+                        .get(&scope.resolve(label)?)
+                        .ok_or(ParseError::LabelNotDeclared)
+                        .and_then(|&label_address| page_offset(label_address, address)),
+
+                    // A label not declared yet could still be a number literal,
+                    // which is known only once the symbol table is complete
+                    Symbols::Partial(symbol_table, scope, fixups) => {
+                        let span = self.span();
+                        let literal = match &label {
+                            Token::Label(label) => parse_number_literal(label),
+                            _ => None,
+                        };
+                        let offset = scope.resolve(label).and_then(|label| {
+                            symbol_table.get(&label).map_or_else(
+                                || {
+                                    fixups.borrow_mut().offset = Some(Offset {
+                                        reference: Reference {
+                                            label,
+                                            span: span.clone(),
+                                        },
+                                        literal,
+                                        address,
+                                    });
+                                    Ok(0)
+                                },
+                                |&label_address| page_offset(label_address, address),
+                            )
+                        });
+
+                        Ok(fixups.borrow_mut().defer(offset, span))
                     }
                 }
             }
 
-            _ => return Err(ParseError::UnexpectedToken),
-        })
+            _ => Err(ParseError::UnexpectedToken),
+        }
+    }
+}
+
+/// Check that a number literal fits into a 9 bit positive offset
+pub const fn offset9(number: u16) -> ParseResult<u16> {
+    // Check if it's negative
+    if number >> 15 == 1 {
+        return Err(ParseError::NumberLiteralIsNegative);
+    }
+    // Check if it's bigger than 9 bit
+    else if number >> 9 != 0 {
+        return Err(ParseError::NumberLiteralTooBig);
     }
+
+    Ok(number)
+}
+
+/// Return the last 9 bits of the address of a label, checking that it's on
+/// the same memory page as the operation at `address`
+pub const fn page_offset(label_address: u16, address: u16) -> ParseResult<u16> {
+    if label_address & 0xfe00 != address & 0xfe00 {
+        return Err(ParseError::LabelNotOnSamePage);
+    }
+
+    Ok(label_address & 0x1ff)
 }
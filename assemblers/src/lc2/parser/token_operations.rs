@@ -1,6 +1,10 @@
-use super::{token_helpers::TokenHelpers, Operation, PseudoOperation, Token};
+use super::{
+    label_scope::LabelScope,
+    token_helpers::{Symbols, TokenHelpers},
+    BytePacking, Check, ExpectationKind, Lc2Assembler, Operation, PseudoOperation, TestDirective,
+    Token, TokenStream,
+};
 use crate::ParseError;
-use logos::Lexer;
 use std::collections::HashMap;
 
 pub type ParseResult<T> = std::result::Result<T, ParseError>;
@@ -10,19 +14,50 @@ pub trait TokenOperations {
     type Data;
 
     fn skip_token(&mut self, skip: Token) -> ParseResult<()>;
-    fn parse_start_address(&mut self, orig_optional: bool) -> ParseResult<u16>;
+    fn parse_end_of_line(&mut self, after_label: bool) -> ParseResult<()>;
+    fn parse_start_address(&mut self, options: &Lc2Assembler) -> ParseResult<u16>;
     fn parse_pseudo_operation(
         &mut self,
         pseudo_operation: PseudoOperation,
+        options: &Lc2Assembler,
     ) -> ParseResult<Vec<Self::Data>>;
+    fn parse_padding(
+        &mut self,
+        pseudo_operation: PseudoOperation,
+        address: Self::Address,
+        symbol_table: Symbols,
+    ) -> ParseResult<Vec<Self::Data>>;
+    fn parse_block(
+        &mut self,
+        options: &Lc2Assembler,
+        labels: (&HashMap<String, u16>, &LabelScope),
+        symbol_table: Symbols,
+        address: Self::Address,
+    ) -> ParseResult<Vec<Self::Data>>;
+    fn parse_entry_point(
+        &mut self,
+        pseudo_operation: PseudoOperation,
+        symbol_table: Symbols,
+    ) -> ParseResult<Option<Self::Address>>;
+    fn parse_test_directive(
+        &mut self,
+        pseudo_operation: PseudoOperation,
+        symbol_table: Symbols,
+    ) -> ParseResult<TestDirective>;
+    fn parse_check(
+        &mut self,
+        pseudo_operation: PseudoOperation,
+        symbol_table: Symbols,
+    ) -> ParseResult<Check>;
     fn parse_operation(
         &mut self,
         operation: Operation,
-        symbol_table: Option<(&HashMap<String, u16>, Self::Address)>,
+        symbol_table: Symbols,
+        address: Self::Address,
     ) -> ParseResult<Vec<Self::Data>>;
 }
 
-impl TokenOperations for std::iter::Peekable<Lexer<'_, Token>> {
+impl TokenOperations for TokenStream<'_> {
     type Address = u16;
     type Data = u16;
 
@@ -34,29 +69,53 @@ impl TokenOperations for std::iter::Peekable<Lexer<'_, Token>> {
         Ok(())
     }
 
+    /// Make sure that nothing but a comment follows on the current line. If
+    /// `after_label` is set, the line can still contain an operation or a
+    /// pseudo-operation.
+    ///
+    /// If the newlines are not tokenized there is nothing to check
+    fn parse_end_of_line(&mut self, after_label: bool) -> ParseResult<()> {
+        if !self.newlines() {
+            return Ok(());
+        }
+
+        match self.peek() {
+            // The line ends here
+            None | Some(Ok(Token::Newline | Token::Comment(_))) => Ok(()),
+
+            // A label can be followed by a statement on the same line
+            Some(Ok(Token::Operation(_) | Token::PseudoOperation(_))) if after_label => Ok(()),
+
+            Some(Ok(_)) => Err(ParseError::TrailingTokens),
+            Some(Err(error)) => Err(error.clone()),
+        }
+    }
+
     /// Get the start address from the first `.orig` directive. If
-    /// `orig_optional` is set and the first directive isn't an `.orig` return
-    /// `0`
-    fn parse_start_address(&mut self, orig_optional: bool) -> ParseResult<u16> {
+    /// `options.optional_starting_orig` is set and the first directive isn't an
+    /// `.orig` return `0`
+    fn parse_start_address(&mut self, options: &Lc2Assembler) -> ParseResult<u16> {
         // Loop to ignore start comments
         loop {
             match self.peek_next_token()? {
-                // Ignore comments
-                Token::Comment(_) => {
-                    self.next_token()?;
+                // Ignore comments and empty lines
+                Token::Comment(_) | Token::Newline => {
+                    self.next();
                     continue;
                 }
 
                 // Get the address from the first `.orig` directive
                 Token::PseudoOperation(PseudoOperation::Orig) => {
                     self.next_token()?;
-                    return Ok(self.parse_pseudo_operation(PseudoOperation::Orig)?[0]);
+                    let address = self.parse_pseudo_operation(PseudoOperation::Orig, options)?[0];
+                    self.parse_end_of_line(false)?;
+                    return Ok(address);
                 }
 
                 // If the first token after the comments isn't a `.orig`
-                // directive return an error, but if `orig_optional` is `true`
+                // directive return an error, but if the `.orig` is optional
                 // then return 0
-                _ if orig_optional => return Ok(0),
+                _ if options.optional_starting_orig => return Ok(0),
 
                 // If there is at least one `.orig` directive in the assembly
                 // then return an `OrigNotFirst` error, else return a `NoOrig`
@@ -76,11 +135,11 @@ impl TokenOperations for std::iter::Peekable<Lexer<'_, Token>> {
     fn parse_pseudo_operation(
         &mut self,
         pseudo_operation: PseudoOperation,
+        options: &Lc2Assembler,
     ) -> ParseResult<Vec<Self::Data>> {
         Ok(match pseudo_operation {
-            // For the `.orig` and the `.fill` directives return the number
-            // immediatly after them
-            PseudoOperation::Orig | PseudoOperation::Fill => {
+            // For the `.orig` directive return the number immediatly after it
+            PseudoOperation::Orig => {
                 let Token::Number(start) = self.next_token()? else {
                     return Err(ParseError::UnexpectedToken);
                 };
@@ -88,102 +147,345 @@ impl TokenOperations for std::iter::Peekable<Lexer<'_, Token>> {
                 vec![start]
             }
 
+            // For the `.fill` and `.word` directives return the comma
+            // separated numbers after them
+            PseudoOperation::Fill | PseudoOperation::Word => self.get_number_list()?,
+
             // For the `.stringz` directive returns the next string followed by
-            // a null byte
+            // a null word
             PseudoOperation::Stringz => {
-                let Token::String(mut string) = self.next_token()? else {
+                let (string, encoding) = self.get_string(options.string_encoding)?;
+
+                let mut data = encoding.encode_literal(&string)?;
+                data.push(0);
+                data
+            }
+
+            // The `.end` directive doesn't have a binary representation
+            PseudoOperation::End => Vec::new(),
+
+            // The padding directives depend on the current address and the
+            // `.blkw` directive on the labels, so they are parsed by
+            // `parse_padding()` and `parse_block()`
+            PseudoOperation::Align | PseudoOperation::Page | PseudoOperation::Blkw => {
+                return Err(ParseError::UnexpectedToken)
+            }
+
+            // The `.entry` directive, the test directives and the checks don't
+            // have a binary representation, they are parsed by
+            // `parse_entry_point()`, `parse_test_directive()` and
+            // `parse_check()`
+            PseudoOperation::Entry
+            | PseudoOperation::Test
+            | PseudoOperation::Setreg
+            | PseudoOperation::Input
+            | PseudoOperation::Expect
+            | PseudoOperation::ExpectOutput
+            | PseudoOperation::Assert
+            | PseudoOperation::Error
+            | PseudoOperation::Warning => return Err(ParseError::UnexpectedToken),
+
+            // For the `.stringzp` custom directive returns the next string
+            // followed by a null byte in a packed form.
+            //
+            // A packed string is a string in which a single word of memory
+            // contains two characters: the first one in the lower byte and the
+            // second one in the high byte.
+            PseudoOperation::Stringzp => {
+                let (string, encoding) = self.get_string(options.string_encoding)?;
+
+                let mut bytes = encoding.encode_bytes(&string)?;
+                bytes.push(0);
+                BytePacking::LittleEndian.pack(&bytes)
+            }
+
+            // For the `.string` custom directive returns the next string,
+            // without a null terminator
+            PseudoOperation::String => {
+                let (string, encoding) = self.get_string(options.string_encoding)?;
+                encoding.encode_literal(&string)?
+            }
+
+            // For the `.pstring` custom directive returns the next string,
+            // prefixed by a word containing its length
+            PseudoOperation::Pstring => {
+                let (string, encoding) = self.get_string(options.string_encoding)?;
+                let data = encoding.encode_literal(&string)?;
+
+                let length = u16::try_from(data.len()).map_err(|_| ParseError::BinaryTooBig)?;
+                std::iter::once(length).chain(data).collect()
+            }
+
+            // For the `.bytes` custom directive returns the comma separated
+            // bytes after it, packed like the `.stringzp` strings
+            PseudoOperation::Bytes => {
+                let bytes = self
+                    .get_number_list()?
+                    .into_iter()
+                    .map(|number| {
+                        // Accept both the unsigned and the signed bytes
+                        match number.to_be_bytes() {
+                            [0x00, byte] | [0xff, byte @ 0x80..=0xff] => Ok(byte),
+                            _ => Err(ParseError::NumberLiteralTooBig),
+                        }
+                    })
+                    .collect::<ParseResult<Vec<u8>>>()?;
+
+                BytePacking::LittleEndian.pack(&bytes)
+            }
+
+            // For the `.incbin` custom directive returns the bytes of the file,
+            // optionally starting from an offset and with a maximum length
+            PseudoOperation::Incbin => {
+                let Token::String(path) = self.next_token()? else {
                     return Err(ParseError::UnexpectedToken);
                 };
 
-                string.push('\0');
-                string.bytes().map(u16::from).collect()
+                // Get the optional offset and length
+                let mut arguments = Vec::new();
+                while arguments.len() < 2 && self.peek_next_token() == Ok(&Token::Comma) {
+                    self.next_token()?;
+                    let Token::Number(argument) = self.next_token()? else {
+                        return Err(ParseError::UnexpectedToken);
+                    };
+                    arguments.push(usize::from(argument));
+                }
+
+                // Read the file
+                let bytes = options
+                    .include_resolver
+                    .as_ref()
+                    .ok_or(ParseError::IncludeNotEnabled)?
+                    .resolve(&path.to_string())
+                    .ok_or(ParseError::IncludeNotFound)?;
+
+                // Take only the requested bytes
+                let offset = arguments.first().copied().unwrap_or(0);
+                let length = arguments
+                    .get(1)
+                    .copied()
+                    .unwrap_or_else(|| bytes.len().saturating_sub(offset));
+                let bytes = offset
+                    .checked_add(length)
+                    .and_then(|end| bytes.get(offset..end))
+                    .ok_or(ParseError::IncludeOutOfRange)?;
+
+                options.incbin_packing.pack(bytes)
             }
+        })
+    }
 
-            // For the `.blkw` directive returns a the second number repeated
-            // for the first number
-            PseudoOperation::Blkw => {
-                // Get how many times to repeat the next word
-                let Token::Number(times) = self.next_token()? else {
+    /// Consume a pseudo-operation that pads the binary depending on the current
+    /// address, returning the padding words:
+    ///   - `.align n[, fill]` pads to the next multiple of `n` words;
+    ///   - `.page [fill]` pads to the next 512 words LC2 page.
+    fn parse_padding(
+        &mut self,
+        pseudo_operation: PseudoOperation,
+        address: Self::Address,
+        symbol_table: Symbols,
+    ) -> ParseResult<Vec<Self::Data>> {
+        // Get the alignment
+        let alignment = match pseudo_operation {
+            PseudoOperation::Align => {
+                let Token::Number(alignment) = self.next_token()? else {
                     return Err(ParseError::UnexpectedToken);
                 };
+                if alignment == 0 {
+                    return Err(ParseError::InvalidAlignment);
+                }
 
-                // Get the word to repeat...
-                let word = match self.peek_next_token() {
-                    // If the next token is a number, consume it and get it as
-                    // the word to repeat
-                    Ok(Token::Number(word)) => {
-                        // Get the number
-                        let word = *word;
+                alignment
+            }
+            PseudoOperation::Page => 0x200,
+            _ => return Err(ParseError::UnexpectedToken),
+        };
 
-                        // Consume it
-                        self.next_token()?;
+        // Get the word to pad with, or pad with a null word
+        let word = self
+            .get_fill_word(symbol_table, pseudo_operation == PseudoOperation::Align)?
+            .unwrap_or(0);
 
-                        // Return it
-                        word
-                    }
+        // Get how many words are needed to reach the next aligned address
+        let padding = (alignment - address % alignment) % alignment;
 
-                    // If the next token is a comma followed by a number, get it
-                    // as the word to repeat
-                    Ok(Token::Comma) => {
-                        // Consume the comma
-                        self.next_token()?;
+        Ok(vec![word; usize::from(padding)])
+    }
 
-                        // Get the next number
-                        let Token::Number(word) = self.next_token()? else {
-                            return Err(ParseError::UnexpectedToken);
-                        };
+    /// Consume a `.blkw count[, fill]` directive, returning the `fill` word
+    /// (or the `options.blkw_fill` one) repeated `count` times.
+    ///
+    /// Both can be expressions, but since the count changes the address of the
+    /// labels after it, it can only use the `labels` declared before the
+    /// directive. The fill word is resolved with `symbol_table`, so it can use
+    /// any label
+    fn parse_block(
+        &mut self,
+        options: &Lc2Assembler,
+        labels: (&HashMap<String, u16>, &LabelScope),
+        symbol_table: Symbols,
+        address: Self::Address,
+    ) -> ParseResult<Vec<Self::Data>> {
+        // Get how many times to repeat the word
+        let count = self.get_expression(Symbols::Complete(labels.0, labels.1))?;
 
-                        // Return it
-                        word
-                    }
+        // An empty block is probably a mistake, while a block bigger than the
+        // rest of the memory probably comes from a negative expression
+        if count == 0 {
+            return Err(ParseError::EmptyBlock);
+        }
+        if address.checked_add(count).is_none() {
+            return Err(ParseError::BlockTooBig);
+        }
+
+        // Get the word to repeat
+        let word = self
+            .get_fill_word(symbol_table, true)?
+            .unwrap_or(options.blkw_fill);
+
+        Ok(vec![word; usize::from(count)])
+    }
+
+    /// Consume the entry point of the program: the mandatory operand of a
+    /// `.entry` directive or the optional one of an `.end` directive (like
+    /// `.end main`)
+    fn parse_entry_point(
+        &mut self,
+        pseudo_operation: PseudoOperation,
+        symbol_table: Symbols,
+    ) -> ParseResult<Option<Self::Address>> {
+        match pseudo_operation {
+            PseudoOperation::Entry => self.get_expression(symbol_table).map(Some),
+
+            // The operand of `.end` must be on the same line, or it could be
+            // the first token after the end of the program
+            PseudoOperation::End if self.next_on_same_line() => match self.peek() {
+                Some(Ok(Token::Number(_) | Token::Label(_) | Token::LocalLabel(_))) => {
+                    self.get_expression(symbol_table).map(Some)
+                }
+                _ => Ok(None),
+            },
+            PseudoOperation::End => Ok(None),
 
-                    // Else fill the words with a null word
-                    _ => 0,
+            _ => Err(ParseError::UnexpectedToken),
+        }
+    }
+
+    /// Consume a test directive, whose values can be expressions:
+    ///   - `.test name` (or `.test "name"`);
+    ///   - `.setreg R0, value` and `.expect R0, value`;
+    ///   - `.input "string"` and `.expect_output "string"`.
+    fn parse_test_directive(
+        &mut self,
+        pseudo_operation: PseudoOperation,
+        symbol_table: Symbols,
+    ) -> ParseResult<TestDirective> {
+        match pseudo_operation {
+            PseudoOperation::Test => match self.next_token()? {
+                Token::Label(name) => Ok(TestDirective::Test(name)),
+                Token::String(name) => Ok(TestDirective::Test(name.to_string())),
+                _ => Err(ParseError::UnexpectedToken),
+            },
+
+            PseudoOperation::Setreg | PseudoOperation::Expect => {
+                let Token::Register(register) = self.next_token()? else {
+                    return Err(ParseError::UnexpectedToken);
                 };
+                self.skip_token(Token::Comma)?;
+                let value = self.get_expression(symbol_table)?;
 
-                vec![word; usize::from(times)]
+                Ok(if pseudo_operation == PseudoOperation::Setreg {
+                    TestDirective::SetRegister(register, value)
+                } else {
+                    TestDirective::Expect(ExpectationKind::Register(register, value))
+                })
             }
 
-            // The `.end` directive doesn't have a binary representation
-            PseudoOperation::End => Vec::new(),
-
-            // For the `.stringzp` custom directive returns the next string
-            // followed by a null byte in a packed form.
-            //
-            // A packed string is a string in which a single word of memory
-            // contains two characters: the first one in the lower byte and the
-            // second one in the high byte.
-            PseudoOperation::Stringzp => {
-                let Token::String(mut string) = self.next_token()? else {
+            PseudoOperation::Input | PseudoOperation::ExpectOutput => {
+                let Token::String(string) = self.next_token()? else {
                     return Err(ParseError::UnexpectedToken);
                 };
 
-                string.push('\0');
-                string
-                    .into_bytes()
-                    .chunks(2)
-                    .map(|bytes| {
-                        // Put the low byte into the data word
-                        let mut data = u16::from(bytes[0]);
-
-                        // Put the high byte into the data word, if it exists
-                        if bytes.len() == 2 {
-                            data |= u16::from(bytes[1]) << 8;
-                        }
+                Ok(if pseudo_operation == PseudoOperation::Input {
+                    TestDirective::Input(string.to_string())
+                } else {
+                    TestDirective::Expect(ExpectationKind::Output(string.to_string()))
+                })
+            }
 
-                        // Return the packed characters
-                        data
-                    })
-                    .collect()
+            _ => Err(ParseError::UnexpectedToken),
+        }
+    }
+
+    /// Consume a check, evaluated by `Check::diagnostic()`:
+    ///   - `.assert condition[, "message"]` raises an error if the condition
+    ///     doesn't hold. The condition is an expression, true if it's not zero,
+    ///     or two expressions compared by `==`, `!=`, `<`, `<=`, `>` or `>=`;
+    ///   - `.error "message"` always raises an error;
+    ///   - `.warning "message"` always raises a warning.
+    fn parse_check(
+        &mut self,
+        pseudo_operation: PseudoOperation,
+        symbol_table: Symbols,
+    ) -> ParseResult<Check> {
+        match pseudo_operation {
+            PseudoOperation::Assert => {
+                let left = self.get_expression(symbol_table)?;
+
+                // The comparison must be on the same line
+                let same_line = self.next_on_same_line();
+                let comparison = match self.peek() {
+                    Some(Ok(Token::Comparison(comparison))) if same_line => Some(*comparison),
+                    _ => None,
+                };
+                let comparison = match comparison {
+                    Some(comparison) => {
+                        self.next_token()?;
+                        Some((comparison, self.get_expression(symbol_table)?))
+                    }
+                    None => None,
+                };
+
+                // Get the optional message
+                let message = if self.peek_next_token() == Ok(&Token::Comma) {
+                    self.next_token()?;
+                    let Token::String(message) = self.next_token()? else {
+                        return Err(ParseError::UnexpectedToken);
+                    };
+                    Some(message.to_string())
+                } else {
+                    None
+                };
+
+                Ok(Check::Assert {
+                    left,
+                    comparison,
+                    message,
+                })
             }
-        })
+
+            PseudoOperation::Error | PseudoOperation::Warning => {
+                let Token::String(message) = self.next_token()? else {
+                    return Err(ParseError::UnexpectedToken);
+                };
+
+                Ok(if pseudo_operation == PseudoOperation::Error {
+                    Check::Error(message.to_string())
+                } else {
+                    Check::Warning(message.to_string())
+                })
+            }
+
+            _ => Err(ParseError::UnexpectedToken),
+        }
     }
 
     /// Consume an operation, returning the binary representation
     fn parse_operation(
         &mut self,
         operation: Operation,
-        symbol_table: Option<(&HashMap<String, u16>, Self::Address)>,
+        symbol_table: Symbols,
+        address: Self::Address,
     ) -> ParseResult<Vec<Self::Data>> {
         Ok(match operation {
             Operation::Add | Operation::And => {
@@ -226,7 +528,7 @@ impl TokenOperations for std::iter::Peekable<Lexer<'_, Token>> {
                     (u16::from(n) << 11)
                         | (u16::from(z) << 10)
                         | (u16::from(p) << 9)
-                        | self.get_pgoffset9(symbol_table, false)?,
+                        | self.get_pgoffset9(symbol_table, address, false)?,
                 ]
             }
 
@@ -234,7 +536,7 @@ impl TokenOperations for std::iter::Peekable<Lexer<'_, Token>> {
                 vec![
                     (0b0100 << 12)
                         | (u16::from(link) << 11)
-                        | self.get_pgoffset9(symbol_table, false)?,
+                        | self.get_pgoffset9(symbol_table, address, false)?,
                 ]
             }
 
@@ -265,7 +567,7 @@ impl TokenOperations for std::iter::Peekable<Lexer<'_, Token>> {
                 vec![
                     (opcode << 12)
                         | (self.get_register()? << 9)
-                        | self.get_pgoffset9(symbol_table, true)?,
+                        | self.get_pgoffset9(symbol_table, address, true)?,
                 ]
             }
 
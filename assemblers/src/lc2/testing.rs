@@ -0,0 +1,249 @@
+use super::Lc2Assembler;
+use crate::{line, Diagnostic, ParseError};
+use architectures::lc2::Gpr;
+use std::{fmt::Display, ops::Range};
+
+/// A test case declared by a `.test name` directive and by the test
+/// directives after it, up to the next `.test`:
+///   - `.setreg R0, 5` sets a register before running the program;
+///   - `.input "abc"` appends to the console input;
+///   - `.expect R1, 10` checks a register after the program halted;
+///   - `.expect_output "..."` checks the whole console output.
+///
+/// The test directives don't emit any word, and the values can be expressions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestCase {
+    pub name: String,
+    pub registers: Vec<(Gpr, u16)>,
+    pub input: String,
+    pub expectations: Vec<Expectation>,
+    /// The line of the `.test` directive, starting from 1
+    pub line: usize,
+    /// The byte range of the `.test` directive
+    pub span: Range<usize>,
+}
+
+/// A check done after running a test case
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expectation {
+    pub kind: ExpectationKind,
+    /// The line of the directive, starting from 1
+    pub line: usize,
+    /// The byte range of the directive
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpectationKind {
+    Register(Gpr, u16),
+    Output(String),
+}
+
+/// A test directive parsed by the assembler
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum TestDirective {
+    Test(String),
+    SetRegister(Gpr, u16),
+    Input(String),
+    Expect(ExpectationKind),
+}
+
+/// Group the test directives into test cases
+pub(super) fn collect(
+    assembly: &str,
+    directives: Vec<(TestDirective, Range<usize>)>,
+) -> Result<Vec<TestCase>, Diagnostic> {
+    // Every directive but `.test` belongs to the last test case
+    let outside = |span: &Range<usize>| {
+        Diagnostic::new(assembly, ParseError::TestDirectiveOutsideTest, span.clone())
+    };
+
+    let mut cases: Vec<TestCase> = Vec::new();
+    for (directive, span) in directives {
+        match directive {
+            TestDirective::Test(name) => cases.push(TestCase {
+                name,
+                registers: Vec::new(),
+                input: String::new(),
+                expectations: Vec::new(),
+                line: line(assembly, span.start),
+                span,
+            }),
+            TestDirective::SetRegister(register, value) => cases
+                .last_mut()
+                .ok_or_else(|| outside(&span))?
+                .registers
+                .push((register, value)),
+            TestDirective::Input(input) => cases
+                .last_mut()
+                .ok_or_else(|| outside(&span))?
+                .input
+                .push_str(&input),
+            TestDirective::Expect(kind) => {
+                let case = cases.last_mut().ok_or_else(|| outside(&span))?;
+                case.expectations.push(Expectation {
+                    kind,
+                    line: line(assembly, span.start),
+                    span,
+                });
+            }
+        }
+    }
+
+    Ok(cases)
+}
+
+/// An LC2 machine that runs the test cases, like the emulator of the
+/// `architectures` crate
+pub trait Machine {
+    type Error: Display;
+
+    /// Load the words of the program starting from `origin`, and set the PC to
+    /// `entry_point`
+    fn load(&mut self, origin: u16, words: &[u16], entry_point: u16);
+
+    fn set_register(&mut self, register: Gpr, value: u16);
+
+    fn register(&self, register: Gpr) -> u16;
+
+    /// Run the program until `HALT`, reading from the console `input`, and
+    /// return the console output
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the program can't run until `HALT`
+    fn run(&mut self, input: &str) -> Result<String, Self::Error>;
+}
+
+/// The outcome of a test case
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestReport {
+    pub name: String,
+    /// The line of the `.test` directive, starting from 1
+    pub line: usize,
+    /// The byte range of the `.test` directive
+    pub span: Range<usize>,
+    pub failures: Vec<TestFailure>,
+}
+
+impl TestReport {
+    #[must_use]
+    pub const fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// A failed expectation, or the error that stopped the program (located at the
+/// `.test` directive)
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("Line {line}: {message}")]
+pub struct TestFailure {
+    pub message: String,
+    /// The line of the directive, starting from 1
+    pub line: usize,
+    /// The byte range of the directive
+    pub span: Range<usize>,
+}
+
+impl Lc2Assembler {
+    /// Assemble the program and run every test case on a new machine, with the
+    /// binary loaded from the origin and the PC set to the entry point (or to
+    /// the origin)
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the assembly is not valid
+    pub fn run_tests<M: Machine>(
+        &self,
+        assembly: &str,
+        mut machine: impl FnMut() -> M,
+    ) -> Result<Vec<TestReport>, Diagnostic> {
+        let output = self.assemble_with_diagnostic(assembly)?;
+
+        // The start address isn't loaded into the memory
+        let words: Vec<u16> = output
+            .binary
+            .chunks(2)
+            .skip(usize::from(self.prepend_start_address))
+            .map(|word| u16::from_be_bytes([word[0], word.get(1).copied().unwrap_or(0)]))
+            .collect();
+        let entry_point = output.entry_point.unwrap_or(output.origin);
+
+        Ok(output
+            .tests
+            .into_iter()
+            .map(|case| {
+                let mut machine = machine();
+                machine.load(output.origin, &words, entry_point);
+                run(&mut machine, case)
+            })
+            .collect())
+    }
+}
+
+/// Run a test case on a loaded machine
+fn run(machine: &mut impl Machine, case: TestCase) -> TestReport {
+    let TestCase {
+        name,
+        registers,
+        input,
+        expectations,
+        line,
+        span,
+    } = case;
+
+    for (register, value) in registers {
+        machine.set_register(register, value);
+    }
+
+    // An error makes the expectations meaningless
+    let output = match machine.run(&input) {
+        Ok(output) => output,
+        Err(error) => {
+            return TestReport {
+                name,
+                line,
+                span: span.clone(),
+                failures: vec![TestFailure {
+                    message: error.to_string(),
+                    line,
+                    span,
+                }],
+            }
+        }
+    };
+
+    let failures = expectations
+        .into_iter()
+        .filter_map(|expectation| {
+            let message = match expectation.kind {
+                // The register is not guaranteed to be `Copy`
+                #[allow(clippy::clone_on_copy)]
+                ExpectationKind::Register(register, expected) => {
+                    let actual = machine.register(register.clone());
+                    (actual != expected).then(|| {
+                        format!(
+                            "R{} is x{actual:04X}, but x{expected:04X} was expected",
+                            u8::from(register) & 0b111
+                        )
+                    })
+                }
+                ExpectationKind::Output(expected) => (output != expected)
+                    .then(|| format!("The output is {output:?}, but {expected:?} was expected")),
+            }?;
+
+            Some(TestFailure {
+                message,
+                line: expectation.line,
+                span: expectation.span,
+            })
+        })
+        .collect();
+
+    TestReport {
+        name,
+        line,
+        span,
+        failures,
+    }
+}
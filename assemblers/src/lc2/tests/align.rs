@@ -0,0 +1,68 @@
+use super::*;
+
+fn assemble(assembly: &str) -> Result<(Vec<u8>, HashMap<String, u16>), ParseError> {
+    Lc2AssemblerBuilder::default()
+        .prepend_start_address(false)
+        .multiple_origs(true)
+        .build()
+        .assemble(assembly)
+}
+
+#[test]
+fn align() {
+    assert_eq!(
+        assemble(".orig x3000\n.fill 1\n.align 4\nlabel .fill 2\n.end").map(|x| x.0),
+        Ok(vec![0x00, 0x01, 0, 0, 0, 0, 0, 0, 0x00, 0x02])
+    );
+    assert_eq!(
+        assemble(".orig x3000\n.fill 1\n.align 4, xffff\n.fill 2\n.end").map(|x| x.0),
+        Ok(vec![
+            0x00, 0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x02
+        ])
+    );
+
+    // An aligned address doesn't need any padding
+    assert_eq!(
+        assemble(".orig x3000\n.align 8\n.fill 1\n.end").map(|x| x.0),
+        Ok(vec![0x00, 0x01])
+    );
+
+    // The symbol table takes the padding into account
+    assert_eq!(
+        assemble(".orig x3001\n.align 4\nlabel .fill 2\n.end").map(|x| x.1),
+        Ok(HashMap::from([("label".to_string(), 0x3004)]))
+    );
+}
+
+#[test]
+fn page() {
+    let (binary, symbol_table) =
+        assemble(".orig x3000\n.fill 1\n.page 7\nlabel ld r0, label\n.end").unwrap();
+
+    assert_eq!(binary.len(), 0x201 * 2);
+    assert_eq!(binary[2..4], [0x00, 0x07]);
+    assert_eq!(binary[0x3fe..], [0x00, 0x07, 0x20, 0x00]);
+    assert_eq!(symbol_table, HashMap::from([("label".to_string(), 0x3200)]));
+
+    // A label on the next page can't be reached
+    assert_eq!(
+        assemble(".orig x3000\nld r0, label\n.page\nlabel .fill 1\n.end"),
+        Err(ParseError::LabelNotOnSamePage)
+    );
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        assemble(".orig x3000\n.align 0\n.end"),
+        Err(ParseError::InvalidAlignment)
+    );
+    assert_eq!(
+        assemble(".orig x3000\n.align\n.end"),
+        Err(ParseError::UnexpectedToken)
+    );
+    assert_eq!(
+        assemble(".orig xfff0\n.fill 1\n.page\n.end"),
+        Err(ParseError::BinaryTooBig)
+    );
+}
@@ -0,0 +1,183 @@
+use super::*;
+use crate::lc2::analysis::{ControlFlowGraph, EdgeKind, FlowDiagnostic};
+
+fn analyze(assembly: &str) -> ControlFlowGraph {
+    let output = Lc2AssemblerBuilder::default()
+        .prepend_start_address(false)
+        .build()
+        .assemble_with_diagnostic(assembly)
+        .unwrap();
+
+    let words: Vec<u16> = output
+        .binary
+        .chunks(2)
+        .map(|word| u16::from_be_bytes([word[0], word[1]]))
+        .collect();
+
+    ControlFlowGraph::new(&words, 0x3000, &output.symbol_table, output.entry_point)
+}
+
+#[test]
+fn reachability() {
+    let graph = analyze(
+        ".orig x3000
+        brz skip
+        add r0, r0, #1
+        skip halt
+        dead add r0, r0, #2
+        .end",
+    );
+
+    assert!(graph.is_reachable(0x3000));
+    assert!(graph.is_reachable(0x3001));
+    assert!(graph.is_reachable(0x3002));
+    assert!(!graph.is_reachable(0x3003));
+    assert_eq!(graph.nodes()[&0x3000].successors.len(), 2);
+    assert_eq!(graph.label(0x3002), Some("skip"));
+    assert_eq!(graph.diagnostics(), []);
+
+    // The analysis starts from the entry point
+    let graph = analyze(".orig x3000\n.fill 0\nmain halt\n.end main");
+    assert!(!graph.is_reachable(0x3000));
+    assert!(graph.is_reachable(0x3001));
+}
+
+#[test]
+fn falls_off_end() {
+    let graph = analyze(".orig x3000\nadd r0, r0, #1\n.end");
+    assert_eq!(
+        graph.diagnostics(),
+        [FlowDiagnostic::FallsOffEnd { from: 0x3000 }]
+    );
+}
+
+#[test]
+fn falls_into_data() {
+    let graph = analyze(
+        ".orig x3000
+        ld r0, value
+        value .fill 5
+        .end",
+    );
+
+    assert!(graph.data().contains(&0x3001));
+    assert!(graph
+        .diagnostics()
+        .contains(&FlowDiagnostic::FallsIntoData {
+            from: 0x3000,
+            to: 0x3001
+        }));
+}
+
+#[test]
+fn subroutines() {
+    let graph = analyze(
+        ".orig x3000
+        jsr square
+        jsr forever
+        halt
+        square add r0, r0, r0
+        brp done
+        add r0, r0, #1
+        done ret
+        forever br forever
+        .end",
+    );
+
+    assert_eq!(graph.nodes()[&0x3000].successors[0].kind, EdgeKind::Call);
+
+    let subroutines = graph.subroutines();
+    assert_eq!(subroutines.len(), 2);
+    assert_eq!(subroutines[0].name.as_deref(), Some("square"));
+    assert_eq!(
+        subroutines[0].operations,
+        [0x3003, 0x3004, 0x3005, 0x3006].into()
+    );
+    assert_eq!(subroutines[0].returns, [0x3006].into());
+
+    assert_eq!(
+        graph.diagnostics(),
+        [FlowDiagnostic::MissingReturn { subroutine: 0x3007 }]
+    );
+}
+
+#[test]
+fn clobbered_return_address() {
+    // Calling a subroutine or a trap overwrites R7
+    let graph = analyze(
+        ".orig x3000
+        jsr outer
+        halt
+        outer jsr inner
+        out
+        ret
+        inner jmpr r7, 0
+        .end",
+    );
+    assert_eq!(
+        graph.diagnostics(),
+        [FlowDiagnostic::ClobberedReturnAddress {
+            subroutine: 0x3002,
+            clobbered: 0x3002,
+            ret: 0x3004
+        }]
+    );
+
+    // Saving and restoring R7 is fine, but only if it's saved on every path
+    let graph = analyze(
+        ".orig x3000
+        jsr outer
+        halt
+        outer brz skip
+        st r7, save
+        skip jsr inner
+        ld r7, save
+        ret
+        inner ret
+        save .blkw 1
+        .end",
+    );
+    assert_eq!(
+        graph.diagnostics(),
+        [FlowDiagnostic::ClobberedReturnAddress {
+            subroutine: 0x3002,
+            clobbered: 0x3004,
+            ret: 0x3006
+        }]
+    );
+
+    let graph = analyze(
+        ".orig x3000
+        jsr outer
+        halt
+        outer st r7, save
+        jsr inner
+        ld r7, save
+        ret
+        inner ret
+        save .blkw 1
+        .end",
+    );
+    assert_eq!(graph.diagnostics(), []);
+
+    // Saving R7 after it was overwritten doesn't restore the return address
+    let graph = analyze(
+        ".orig x3000
+        jsr sub
+        halt
+        sub trap x21
+        st r7, save
+        ld r7, save
+        ret
+        save .blkw 1
+        .end",
+    );
+    assert_eq!(
+        graph.diagnostics(),
+        [FlowDiagnostic::ClobberedReturnAddress {
+            subroutine: 0x3002,
+            clobbered: 0x3002,
+            ret: 0x3005
+        }]
+    );
+}
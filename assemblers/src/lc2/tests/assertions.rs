@@ -0,0 +1,101 @@
+use super::*;
+use crate::{
+    lc2::ast,
+    lint::{Lint, LintLevel, Warning},
+    Diagnostic,
+};
+
+fn assemble(assembly: &str) -> Result<Vec<Warning>, Diagnostic> {
+    Lc2AssemblerBuilder::default()
+        .build()
+        .assemble_with_diagnostic(assembly)
+        .map(|output| output.warnings)
+}
+
+fn assemble_error(assembly: &str) -> ParseError {
+    assemble(assembly).unwrap_err().error
+}
+
+#[test]
+fn assertions() {
+    // The labels declared after the assertion can be used
+    assert_eq!(
+        assemble(".orig x3000\n.assert end - table <= 3\ntable .blkw 3\nend halt\n.end"),
+        Ok(Vec::new())
+    );
+    assert_eq!(
+        assemble(".orig x3000\n.assert table == x3000\n.assert table\ntable halt\n.end"),
+        Ok(Vec::new())
+    );
+
+    assert_eq!(
+        assemble(
+            ".orig x3000\n.fill 0\n.ASSERT buffer == x3000, \"misplaced\"\nbuffer .blkw 2\n.end"
+        ),
+        Err(Diagnostic {
+            error: ParseError::AssertionFailed(Some("misplaced".to_owned())),
+            span: 20..27,
+            line: 3
+        })
+    );
+    assert_eq!(
+        assemble(".orig x3000\n.assert table - table\ntable halt\n.end")
+            .map_err(|diagnostic| diagnostic.error.to_string()),
+        Err("The assertion failed".to_owned())
+    );
+
+    // The words are compared as unsigned numbers
+    assert_eq!(
+        assemble(".orig x3000\n.assert #-1 > 1, \"unsigned\"\n.end"),
+        Ok(Vec::new())
+    );
+}
+
+#[test]
+fn errors_and_warnings() {
+    assert_eq!(
+        assemble(".orig x3000\nhalt\n.error \"not supported\"\n.end"),
+        Err(Diagnostic {
+            error: ParseError::UserError("not supported".to_owned()),
+            span: 17..23,
+            line: 3
+        })
+    );
+
+    let warnings = assemble(".orig x3000\nhalt\n.warning \"slow\"\n.end").unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].lint, Lint::UserWarning);
+    assert_eq!(warnings[0].to_string(), "Line 3: slow (user_warning)");
+
+    // The warnings follow the level of their lint
+    let assemble = |level| {
+        Lc2AssemblerBuilder::default()
+            .lint(Lint::UserWarning, level)
+            .build()
+            .assemble(".orig x3000\n.warning \"slow\"\nhalt\n.end")
+            .map(|_| ())
+    };
+    assert_eq!(assemble(LintLevel::Allow), Ok(()));
+    assert_eq!(
+        assemble(LintLevel::Deny),
+        Err(ParseError::UserError("slow".to_owned()))
+    );
+
+    assert_eq!(
+        assemble_error(".orig x3000\n.warning slow\n.end"),
+        ParseError::UnexpectedToken
+    );
+    assert_eq!(
+        assemble_error(".orig x3000\n.assert nowhere\n.end"),
+        ParseError::LabelNotDeclared
+    );
+}
+
+#[test]
+fn syntax_tree() {
+    let program = ast::parse(".assert end - start < 10, \"too long\"").unwrap();
+    assert_eq!(
+        program.to_string(),
+        ".ASSERT end - start < #10, \"too long\"\n"
+    );
+}
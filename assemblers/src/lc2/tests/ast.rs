@@ -0,0 +1,311 @@
+use super::*;
+use crate::lc2::ast::{
+    self, parse, Comment, Directive, Instruction, Label, LabelDeclaration, LabelReference,
+    LabelScope, Line, Operand, Operation, Program, PseudoOperation, Sign, Statement, Term, Visitor,
+};
+use crate::Diagnostic;
+use architectures::lc2::Gpr;
+use std::ops::Range;
+
+const PROGRAM: &str = "\
+.orig x3000
+main: lea r0, string ; print it
+- puts
+brnp -
+.loop add r1, r1, #-1
+brp .loop
+halt
+string .stringz \"Hi!\\n\"
+.blkw string-main
+end .end
+";
+
+#[test]
+fn lines() {
+    let program = parse("main: ld r0, value ; load\nvalue .fill xadd, -'a'").unwrap();
+
+    assert_eq!(
+        program.lines,
+        [
+            Line {
+                label: Some(Label {
+                    kind: LabelDeclaration::Global("main".to_owned()),
+                    span: 0..5
+                }),
+                statement: Some(Statement::Instruction(Instruction {
+                    operation: Operation::Load,
+                    operands: vec![
+                        Operand::Register(Gpr::R0),
+                        Operand::Label(LabelReference::Global("value".to_owned()), 13..18)
+                    ],
+                    span: 6..18
+                })),
+                comment: Some(Comment {
+                    text: "; load".to_owned(),
+                    span: 19..25
+                }),
+                span: 0..25
+            },
+            Line {
+                label: Some(Label {
+                    kind: LabelDeclaration::Global("value".to_owned()),
+                    span: 26..31
+                }),
+                statement: Some(Statement::Directive(Directive {
+                    pseudo_operation: PseudoOperation::Fill,
+                    operands: vec![Operand::Number(0xadd), Operand::Number(-0x61)],
+                    span: 32..48
+                })),
+                comment: None,
+                span: 26..48
+            }
+        ]
+    );
+}
+
+#[test]
+fn operands() {
+    let operands = |assembly: &str| match parse(assembly).unwrap().lines.remove(0).statement {
+        Some(Statement::Directive(directive)) => directive.operands,
+        statement => panic!("{statement:?}"),
+    };
+
+    assert_eq!(
+        operands(".blkw end-1, end - .loop + 1b"),
+        [
+            Operand::Expression(vec![
+                (
+                    Sign::Plus,
+                    Term::Label(LabelReference::Global("end".to_owned()), 6..9)
+                ),
+                (Sign::Plus, Term::Number(-1))
+            ]),
+            Operand::Expression(vec![
+                (
+                    Sign::Plus,
+                    Term::Label(LabelReference::Global("end".to_owned()), 13..16)
+                ),
+                (
+                    Sign::Minus,
+                    Term::Label(LabelReference::Local("loop".to_owned()), 19..24)
+                ),
+                (
+                    Sign::Plus,
+                    Term::Label(
+                        LabelReference::Numeric {
+                            name: "1".to_owned(),
+                            forward: false
+                        },
+                        27..29
+                    )
+                )
+            ])
+        ]
+    );
+
+    // The anonymous labels are never part of an expression
+    assert_eq!(
+        operands(".fill - , ++"),
+        [
+            Operand::Label(
+                LabelReference::Anonymous {
+                    forward: false,
+                    distance: 1
+                },
+                6..7
+            ),
+            Operand::Label(
+                LabelReference::Anonymous {
+                    forward: true,
+                    distance: 2
+                },
+                10..12
+            )
+        ]
+    );
+    assert_eq!(
+        operands(".stringz \"a\\\"b\", latin1"),
+        [
+            Operand::String("a\"b".into()),
+            Operand::Label(LabelReference::Global("latin1".to_owned()), 17..23)
+        ]
+    );
+}
+
+#[test]
+fn many_statements() {
+    // A line with many statements is split, keeping the label on the first
+    // one and the comment on the last one
+    let program = parse("label: add r0 r0 r0 halt ; done").unwrap();
+
+    assert_eq!(program.lines.len(), 2);
+    assert!(program.lines[0].label.is_some() && program.lines[0].comment.is_none());
+    assert!(program.lines[1].label.is_none() && program.lines[1].comment.is_some());
+    assert_eq!(program.to_string(), "label: ADD R0, R0, R0\nHALT ; done\n");
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        parse("halt\nadd r0, :").map_err(|diagnostic| (diagnostic.error, diagnostic.line)),
+        Err((ParseError::UnexpectedToken, 2))
+    );
+    assert_eq!(
+        parse("1f halt").map_err(|diagnostic| diagnostic.error),
+        Err(ParseError::UnexpectedToken)
+    );
+}
+
+#[test]
+fn visitor() {
+    #[derive(Default)]
+    struct References(Vec<String>, usize);
+
+    impl Visitor for References {
+        fn visit_label_reference(&mut self, label: &LabelReference, _span: &Range<usize>) {
+            self.0.push(label.to_string());
+        }
+
+        fn visit_comment(&mut self, _comment: &Comment) {
+            self.1 += 1;
+        }
+    }
+
+    let mut references = References::default();
+    parse(PROGRAM).unwrap().accept(&mut references);
+
+    assert_eq!(references.0, ["string", "-", ".loop", "string", "main"]);
+    assert_eq!(references.1, 1);
+}
+
+#[test]
+fn label_scope() {
+    struct Qualify(LabelScope, Vec<String>);
+    impl Visitor for Qualify {
+        fn visit_line(&mut self, line: &Line) {
+            if let Some(label) = &line.label {
+                self.1.push(self.0.declaration(&label.kind).unwrap());
+            }
+            ast::walk_line(self, line);
+        }
+
+        fn visit_label_reference(&mut self, label: &LabelReference, _span: &Range<usize>) {
+            self.1.push(self.0.reference(label).unwrap());
+        }
+    }
+
+    // The names are the same of the symbol table
+    let mut qualify = Qualify(LabelScope::default(), Vec::new());
+    parse(PROGRAM).unwrap().accept(&mut qualify);
+    assert_eq!(
+        qualify.1,
+        [
+            "main",
+            "string",
+            "-@0",
+            "-@0",
+            "main.loop",
+            "main.loop",
+            "string",
+            "string",
+            "main",
+            "end"
+        ]
+    );
+
+    assert_eq!(
+        LabelScope::default().reference(&LabelReference::Local("loop".to_owned())),
+        Err(ParseError::LocalLabelWithoutScope)
+    );
+}
+
+#[test]
+fn assemble_program() {
+    let assembler = Lc2AssemblerBuilder::default().build();
+    let program = parse(PROGRAM).unwrap();
+
+    assert_eq!(
+        assembler
+            .assemble_program(&program)
+            .map(|output| output.binary),
+        assembler
+            .assemble_with_diagnostic(PROGRAM)
+            .map(|output| output.binary)
+    );
+
+    // The tree can be transformed before the assembly
+    let mut program = program;
+    program.lines.insert(
+        1,
+        Line {
+            statement: Some(Statement::Instruction(Instruction {
+                operation: Operation::Branch(false, false, false),
+                operands: vec![Operand::Number(0)],
+                span: 0..0,
+            })),
+            ..Line::default()
+        },
+    );
+    let binary = assembler.assemble_program(&program).unwrap().binary;
+    assert_eq!(binary[..6], [0x30, 0x00, 0x00, 0x00, 0xe0, 0x07]);
+}
+
+#[test]
+fn assemble_program_spans() {
+    let assembler = Lc2AssemblerBuilder::default().build();
+
+    // The spans point into the parsed source, not into the printed program
+    let source = ".orig x3000\n  main:   add r0,r0,#1\n\tbrnzp   missing\n.end\n";
+    let start = source.find("missing").unwrap();
+    assert_eq!(
+        assembler.assemble_program(&parse(source).unwrap()),
+        Err(Diagnostic {
+            error: ParseError::LabelNotDeclared,
+            span: start..start + 7,
+            line: 3
+        })
+    );
+
+    let source = ".orig x3000\n  main:   add r0,r0,#1\n\thalt\n.end\n";
+    let spans: Vec<Range<usize>> = assembler
+        .assemble_program(&parse(source).unwrap())
+        .unwrap()
+        .operations
+        .into_iter()
+        .map(|operation| operation.span)
+        .collect();
+    assert_eq!(spans, [22..34, 36..40]);
+}
+
+#[test]
+fn display() {
+    assert_eq!(
+        Program {
+            lines: vec![Line {
+                label: Some(Label {
+                    kind: LabelDeclaration::Anonymous { forward: true },
+                    span: 0..0
+                }),
+                statement: Some(Statement::Directive(Directive {
+                    pseudo_operation: PseudoOperation::Stringz,
+                    operands: vec![Operand::String("a\"\\\n\u{1b}".into())],
+                    span: 0..0
+                })),
+                comment: None,
+                span: 0..0
+            }]
+        }
+        .to_string(),
+        "+: .STRINGZ \"a\\\"\\\\\\n\\u{1b}\"\n"
+    );
+    assert_eq!(
+        parse("brnz 1b\nbr -").unwrap().to_string(),
+        "BRnz 1b\nBRnzp -\n"
+    );
+
+    // The escaped bytes stay bytes
+    assert_eq!(
+        parse(r#".stringz "\xffè\0""#).unwrap().to_string(),
+        ".STRINGZ \"\\xFFè\\x00\"\n"
+    );
+}
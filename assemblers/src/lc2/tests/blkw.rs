@@ -0,0 +1,108 @@
+use super::*;
+
+fn assemble(assembly: &str) -> Result<Vec<u8>, ParseError> {
+    Lc2AssemblerBuilder::default()
+        .prepend_start_address(false)
+        .build()
+        .assemble(assembly)
+        .map(|(binary, _symbol_table)| binary)
+}
+
+#[test]
+fn expressions() {
+    assert_eq!(
+        assemble(".orig x3000\n.blkw 1 + 2 - 1\n.end"),
+        Ok(vec![0; 4])
+    );
+    assert_eq!(assemble(".orig x3000\n.blkw 3-1\n.end"), Ok(vec![0; 4]));
+
+    // A negative number is subtracted even if it's separated by a space,
+    // since the fill word must be preceded by a comma
+    assert_eq!(assemble(".orig x3000\n.blkw 3 -1\n.end"), Ok(vec![0; 4]));
+    assert_eq!(
+        assemble(".orig x3000\n.blkw 2, -1\n.end"),
+        Ok(vec![0xff, 0xff, 0xff, 0xff])
+    );
+    assert_eq!(
+        assemble(".orig x3000\n.align 4 -1\n.end"),
+        Err(ParseError::UnexpectedToken)
+    );
+}
+
+#[test]
+fn labels() {
+    // The count can use the labels declared before the block
+    let (binary, symbol_table) = Lc2AssemblerBuilder::default()
+        .prepend_start_address(false)
+        .build()
+        .assemble(
+            ".orig x3000
+            start .fill 1
+            .fill 2
+            end .blkw end - start, start
+            after .fill 3
+            .end",
+        )
+        .unwrap();
+
+    assert_eq!(
+        binary,
+        [0x00, 0x01, 0x00, 0x02, 0x30, 0x00, 0x30, 0x00, 0x00, 0x03]
+    );
+    assert_eq!(symbol_table["after"], 0x3004);
+
+    // The fill word can use the labels declared after the block
+    assert_eq!(
+        assemble(".orig x3000\n.blkw 1, after + 1\nafter .fill 0\n.end"),
+        Ok(vec![0x30, 0x02, 0x00, 0x00])
+    );
+
+    // But the count can't
+    assert_eq!(
+        assemble(".orig x3000\n.blkw after\nafter .fill 0\n.end"),
+        Err(ParseError::LabelNotDeclared)
+    );
+}
+
+#[test]
+fn next_line() {
+    // Without a comma, a label on the next line is not the fill word
+    assert_eq!(
+        assemble(".orig x3000\n.blkw 1\nlabel .fill 1\n.end"),
+        Ok(vec![0x00, 0x00, 0x00, 0x01])
+    );
+}
+
+#[test]
+fn default_fill() {
+    let assemble = |assembly| {
+        Lc2AssemblerBuilder::default()
+            .prepend_start_address(false)
+            .blkw_fill(0xf025)
+            .build()
+            .assemble(assembly)
+            .map(|(binary, _symbol_table)| binary)
+    };
+
+    assert_eq!(
+        assemble(".orig x3000\n.blkw 2\n.end"),
+        Ok(vec![0xf0, 0x25, 0xf0, 0x25])
+    );
+    assert_eq!(assemble(".orig x3000\n.blkw 1, 0\n.end"), Ok(vec![0, 0]));
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        assemble(".orig x3000\n.blkw 0\n.end"),
+        Err(ParseError::EmptyBlock)
+    );
+    assert_eq!(
+        assemble(".orig x3000\nstart .fill 0\nend .blkw start - end\n.end"),
+        Err(ParseError::BlockTooBig)
+    );
+    assert_eq!(
+        assemble(".orig x3000\n.blkw 1 +\n.end"),
+        Err(ParseError::UnexpectedToken)
+    );
+}
@@ -0,0 +1,142 @@
+use super::*;
+use crate::lc2::disassembler::{disassemble, disassemble_word, DisassembledWord};
+use std::fmt::Write;
+
+fn assemble(assembly: &str) -> (Vec<u16>, HashMap<String, u16>, Option<u16>) {
+    let output = Lc2AssemblerBuilder::default()
+        .prepend_start_address(false)
+        .build()
+        .assemble_with_diagnostic(assembly)
+        .unwrap();
+
+    let words = output
+        .binary
+        .chunks(2)
+        .map(|word| u16::from_be_bytes([word[0], word[1]]))
+        .collect();
+
+    (words, output.symbol_table, output.entry_point)
+}
+
+#[test]
+fn words() {
+    let cases = [
+        (0x1042, "ADD R0, R1, R2"),
+        (0x5A7F, "AND R5, R1, #-1"),
+        (0x1DAF, "ADD R6, R6, #15"),
+        (0x0E05, "BRnzp 0x005"),
+        (0x0000, "NOP 0x000"),
+        (0x0105, "NOP 0x105"),
+        (0x4810, "JSR 0x010"),
+        (0x4010, "JMP 0x010"),
+        (0xC8C3, "JSRR R3, #3"),
+        (0xC080, "JMPR R2, #0"),
+        (0x2201, "LD R1, 0x001"),
+        (0xA3FF, "LDI R1, 0x1FF"),
+        (0xE003, "LEA R0, 0x003"),
+        (0x3E00, "ST R7, 0x000"),
+        (0xB600, "STI R3, 0x000"),
+        (0x6B45, "LDR R5, R5, #5"),
+        (0x7F80, "STR R7, R6, #0"),
+        (0x907F, "NOT R0, R1"),
+        (0xD000, "RET"),
+        (0x8000, "RTI"),
+        (0xF025, "HALT"),
+        (0xF030, "TRAP 0x30"),
+    ];
+    for (word, instruction) in cases {
+        assert_eq!(
+            disassemble_word(word, 0x3000).as_deref(),
+            Some(instruction),
+            "{word:04X}"
+        );
+    }
+
+    // The unused bits must be cleared
+    for word in [0x1048, 0x9000, 0xD001, 0x8100, 0x4200, 0xF125] {
+        assert_eq!(disassemble_word(word, 0x3000), None, "{word:04X}");
+    }
+}
+
+#[test]
+fn program() {
+    let (words, symbol_table, entry_point) = assemble(
+        ".orig x3000
+        main lea r0, string
+        puts
+        brnzp done
+        1: .fill 1
+        done halt
+        string .stringz \"Hi\"
+        .end",
+    );
+
+    let disassembly = disassemble(&words, 0x3000, &symbol_table, entry_point);
+    let lines: Vec<_> = disassembly
+        .iter()
+        .map(|word| (word.label.as_deref(), word.instruction.as_str()))
+        .collect();
+
+    // The data is not decoded, and the numeric labels are skipped
+    assert_eq!(
+        lines,
+        [
+            (Some("main"), "LEA R0, string"),
+            (None, "PUTS"),
+            (None, "BRnzp done"),
+            (None, ".FILL x0001"),
+            (Some("done"), "HALT"),
+            (Some("string"), ".FILL x0048"),
+            (None, ".FILL x0069"),
+            (None, ".FILL x0000"),
+        ]
+    );
+    assert_eq!(
+        disassembly[0],
+        DisassembledWord {
+            address: 0x3000,
+            word: 0xE005,
+            label: Some("main".to_owned()),
+            instruction: "LEA R0, string".to_owned(),
+            target: Some(0x3005),
+        }
+    );
+}
+
+#[test]
+fn round_trip() {
+    let (words, symbol_table, entry_point) = assemble(
+        ".orig x3000
+        and r1, r1, #0
+        loop add r1, r1, #-3
+        ld r2, value
+        jsr subroutine
+        brp loop
+        nop value
+        halt
+        value .fill xabcd
+        subroutine not r2, r2
+        str r2, r6, #1
+        jmpr r7, #0
+        .end",
+    );
+
+    // Without the symbol table the targets become page offsets
+    for symbol_table in [symbol_table, HashMap::new()] {
+        let disassembly = disassemble(&words, 0x3000, &symbol_table, entry_point);
+
+        let mut source = String::from(".orig x3000\n");
+        for word in disassembly {
+            writeln!(
+                source,
+                "{} {}",
+                word.label.unwrap_or_default(),
+                word.instruction
+            )
+            .unwrap();
+        }
+        source.push_str(".end\n");
+
+        assert_eq!(assemble(&source).0, words, "{source}");
+    }
+}
@@ -0,0 +1,64 @@
+use super::*;
+
+fn entry_point(assembly: &str) -> Result<Option<u16>, ParseError> {
+    Lc2AssemblerBuilder::default()
+        .build()
+        .assemble_with_diagnostic(assembly)
+        .map(|output| output.entry_point)
+        .map_err(|diagnostic| diagnostic.error)
+}
+
+#[test]
+fn entry() {
+    assert_eq!(
+        entry_point(".orig x0200\n.fill 0\nmain halt\n.end"),
+        Ok(None)
+    );
+    assert_eq!(
+        entry_point(".orig x0200\n.entry main\n.fill 0\nmain halt\n.end"),
+        Ok(Some(0x0201))
+    );
+    assert_eq!(
+        entry_point(".orig x0200\n.fill 0\nmain halt\n.entry main + 1\n.end"),
+        Ok(Some(0x0202))
+    );
+    assert_eq!(
+        entry_point(".orig x0200\n.entry x3000\n.end"),
+        Ok(Some(0x3000))
+    );
+}
+
+#[test]
+fn end() {
+    assert_eq!(
+        entry_point(".orig x0200\n.fill 0\nmain halt\n.end main"),
+        Ok(Some(0x0201))
+    );
+
+    // The start address is not changed
+    let (binary, _symbol_table) = Lc2AssemblerBuilder::default()
+        .build()
+        .assemble(".orig x0200\n.fill 0\nmain halt\n.end main")
+        .unwrap();
+    assert_eq!(binary[..2], [0x02, 0x00]);
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        entry_point(".orig x0200\n.entry main\nmain halt\n.end main"),
+        Err(ParseError::TooManyEntryPoints)
+    );
+    assert_eq!(
+        entry_point(".orig x0200\n.entry main\n.entry main\nmain halt\n.end"),
+        Err(ParseError::TooManyEntryPoints)
+    );
+    assert_eq!(
+        entry_point(".orig x0200\nhalt\n.end main"),
+        Err(ParseError::LabelNotDeclared)
+    );
+    assert_eq!(
+        entry_point(".orig x0200\n.entry\n.end"),
+        Err(ParseError::UnexpectedToken)
+    );
+}
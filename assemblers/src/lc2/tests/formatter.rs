@@ -0,0 +1,119 @@
+use super::*;
+use crate::lc2::formatter::{BinaryPrefix, Case, DecimalPrefix, HexPrefix, Lc2FormatterBuilder};
+
+const PROGRAM: &str = "\
+; Count down from ten
+  .orig x3000
+main:   and r0,r0,#0 ; clear
+  add R0, r0, 10
+LOOP add r0 , r0, #-1
+        brp loop   // again
+  ; Done
+   halt
+.loop2:  ld r1,x5
+1: .fill 0X1f, $Ff, %101, b11
+end: .blkw 3-1, 'a'
+  .end
+";
+
+fn format(assembly: &str) -> String {
+    Lc2FormatterBuilder::default()
+        .build()
+        .format(assembly)
+        .unwrap()
+}
+
+#[test]
+fn columns() {
+    assert_eq!(
+        format(PROGRAM),
+        "\
+; Count down from ten
+        .ORIG   x3000
+main    AND     R0, R0, #0              ; clear
+        ADD     R0, R0, #10
+LOOP    ADD     R0, R0, #-1
+        BRP     loop                    // again
+        ; Done
+        HALT
+.loop2  LD      R1, x5
+1:      .FILL   x1F, xFF, b101, b11
+end     .BLKW   #3-1, 'a'
+        .END
+"
+    );
+}
+
+#[test]
+fn options() {
+    let formatted = Lc2FormatterBuilder::default()
+        .mnemonic_column(4)
+        .operand_column(10)
+        .comment_column(0)
+        .mnemonic_case(Case::Lower)
+        .register_case(Case::Lower)
+        .hex_digit_case(Case::Lower)
+        .hex_prefix(HexPrefix::Dollar)
+        .decimal_prefix(DecimalPrefix::None)
+        .binary_prefix(BinaryPrefix::Percent)
+        .label_colons(true)
+        .build()
+        .format("main: AND R0,R0,#0 ; clear\nloop .FILL xFF, b1, #-1\n")
+        .unwrap();
+
+    assert_eq!(
+        formatted,
+        "main: and r0, r0, 0 ; clear\nloop: .fill $ff, %1, -1\n"
+    );
+}
+
+#[test]
+fn label_operands() {
+    // A literal like `x5` would be read as a label where a label is expected
+    assert_eq!(
+        format("ld r0, 0x5\nbr 0b1\n.fill 0x5"),
+        "        LD      R0, 0x5\n        BR      0b1\n        .FILL   x5\n"
+    );
+
+    // A label that looks like a number keeps its colon if it's alone
+    assert_eq!(format("xadd:\nb1: halt"), "xadd:\nb1      HALT\n");
+}
+
+#[test]
+fn idempotence() {
+    let inputs = [
+        PROGRAM,
+        "main: ld r0, xadd\nxadd: .fill 1\n",
+        ".orig x3000\n- add r0, r0, #1\nbrp -\n+: .stringz \"a ; b\"\n.end\n",
+        "\n\n  ; only comments   \n\n// and blank lines\n\n\n",
+    ];
+
+    for input in inputs {
+        let formatted = format(input);
+        assert_eq!(format(&formatted), formatted);
+    }
+}
+
+#[test]
+fn same_binary() {
+    let assemble = |assembly: &str| {
+        Lc2AssemblerBuilder::default()
+            .build()
+            .assemble(assembly)
+            .unwrap()
+    };
+
+    let program = PROGRAM.replace("1: ", "").replace("brp loop", "brp main");
+    assert_eq!(assemble(&format(&program)), assemble(&program));
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        Lc2FormatterBuilder::default()
+            .build()
+            .format("add r0, r0, @")
+            .map_err(|diagnostic| (diagnostic.error, diagnostic.span)),
+        Err((ParseError::NonValidToken, 12..13))
+    );
+}
@@ -0,0 +1,100 @@
+use crate::lc2::highlight::{classify, textmate_grammar, tree_sitter_query, TokenKind};
+
+const PROGRAM: &str = "\
+.orig x3000
+main:   lea r0, string ; Print the string
+        ld r1, xadd
+1:      brnp 1b
+-       br -
+        halt
+string  .stringz \"Hi!\"
+xadd    .fill string-main+1
+.end
+";
+
+/// Return every highlighted token with its source
+fn tokens(source: &str) -> Vec<(TokenKind, &str)> {
+    classify(source)
+        .into_iter()
+        .map(|token| (token.kind, &source[token.span]))
+        .collect()
+}
+
+#[test]
+fn classify_tokens() {
+    use TokenKind::{
+        Comment, Directive, LabelDefinition, LabelReference, Mnemonic, Number, Register, String,
+    };
+
+    assert_eq!(
+        tokens(PROGRAM),
+        [
+            (Directive, ".orig"),
+            (Number, "x3000"),
+            (LabelDefinition, "main"),
+            (Mnemonic, "lea"),
+            (Register, "r0"),
+            (LabelReference, "string"),
+            (Comment, "; Print the string"),
+            (Mnemonic, "ld"),
+            (Register, "r1"),
+            (LabelReference, "xadd"),
+            (LabelDefinition, "1:"),
+            (Mnemonic, "brnp"),
+            (LabelReference, "1b"),
+            (LabelDefinition, "-"),
+            (Mnemonic, "br"),
+            (LabelReference, "-"),
+            (Mnemonic, "halt"),
+            (LabelDefinition, "string"),
+            (Directive, ".stringz"),
+            (String, "\"Hi!\""),
+            (LabelDefinition, "xadd"),
+            (Directive, ".fill"),
+            (LabelReference, "string"),
+            (LabelReference, "main"),
+            (Number, "1"),
+            (Directive, ".end"),
+        ]
+    );
+}
+
+#[test]
+fn classify_errors() {
+    // A reserved name used as a label keeps its class, while the tokens that
+    // can't be lexed are skipped
+    assert_eq!(
+        tokens("add: halt\n.fill ^ 'a'"),
+        [
+            (TokenKind::Mnemonic, "add"),
+            (TokenKind::Mnemonic, "halt"),
+            (TokenKind::Directive, ".fill"),
+            (TokenKind::Number, "'a'"),
+        ]
+    );
+}
+
+#[test]
+fn grammars() {
+    let grammar = textmate_grammar();
+    assert!(grammar.contains("\"scopeName\": \"source.lc2\""));
+    assert!(grammar.contains(
+        r#"{ "match": "(?<![\\w.])(?:(?:[rR][0-7]))(?![\\w.])", "name": "variable.language.register.lc2" }"#
+    ));
+    assert!(grammar.contains(r#"{ "match": "(?:(;|//).*)", "name": "comment.line.lc2" }"#));
+    assert!(grammar.contains(r#"(?:\"([^\"\\\\]|\\\\.)*\")"#));
+
+    assert_eq!(
+        tree_sitter_query(),
+        "\
+(mnemonic) @keyword
+(register) @variable.builtin
+(directive) @keyword.directive
+(label_definition) @label
+(label_reference) @label
+(number) @number
+(string) @string
+(comment) @comment
+"
+    );
+}
@@ -0,0 +1,152 @@
+use super::*;
+use crate::{
+    include::{FileSystemResolver, MemoryResolver},
+    lc2::BytePacking,
+};
+use std::sync::Arc;
+
+fn assemble(packing: BytePacking, assembly: &str) -> Result<Vec<u8>, ParseError> {
+    Lc2AssemblerBuilder::default()
+        .optional_starting_orig(true)
+        .optional_end(true)
+        .include_resolver(Arc::new(
+            MemoryResolver::new().with_file("font.bin", [0x01, 0x02, 0x03]),
+        ))
+        .incbin_packing(packing)
+        .build()
+        .assemble(assembly)
+        .map(|(binary, _symbol_table)| binary)
+}
+
+#[test]
+fn packing() {
+    let assembly = ".incbin \"font.bin\"";
+
+    assert_eq!(
+        assemble(BytePacking::BigEndian, assembly),
+        Ok(vec![0x01, 0x02, 0x03, 0x00])
+    );
+    assert_eq!(
+        assemble(BytePacking::LittleEndian, assembly),
+        Ok(vec![0x02, 0x01, 0x00, 0x03])
+    );
+    assert_eq!(
+        assemble(BytePacking::OnePerWord, assembly),
+        Ok(vec![0x00, 0x01, 0x00, 0x02, 0x00, 0x03])
+    );
+
+    assert_eq!(
+        BytePacking::try_from("little-endian"),
+        Ok(BytePacking::LittleEndian)
+    );
+    assert_eq!(
+        BytePacking::try_from("one_per_word"),
+        Ok(BytePacking::OnePerWord)
+    );
+    assert_eq!(
+        BytePacking::try_from("middle_endian"),
+        Err(ParseError::UnknownBytePacking)
+    );
+}
+
+#[test]
+fn offset_and_length() {
+    assert_eq!(
+        assemble(BytePacking::OnePerWord, ".incbin \"font.bin\", 1"),
+        Ok(vec![0x00, 0x02, 0x00, 0x03])
+    );
+    assert_eq!(
+        assemble(BytePacking::OnePerWord, ".incbin \"font.bin\", 1, 1"),
+        Ok(vec![0x00, 0x02])
+    );
+    assert_eq!(
+        assemble(BytePacking::OnePerWord, ".incbin \"font.bin\", 3"),
+        Ok(vec![])
+    );
+
+    assert_eq!(
+        assemble(BytePacking::OnePerWord, ".incbin \"font.bin\", 4"),
+        Err(ParseError::IncludeOutOfRange)
+    );
+    assert_eq!(
+        assemble(BytePacking::OnePerWord, ".incbin \"font.bin\", 1, 3"),
+        Err(ParseError::IncludeOutOfRange)
+    );
+}
+
+#[test]
+fn symbol_table_sizing() {
+    let (_binary, symbol_table) = Lc2AssemblerBuilder::default()
+        .include_resolver(Arc::new(
+            MemoryResolver::new().with_file("font.bin", [0x01, 0x02, 0x03]),
+        ))
+        .build()
+        .assemble(
+            r#"
+            .orig 0x3000
+            .incbin "font.bin"
+            label: .fill 0
+            .end
+            "#,
+        )
+        .unwrap();
+
+    assert_eq!(symbol_table.get("label"), Some(&0x3002));
+}
+
+#[test]
+fn file_system() {
+    let (binary, _symbol_table) = Lc2AssemblerBuilder::default()
+        .optional_starting_orig(true)
+        .optional_end(true)
+        .include_resolver(Arc::new(FileSystemResolver::new(env!(
+            "CARGO_MANIFEST_DIR"
+        ))))
+        .build()
+        .assemble(".incbin \"Cargo.toml\", 0, 9")
+        .unwrap();
+
+    assert_eq!(binary, b"[package]\0");
+
+    // The files outside of the base directory can't be included
+    let assembler = Lc2AssemblerBuilder::default()
+        .optional_starting_orig(true)
+        .optional_end(true)
+        .include_resolver(Arc::new(FileSystemResolver::new(env!(
+            "CARGO_MANIFEST_DIR"
+        ))))
+        .build();
+    for path in [
+        "../Cargo.toml",
+        "src/../Cargo.toml",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"),
+    ] {
+        assert_eq!(
+            assembler.assemble(&format!(".incbin \"{path}\"")),
+            Err(ParseError::IncludeNotFound),
+            "{path}"
+        );
+    }
+    assert!(assembler.assemble(".incbin \"./Cargo.toml\", 0, 1").is_ok());
+}
+
+#[test]
+fn errors() {
+    let assembler = Lc2AssemblerBuilder::default()
+        .optional_starting_orig(true)
+        .optional_end(true)
+        .build();
+    assert_eq!(
+        assembler.assemble(".incbin \"font.bin\""),
+        Err(ParseError::IncludeNotEnabled)
+    );
+
+    assert_eq!(
+        assemble(BytePacking::BigEndian, ".incbin \"missing.bin\""),
+        Err(ParseError::IncludeNotFound)
+    );
+    assert_eq!(
+        assemble(BytePacking::BigEndian, ".incbin font.bin"),
+        Err(ParseError::UnexpectedToken)
+    );
+}
@@ -0,0 +1,173 @@
+use super::*;
+use crate::{
+    lc2::incremental::{IncrementalAssembler, Reuse, TextEdit},
+    lint::{Lint, LintLevel},
+};
+
+const PROGRAM: &str = "\
+.orig x3000
+main    ld r0, value
+loop    add r0, r0, #-1
+        brp loop
+        halt
+value   .fill 5
+.end
+";
+
+/// Replace the first occurrence of `old` after the offset, checking that the
+/// output is the same of a full assembly
+fn replace(assembler: &mut IncrementalAssembler, from: usize, old: &str, new: &str) -> Reuse {
+    let start = from + assembler.source()[from..].find(old).unwrap();
+    assembler.edit([TextEdit {
+        range: start..start + old.len(),
+        text: new.to_owned(),
+    }]);
+
+    assert_eq!(
+        *assembler.output(),
+        Lc2AssemblerBuilder::new()
+            .build()
+            .assemble_with_diagnostic(assembler.source())
+    );
+    assembler.reuse()
+}
+
+#[test]
+fn operands() {
+    let mut assembler = IncrementalAssembler::new(Lc2AssemblerBuilder::new().build(), PROGRAM);
+    assert_eq!(assembler.reuse().relexed_lines, 7);
+
+    // Only the operands of the operations change, so the labels stay put
+    let reuse = replace(&mut assembler, 0, "#-1", "#-2");
+    assert_eq!(
+        reuse,
+        Reuse {
+            relexed_lines: 1,
+            symbol_table: true
+        }
+    );
+    assert!(replace(&mut assembler, 0, "r0, value", "r1, value").symbol_table);
+    assert!(replace(&mut assembler, 0, "brp loop", "brp main").symbol_table);
+
+    // If the second pass fails the first one runs again, to return the same
+    // error of a full assembly. Since it succeeded, the symbol table can still
+    // be reused
+    assert!(!replace(&mut assembler, 0, "brp main", "brp nowhere").symbol_table);
+    assert!(assembler.output().is_err());
+    assert!(replace(&mut assembler, 0, "nowhere", "loop").symbol_table);
+    assert!(assembler.output().is_ok());
+
+    // An operand that is not valid for the first pass
+    assert!(!replace(&mut assembler, 0, "#-2", "#100").symbol_table);
+    assert!(!replace(&mut assembler, 0, "#100", "#1").symbol_table);
+}
+
+#[test]
+fn layout() {
+    let mut assembler = IncrementalAssembler::new(Lc2AssemblerBuilder::new().build(), PROGRAM);
+
+    // New operations, labels and lines move the labels after them
+    let reuse = replace(&mut assembler, 0, "halt\n", "halt\nhalt\n");
+    assert_eq!(
+        reuse,
+        Reuse {
+            relexed_lines: 2,
+            symbol_table: false
+        }
+    );
+    assert!(!replace(&mut assembler, 0, "value   .fill 5", "value   .fill 5, 6").symbol_table);
+    assert!(!replace(&mut assembler, 0, "loop ", "again").symbol_table);
+
+    // Joining two lines lexes them again as one
+    let reuse = replace(&mut assembler, 0, "halt\nhalt", "halt halt");
+    assert_eq!(reuse.relexed_lines, 1);
+
+    // A label can be a statement after an operation, without a comma
+    let mut assembler = IncrementalAssembler::new(
+        Lc2AssemblerBuilder::new().build(),
+        ".orig x3000\nhalt first\n.fill first\n.end",
+    );
+    assert!(!replace(&mut assembler, 0, "halt first", "halt second").symbol_table);
+    assert!(assembler.output().is_err());
+
+    // The operands of a pseudo-operation after an operation change its size
+    let mut assembler = IncrementalAssembler::new(
+        Lc2AssemblerBuilder::new().build(),
+        ".orig x3000\nhalt .blkw 5\nend halt\n.end",
+    );
+    assert!(!replace(&mut assembler, 0, ".blkw 5", ".blkw 50").symbol_table);
+    assert_eq!(
+        assembler.output().as_ref().unwrap().symbol_table["end"],
+        0x3033
+    );
+    assert!(!replace(&mut assembler, 0, "halt .blkw", "halt add r0, r0, #1 .blkw").symbol_table);
+    assert!(replace(&mut assembler, 0, "#1 .blkw", "#2 .blkw").symbol_table);
+    assert!(!replace(&mut assembler, 0, "#2 .blkw 50", "#2 .blkw 40").symbol_table);
+}
+
+#[test]
+fn edits() {
+    let mut assembler = IncrementalAssembler::new(Lc2AssemblerBuilder::new().build(), "");
+    assert!(assembler.output().is_err());
+
+    // The edits are applied in order
+    assembler.edit([
+        TextEdit {
+            range: 0..0,
+            text: ".orig x3000\n.end".to_owned(),
+        },
+        TextEdit {
+            range: 12..12,
+            text: ".stringz \"a\nb\"\n".to_owned(),
+        },
+    ]);
+    assert_eq!(assembler.source(), ".orig x3000\n.stringz \"a\nb\"\n.end");
+    assert_eq!(
+        *assembler.output(),
+        Lc2AssemblerBuilder::new()
+            .build()
+            .assemble_with_diagnostic(assembler.source())
+    );
+
+    // A string that spans many lines is lexed as a whole
+    let reuse = replace(&mut assembler, 12, "b", "bc");
+    assert!(!reuse.symbol_table);
+    assert_eq!(
+        assembler.output().as_ref().unwrap().binary.len(),
+        (1 + 5) * 2
+    );
+}
+
+#[test]
+fn options() {
+    for single_pass in [false, true] {
+        let mut builder = Lc2AssemblerBuilder::new();
+        builder
+            .single_pass(single_pass)
+            .lint(Lint::UnusedLabel, LintLevel::Deny);
+        let mut assembler = IncrementalAssembler::new(builder.build(), PROGRAM);
+
+        // The options and the lints are the ones of a full assembly
+        let full_assembler = builder.build();
+        let full = |source: &str| full_assembler.assemble_with_diagnostic(source);
+        assert_eq!(*assembler.output(), full(PROGRAM));
+        assert!(assembler.output().is_err());
+
+        let start = PROGRAM.find("main").unwrap();
+        assembler.edit([TextEdit {
+            range: start..start + 4,
+            text: "    ".to_owned(),
+        }]);
+        assert_eq!(*assembler.output(), full(assembler.source()));
+        assert!(assembler.output().is_ok());
+
+        // Only the two passes have a symbol table to reuse
+        let start = assembler.source().find("#-1").unwrap();
+        assembler.edit([TextEdit {
+            range: start..start + 3,
+            text: "#-2".to_owned(),
+        }]);
+        assert_eq!(*assembler.output(), full(assembler.source()));
+        assert_eq!(assembler.reuse().symbol_table, !single_pass);
+    }
+}
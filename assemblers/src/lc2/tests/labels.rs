@@ -45,3 +45,154 @@ fn label_redeclaration() {
 
     assert_eq!(error, Err(ParseError::LabelRedeclaration));
 }
+
+#[test]
+fn local_labels() {
+    let assembler = Lc2AssemblerBuilder::default().build();
+
+    let (binary, symbol_table) = assembler
+        .assemble(
+            r"
+            .orig 0x3000
+
+            first:
+              .loop BR .loop
+
+            second:
+              .loop BR .loop
+                    BR first.loop
+
+            .end
+            ",
+        )
+        .unwrap();
+
+    assert_eq!(binary, [0x30, 0x00, 0x0e, 0x00, 0x0e, 0x01, 0x0e, 0x00]);
+    assert_eq!(symbol_table.get("first.loop"), Some(&0x3000));
+    assert_eq!(symbol_table.get("second.loop"), Some(&0x3001));
+    assert_eq!(symbol_table.get("loop"), None);
+}
+
+#[test]
+fn local_label_without_scope() {
+    let assembler = Lc2AssemblerBuilder::default().build();
+
+    let error = assembler.assemble(
+        r"
+            .orig 0x3000
+            .loop BR .loop
+            .end
+            ",
+    );
+
+    assert_eq!(error, Err(ParseError::LocalLabelWithoutScope));
+}
+
+#[test]
+fn numeric_labels() {
+    let assembler = Lc2AssemblerBuilder::default().build();
+
+    let (binary, symbol_table) = assembler
+        .assemble(
+            r"
+            .orig 0x3000
+
+            1: BR 1f
+            1: BR 1b
+               BR 1b
+
+            .end
+            ",
+        )
+        .unwrap();
+
+    assert_eq!(binary, [0x30, 0x00, 0x0e, 0x01, 0x0e, 0x01, 0x0e, 0x01]);
+    assert_eq!(symbol_table.get("1@0"), Some(&0x3000));
+    assert_eq!(symbol_table.get("1@1"), Some(&0x3001));
+}
+
+#[test]
+fn anonymous_labels() {
+    let assembler = Lc2AssemblerBuilder::default().build();
+
+    let (binary, symbol_table) = assembler
+        .assemble(
+            r"
+            .orig 0x3000
+
+            -  BRz +
+            -  BRp ++
+               BR  --
+            +  .fill 0
+            +  .fill 0
+
+            .end
+            ",
+        )
+        .unwrap();
+
+    assert_eq!(
+        binary,
+        [0x30, 0x00, 0x04, 0x03, 0x02, 0x04, 0x0e, 0x00, 0x00, 0x00, 0x00, 0x00]
+    );
+    assert_eq!(symbol_table.get("-@1"), Some(&0x3001));
+    assert_eq!(symbol_table.get("+@1"), Some(&0x3004));
+
+    // There isn't any label to go back to
+    let error = assembler.assemble(".orig 0x3000 BR - .end");
+    assert_eq!(error, Err(ParseError::LabelNotDeclared));
+}
+
+#[test]
+fn labels_like_numbers() {
+    let assembler = Lc2AssemblerBuilder::default().build();
+
+    let (binary, symbol_table) = assembler
+        .assemble(
+            r"
+            .orig x3000
+
+            xadd: BR xadd
+            b101  LEA R0, b101
+                  BR x10
+
+            .end
+            ",
+        )
+        .unwrap();
+
+    assert_eq!(binary, [0x30, 0x00, 0x0e, 0x00, 0xe0, 0x01, 0x0e, 0x10]);
+    assert_eq!(symbol_table.get("xadd"), Some(&0x3000));
+    assert_eq!(symbol_table.get("b101"), Some(&0x3001));
+}
+
+#[test]
+fn reserved_label_names() {
+    let assembler = Lc2AssemblerBuilder::default().build();
+
+    for assembly in [
+        ".orig 0x3000\n IN: BR IN \n .end",
+        ".orig 0x3000\n r1 ADD R1, R1, 1 \n .end",
+        ".orig 0x3000\n .fill: .fill 0 \n .end",
+    ] {
+        assert_eq!(
+            assembler.assemble(assembly),
+            Err(ParseError::ReservedLabelName)
+        );
+    }
+
+    // Labels that only start like a reserved word are fine
+    let (_binary, symbol_table) = assembler
+        .assemble(
+            r"
+            .orig 0x3000
+            r1x:         ADD R0, R0, 1
+            BRz_done:    BRz BRz_done
+            add_numbers: ADD R0, R0, R1
+            .end
+            ",
+        )
+        .unwrap();
+
+    assert_eq!(symbol_table.len(), 3);
+}
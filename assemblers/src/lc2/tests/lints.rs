@@ -0,0 +1,315 @@
+use super::*;
+use crate::{
+    lint::{Lint, LintLevel, Warning},
+    Diagnostic,
+};
+use architectures::lc2::Gpr;
+
+/// The data-flow lints are checked on their own, since they would trigger on
+/// most of the short snippets used by the other tests
+const DATA_FLOW: [Lint; 3] = [
+    Lint::UninitializedRegister,
+    Lint::ClobberedReturnAddress,
+    Lint::CalleeSavedRegister,
+];
+
+fn lints(assembly: &str) -> Vec<(Lint, usize)> {
+    let mut builder = Lc2AssemblerBuilder::default();
+    for lint in DATA_FLOW {
+        builder.lint(lint, LintLevel::Allow);
+    }
+    warnings(builder.optional_starting_orig(true), assembly)
+}
+
+fn data_flow_lints(assembly: &str) -> Vec<(Lint, usize)> {
+    let mut builder = Lc2AssemblerBuilder::default();
+    for lint in Lint::ALL
+        .into_iter()
+        .filter(|lint| !DATA_FLOW.contains(lint))
+    {
+        builder.lint(lint, LintLevel::Allow);
+    }
+    warnings(builder.optional_starting_orig(true), assembly)
+}
+
+fn warnings(builder: &mut Lc2AssemblerBuilder, assembly: &str) -> Vec<(Lint, usize)> {
+    builder
+        .optional_end(true)
+        .build()
+        .assemble_with_diagnostic(assembly)
+        .unwrap()
+        .warnings
+        .into_iter()
+        .map(|warning| (warning.lint, warning.line))
+        .collect()
+}
+
+#[test]
+fn unused_label() {
+    assert_eq!(
+        lints("main ld r0, used\nhalt\nused .fill 1\nunused .fill 2"),
+        [(Lint::UnusedLabel, 1), (Lint::UnusedLabel, 4)]
+    );
+
+    // Local labels are qualified by their scope, while numeric and anonymous
+    // labels are not checked
+    assert_eq!(
+        lints(".entry main\nmain brz .loop\nhalt\n.loop brnp 1f\n.unused halt\n1: halt\n-: halt"),
+        [(Lint::UnusedLabel, 5)]
+    );
+}
+
+#[test]
+fn unreachable_code() {
+    assert_eq!(
+        lints(".entry main\nmain halt\nadd r0, r0, r0\nadd r0, r0, r0"),
+        [(Lint::UnreachableCode, 3)]
+    );
+    assert_eq!(
+        lints(".entry main\nmain ret\nrti\njmpr r1, 0\nbr main\nlabel rti\njmp main"),
+        [
+            (Lint::UnreachableCode, 3),
+            (Lint::UnusedLabel, 6),
+            (Lint::UnreachableCode, 7)
+        ]
+    );
+
+    // A label can make the code reachable, and a conditional branch can fall
+    // through
+    assert_eq!(
+        lints(".entry main\nmain brz main\nadd r0, r0, r0\nhalt\n1: add r0, r0, r0\nbr 1b"),
+        []
+    );
+}
+
+#[test]
+fn data_fallthrough() {
+    assert_eq!(
+        lints(".entry main\nmain add r0, r0, r0\n.fill 1"),
+        [(Lint::DataFallthrough, 3)]
+    );
+    assert_eq!(
+        lints(".entry main\nmain jsr sub\nsub .stringz \"a\""),
+        [(Lint::DataFallthrough, 3)]
+    );
+
+    // The data after a `HALT` or at the start of the program is fine
+    assert_eq!(
+        lints(".entry main\n.fill 1\nmain halt\n.blkw 2\n.fill 3"),
+        []
+    );
+}
+
+#[test]
+fn nop_branch() {
+    assert_eq!(
+        lints(".entry main\nmain brnop main\nbrz next\nnext halt"),
+        [(Lint::NopBranch, 2), (Lint::NopBranch, 3)]
+    );
+
+    // An explicit `NOP` is fine
+    assert_eq!(lints(".entry main\nmain nop 0\nhalt"), []);
+}
+
+#[test]
+fn self_comparison() {
+    assert_eq!(
+        lints(".entry main\nmain not r1, r0\nadd r1, r1, #1\nadd r1, r1, r0\nhalt"),
+        [(Lint::SelfComparison, 4)]
+    );
+    assert_eq!(
+        lints(".entry main\nmain not r2, r0\nadd r1, r2, #1\nadd r3, r0, r1\nhalt"),
+        [(Lint::SelfComparison, 4)]
+    );
+
+    // Comparing different registers is fine, and so is overwriting the source
+    assert_eq!(
+        lints(".entry main\nmain not r1, r0\nadd r1, r1, #1\nadd r1, r1, r2\nhalt"),
+        []
+    );
+    assert_eq!(
+        lints(".entry main\nmain not r1, r0\nadd r1, r1, #1\nld r0, main\nadd r1, r1, r0\nhalt"),
+        []
+    );
+}
+
+#[test]
+fn wrapped_negation() {
+    assert_eq!(
+        lints(".fill #-32768\n.fill -32769\n.fill #-40000"),
+        [(Lint::WrappedNegation, 2), (Lint::WrappedNegation, 3)]
+    );
+}
+
+#[test]
+fn uninitialized_register() {
+    assert_eq!(
+        data_flow_lints(
+            ".entry main\nmain add r0, r1, #1\nbrz next\nld r1, main\nnext add r2, r1, r0\nhalt"
+        ),
+        [
+            (Lint::UninitializedRegister, 2),
+            (Lint::UninitializedRegister, 5)
+        ]
+    );
+
+    // The registers written on every path are fine
+    assert_eq!(
+        data_flow_lints(".entry main\nmain and r0, r0, #0\nbrz next\nnext add r1, r0, #1\nhalt"),
+        [(Lint::UninitializedRegister, 2)]
+    );
+    assert_eq!(
+        data_flow_lints(".entry main\nmain ld r0, main\nnot r1, r0\nhalt"),
+        []
+    );
+}
+
+#[test]
+fn clobbered_return_address() {
+    assert_eq!(
+        data_flow_lints(
+            ".entry main\nmain jsr sub\nhalt\nsub ld r0, sub\njsr inner\nret\ninner ld r1, sub\nret"
+        ),
+        [(Lint::ClobberedReturnAddress, 5)]
+    );
+
+    // Saving and restoring R7 is fine
+    assert_eq!(
+        data_flow_lints(
+            ".entry main\nmain jsr sub\nhalt\nsub st r7, save\njsr inner\nld r7, save\nret\ninner ret\nsave .fill 0"
+        ),
+        []
+    );
+}
+
+#[test]
+fn callee_saved_register() {
+    let assembly = ".entry main\nmain jsr sub\nhalt\nsub ld r4, value\nst r5, save\nld r5, value\nld r5, save\nret\nvalue .fill 1\nsave .fill 0";
+
+    // No register is callee-saved by default
+    assert_eq!(data_flow_lints(assembly), []);
+
+    let lints = Lc2AssemblerBuilder::default()
+        .optional_starting_orig(true)
+        .optional_end(true)
+        .callee_saved_registers([Gpr::R4, Gpr::R5])
+        .build()
+        .assemble_with_diagnostic(assembly)
+        .unwrap()
+        .warnings;
+    assert_eq!(
+        lints,
+        [Warning {
+            lint: Lint::CalleeSavedRegister,
+            message: None,
+            span: 34..36,
+            line: 4
+        }]
+    );
+
+    // Saving a register after overwriting it doesn't restore it
+    let lints = Lc2AssemblerBuilder::default()
+        .optional_starting_orig(true)
+        .optional_end(true)
+        .callee_saved_registers([Gpr::R4])
+        .build()
+        .assemble_with_diagnostic(
+            ".entry main\nmain jsr sub\nhalt\nsub add r4, r4, #1\nst r4, save\nld r4, save\nret\nsave .fill 0",
+        )
+        .unwrap()
+        .warnings;
+    assert_eq!(
+        lints.iter().map(|warning| warning.line).collect::<Vec<_>>(),
+        [4]
+    );
+
+    // Only a load from the slot where the register was saved restores it
+    for (restore, line) in [
+        ("ld r6, other", Some(5)),
+        ("ldi r6, save", Some(5)),
+        ("ld r6, save", None),
+    ] {
+        let lints = Lc2AssemblerBuilder::default()
+            .optional_starting_orig(true)
+            .optional_end(true)
+            .callee_saved_registers([Gpr::R6])
+            .build()
+            .assemble_with_diagnostic(&format!(
+                ".entry main\nmain jsr sub\nhalt\nsub st r6, save\nadd r6, r6, #1\n{restore}\nret\nsave .fill 0\nother .fill 0"
+            ))
+            .unwrap()
+            .warnings;
+        assert_eq!(
+            lints
+                .iter()
+                .find(|warning| warning.lint == Lint::CalleeSavedRegister)
+                .map(|warning| warning.line),
+            line,
+            "{restore}"
+        );
+    }
+
+    // The stack slots are matched by their base register and offset
+    let lints = Lc2AssemblerBuilder::default()
+        .optional_starting_orig(true)
+        .optional_end(true)
+        .callee_saved_registers([Gpr::R5])
+        .build()
+        .assemble_with_diagnostic(
+            ".entry main\nmain jsr sub\nhalt\nsub str r5, r6, #0\nadd r5, r5, #1\nldr r5, r6, #1\nldr r5, r6, #0\nret",
+        )
+        .unwrap()
+        .warnings;
+    assert_eq!(lints, []);
+}
+
+#[test]
+fn data_flow_without_entry_point() {
+    // Without an entry point the analysis would start from the origin, which
+    // could be data
+    assert_eq!(data_flow_lints("main add r0, r1, #1\nhalt"), []);
+    assert_eq!(
+        data_flow_lints(".entry main\nmain add r0, r1, #1\nhalt"),
+        [(Lint::UninitializedRegister, 2)]
+    );
+}
+
+#[test]
+fn levels() {
+    let assemble = |level| {
+        Lc2AssemblerBuilder::default()
+            .optional_starting_orig(true)
+            .optional_end(true)
+            .lint(Lint::UnusedLabel, level)
+            .build()
+            .assemble_with_diagnostic("main halt\n.fill -40000")
+            .map(|output| output.warnings.len())
+    };
+
+    assert_eq!(assemble(LintLevel::Warn), Ok(2));
+    assert_eq!(assemble(LintLevel::Allow), Ok(1));
+    assert_eq!(
+        assemble(LintLevel::Deny),
+        Err(Diagnostic {
+            error: ParseError::DeniedLint(Lint::UnusedLabel),
+            span: 0..4,
+            line: 1
+        })
+    );
+}
+
+#[test]
+fn names() {
+    for lint in Lint::ALL {
+        assert_eq!(Lint::try_from(lint.name()), Ok(lint));
+    }
+
+    assert_eq!(Lint::try_from("unused-label"), Ok(Lint::UnusedLabel));
+    assert_eq!(Lint::try_from("unknown"), Err(ParseError::UnknownLint));
+
+    assert_eq!(LintLevel::try_from("Deny"), Ok(LintLevel::Deny));
+    assert_eq!(
+        LintLevel::try_from("forbid"),
+        Err(ParseError::UnknownLintLevel)
+    );
+}
@@ -1,7 +1,46 @@
-use crate::{lc2::Lc2AssemblerBuilder, Assembler, ParseError};
+use crate::{
+    lc2::{Lc2AssemblerBuilder, Lc2Output},
+    Assembler, Diagnostic, ParseError,
+};
+use std::collections::HashMap;
 
+mod align;
+mod analysis;
+mod assertions;
+mod ast;
+mod blkw;
+mod disassembler;
+mod entry_point;
+mod formatter;
+mod highlight;
+mod incbin;
+mod incremental;
 mod labels;
+mod lints;
 mod orig_end;
 mod parse_numbers;
 mod parse_operations;
 mod parse_pseudo_operations;
+mod single_pass;
+mod strict_lines;
+mod string_encoding;
+mod testing;
+
+/// Assemble in a single pass, checking that the output is the same of the two
+/// passes assembly
+fn assemble_single_pass(
+    builder: &mut Lc2AssemblerBuilder,
+    assembly: &str,
+) -> Result<Lc2Output, Diagnostic> {
+    let two_passes = builder
+        .single_pass(false)
+        .build()
+        .assemble_with_diagnostic(assembly);
+    let single_pass = builder
+        .single_pass(true)
+        .build()
+        .assemble_with_diagnostic(assembly);
+
+    assert_eq!(single_pass, two_passes, "{assembly}");
+    single_pass
+}
@@ -106,3 +106,45 @@ fn hexadecimal() {
     assert_eq!(Token::lexer("x10000").next().unwrap(), Err(()));
     assert_eq!(Token::lexer("$10000").next().unwrap(), Err(()));
 }
+
+#[test]
+fn character() {
+    assert_eq!(Token::lexer("'a'").next().unwrap(), Ok(Token::Number(97)));
+    assert_eq!(Token::lexer("#'a'").next().unwrap(), Ok(Token::Number(97)));
+    assert_eq!(
+        Token::lexer("-'0'").next().unwrap(),
+        Ok(Token::Number(!48 + 1))
+    );
+    assert_eq!(
+        Token::lexer("#-'0'").next().unwrap(),
+        Ok(Token::Number(!48 + 1))
+    );
+
+    // Escaped characters
+    assert_eq!(Token::lexer(r"'\n'").next().unwrap(), Ok(Token::Number(10)));
+    assert_eq!(Token::lexer(r"'\''").next().unwrap(), Ok(Token::Number(39)));
+    assert_eq!(Token::lexer(r"'\e'").next().unwrap(), Ok(Token::Number(27)));
+    assert_eq!(
+        Token::lexer(r"'\x41'").next().unwrap(),
+        Ok(Token::Number(65))
+    );
+    assert_eq!(
+        Token::lexer(r"'\101'").next().unwrap(),
+        Ok(Token::Number(65))
+    );
+    assert_eq!(
+        Token::lexer(r"'☺'").next().unwrap(),
+        Ok(Token::Number(0x263a))
+    );
+    assert_eq!(
+        Token::lexer(r"'\u{e8}'").next().unwrap(),
+        Ok(Token::Number(0xe8))
+    );
+
+    // Not a single character that fits into a word
+    assert_eq!(Token::lexer("'ab'").next().unwrap(), Err(()));
+    assert_eq!(Token::lexer(r"'\u{1f600}'").next().unwrap(), Err(()));
+
+    // Invalid escape sequence
+    assert_eq!(Token::lexer(r"'\q'").next().unwrap(), Err(()));
+}
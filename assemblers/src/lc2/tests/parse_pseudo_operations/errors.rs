@@ -62,3 +62,60 @@ fn stringzp_not_enabled() {
         Err(ParseError::NonValidToken)
     );
 }
+
+#[test]
+fn invalid_escapes() {
+    assert_eq!(
+        assemble(r#".stringz "\q""#),
+        Err(ParseError::InvalidEscapeSequence)
+    );
+    assert_eq!(
+        assemble(r#".stringz "\x""#),
+        Err(ParseError::InvalidEscapeSequence)
+    );
+    assert_eq!(
+        assemble(r#".stringz "\u{110000}""#),
+        Err(ParseError::InvalidEscapeSequence)
+    );
+    assert_eq!(
+        assemble(r#".stringz "\u{e8""#),
+        Err(ParseError::InvalidEscapeSequence)
+    );
+    assert_eq!(
+        assemble(r#".stringz "\400""#),
+        Err(ParseError::InvalidEscapeSequence)
+    );
+}
+
+#[test]
+fn invalid_character() {
+    assert_eq!(
+        assemble(".fill 'ab'"),
+        Err(ParseError::InvalidCharacterLiteral)
+    );
+    assert_eq!(
+        assemble(r".fill '\z'"),
+        Err(ParseError::InvalidEscapeSequence)
+    );
+}
+
+#[test]
+fn fill_list() {
+    assert_eq!(assemble(".fill 1,"), Err(ParseError::NoMoreTokens));
+    assert_eq!(
+        assemble(".word 1, \"Unexpected\""),
+        Err(ParseError::UnexpectedToken)
+    );
+}
+
+#[test]
+fn bytes() {
+    assert_eq!(
+        assemble(".bytes 1, 256"),
+        Err(ParseError::NumberLiteralTooBig)
+    );
+    assert_eq!(
+        assemble(".bytes -129"),
+        Err(ParseError::NumberLiteralTooBig)
+    );
+}
@@ -35,13 +35,14 @@ fn blkw() {
             .collect::<Vec<_>>()
     );
 
-    // Without comma
+    // Without comma the fill word is not an operand
     assert_eq!(
-        assemble(".blkw 10 0xabcd"),
-        [0xabcd_u16; 10]
-            .iter()
-            .flat_map(|x| x.to_be_bytes())
-            .collect::<Vec<_>>()
+        Lc2AssemblerBuilder::default()
+            .optional_starting_orig(true)
+            .optional_end(true)
+            .build()
+            .assemble(".blkw 10 0xabcd"),
+        Err(ParseError::UnexpectedToken)
     );
 
     // Without second argument
@@ -60,3 +61,68 @@ fn stringzp() {
         [b'e', b'T', b't', b's', 0, 0]
     );
 }
+
+#[test]
+fn stringz_escapes() {
+    assert_eq!(
+        assemble(r#".stringz "\"\\\x41\101\e\a\b\u{e8}""#),
+        [0, b'"', 0, b'\\', 0, b'A', 0, b'A', 0, 0x1b, 0, 0x07, 0, 0x08, 0, 0xc3, 0, 0xa8, 0, 0]
+    );
+}
+
+#[test]
+fn character_operands() {
+    assert_eq!(assemble("ADD R0, R0, '\\t'"), [0x10, 0x29]);
+    assert_eq!(assemble(".fill #-'0'"), (!48_u16 + 1).to_be_bytes());
+    assert_eq!(assemble(".blkw 2, '*'"), [0, b'*', 0, b'*']);
+}
+
+#[test]
+fn fill_list() {
+    assert_eq!(assemble(".fill 0x1234, 0x5678"), [0x12, 0x34, 0x56, 0x78]);
+    assert_eq!(assemble(".word 1, 2, 3"), [0, 1, 0, 2, 0, 3]);
+}
+
+#[test]
+fn string() {
+    assert_eq!(
+        assemble(".string \"Test\""),
+        [0, b'T', 0, b'e', 0, b's', 0, b't']
+    );
+    assert_eq!(assemble(".ascii \"Te\""), [0, b'T', 0, b'e']);
+}
+
+#[test]
+fn pstring() {
+    assert_eq!(
+        assemble(".pstring \"Test\""),
+        [0, 4, 0, b'T', 0, b'e', 0, b's', 0, b't']
+    );
+    assert_eq!(assemble(".pstring \"\""), [0, 0]);
+}
+
+#[test]
+fn bytes() {
+    assert_eq!(assemble(".bytes 1, 2, 3"), [0x02, 0x01, 0x00, 0x03]);
+    assert_eq!(assemble(".bytes 0xff, -1, 'a'"), [0xff, 0xff, 0x00, b'a']);
+}
+
+#[test]
+fn symbol_table_sizing() {
+    let (_binary, symbol_table) = Lc2AssemblerBuilder::default()
+        .optional_starting_orig(true)
+        .optional_end(true)
+        .build()
+        .assemble(
+            r#"
+            .string "ab"
+            .pstring "ab"
+            .word 1, 2
+            .bytes 1, 2, 3
+            label: .fill 0
+            "#,
+        )
+        .unwrap();
+
+    assert_eq!(symbol_table.get("label"), Some(&9));
+}
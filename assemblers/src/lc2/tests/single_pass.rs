@@ -0,0 +1,148 @@
+use super::*;
+use crate::lint::{Lint, LintLevel};
+
+fn assemble_error(assembly: &str) -> ParseError {
+    assemble_single_pass(&mut Lc2AssemblerBuilder::new(), assembly)
+        .unwrap_err()
+        .error
+}
+
+#[test]
+fn forward_references() {
+    let output = assemble_single_pass(
+        &mut Lc2AssemblerBuilder::new(),
+        r#"
+        .orig x3000
+        .entry main
+        .test first
+        .setreg r0, value
+        .expect r1, end - value
+        .assert end - main == 9, "size"
+main    ld r1, value
+        brnzp 1f
+        .blkw 2, end
+1:      lea r2, .local
+        halt
+.local  .align 4, main
+value   .fill 5
+end     .end
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(output.entry_point, Some(0x3000));
+    assert_eq!(
+        output.binary,
+        [
+            0x30, 0x00, // Start address
+            0x22, 0x08, // ld r1, value
+            0x0e, 0x04, // brnzp 1f
+            0x30, 0x09, 0x30, 0x09, // .blkw 2, end
+            0xe4, 0x06, // lea r2, .local
+            0xf0, 0x25, // halt
+            0x30, 0x00, 0x30, 0x00, // .align 4, main
+            0x00, 0x05, // .fill 5
+        ]
+    );
+    assert_eq!(output.symbol_table.get("main.local"), Some(&0x3006));
+
+    // The data directives report their patched words
+    let data: Vec<_> = output
+        .data
+        .iter()
+        .map(|data| (data.address, data.words.as_slice()))
+        .collect();
+    assert_eq!(
+        data,
+        [
+            (0x3002, [0x3009, 0x3009].as_slice()),
+            (0x3006, &[0x3000, 0x3000]),
+            (0x3008, &[0x0005]),
+        ]
+    );
+
+    // The labels that are also number literals are read as numbers only if
+    // they are never declared
+    assemble_single_pass(
+        &mut Lc2AssemblerBuilder::new(),
+        ".orig x3000\nld r0, xab\n.end",
+    )
+    .unwrap();
+    assemble_single_pass(
+        &mut Lc2AssemblerBuilder::new(),
+        ".orig x3000\nld r0, xab\nxab .fill 1\n.end",
+    )
+    .unwrap();
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        assemble_single_pass(
+            &mut Lc2AssemblerBuilder::new(),
+            ".orig x3000\nld r0, nowhere\n.end"
+        ),
+        Err(Diagnostic {
+            error: ParseError::LabelNotDeclared,
+            span: 19..26,
+            line: 2
+        })
+    );
+    assert_eq!(
+        assemble_error(".orig x3000\nld r0, far\n.blkw x200\nfar .fill 1\n.end"),
+        ParseError::LabelNotOnSamePage
+    );
+    assert_eq!(
+        assemble_error(".orig x3000\n.assert later == x3000\nhalt\nlater halt\n.end"),
+        ParseError::AssertionFailed(None)
+    );
+
+    // The errors of the labels are reported only if the rest of the assembly
+    // is valid, like in the second pass
+    assert_eq!(
+        assemble_error(".orig x3000\nld r0, nowhere\n.end\nhalt"),
+        ParseError::EndNotLast
+    );
+    assert_eq!(
+        assemble_error(".orig x3000\nld r0, .local\nmain halt\n.entry main\n.entry main\n.end"),
+        ParseError::TooManyEntryPoints
+    );
+
+    // The first error is reported, even if it's found only at the end
+    let error = assemble_single_pass(
+        &mut Lc2AssemblerBuilder::new(),
+        ".orig x3000\n.blkw 1, nowhere\nback .blkw x200\nld r0, back\n.end",
+    );
+    assert_eq!(error.unwrap_err().line, 2);
+    let error = assemble_single_pass(
+        &mut Lc2AssemblerBuilder::new(),
+        ".orig x3000\nback .blkw x200\nld r0, back\n.blkw 1, nowhere\n.end",
+    );
+    assert_eq!(error.unwrap_err().error, ParseError::LabelNotOnSamePage);
+    assert_eq!(
+        assemble_error(".orig x3000\nld r0, x200\n.end"),
+        ParseError::NumberLiteralTooBig
+    );
+}
+
+#[test]
+fn lints() {
+    // The lints see the patched operations
+    let output = assemble_single_pass(
+        &mut Lc2AssemblerBuilder::new(),
+        ".orig x3000\nbrz next\nnext halt\nunused .fill 1\n.end",
+    )
+    .unwrap();
+    let lints: Vec<Lint> = output.warnings.iter().map(|warning| warning.lint).collect();
+    assert_eq!(lints, [Lint::NopBranch, Lint::UnusedLabel]);
+
+    assert_eq!(
+        assemble_single_pass(
+            Lc2AssemblerBuilder::new().lint(Lint::NopBranch, LintLevel::Deny),
+            ".orig x3000\nbrz next\nnext halt\n.end",
+        )
+        .unwrap_err()
+        .error,
+        ParseError::DeniedLint(Lint::NopBranch)
+    );
+}
@@ -0,0 +1,93 @@
+use super::*;
+use crate::Diagnostic;
+
+fn assemble_strict(assembly: &str) -> Result<Vec<u8>, Diagnostic> {
+    Lc2AssemblerBuilder::default()
+        .strict_lines(true)
+        .build()
+        .assemble_with_diagnostic(assembly)
+        .map(|output| output.binary)
+}
+
+#[test]
+fn normal() {
+    let binary = assemble_strict(
+        r"
+        ; A comment before the start
+        .orig 0x3000 ; A comment after the start
+
+        label:
+        loop:  ADD R0, R0, 1 ; A comment
+               BR loop
+
+        .end
+        ",
+    );
+
+    assert_eq!(binary, Ok(vec![0x30, 0x00, 0x10, 0x21, 0x0e, 0x00]));
+}
+
+#[test]
+fn missing_operand() {
+    let assembly = ".orig 0x3000\nADD R1, R2\nR3\n.end";
+
+    let diagnostic = assemble_strict(assembly).unwrap_err();
+    assert_eq!(diagnostic.error, ParseError::MissingOperand);
+    assert_eq!(diagnostic.line, 2);
+
+    // Without strict lines the operand is taken from the next line
+    let assembler = Lc2AssemblerBuilder::default().build();
+    assert_eq!(
+        assembler.assemble(assembly),
+        Ok((vec![0x30, 0x00, 0x12, 0x83], HashMap::new()))
+    );
+}
+
+#[test]
+fn trailing_tokens() {
+    for (assembly, line) in [
+        (".orig 0x3000 R0\n.end", 1),
+        (".orig 0x3000\nADD R1, R2, R3 R4\n.end", 2),
+        (".orig 0x3000\n.fill 1 2\n.end", 2),
+        (".orig 0x3000\n\nfirst: second: .fill 1\n.end", 3),
+    ] {
+        let diagnostic = assemble_strict(assembly).unwrap_err();
+        assert_eq!(diagnostic.error, ParseError::TrailingTokens);
+        assert_eq!(diagnostic.line, line);
+    }
+}
+
+#[test]
+fn diagnostic_span() {
+    let assembly = ".orig 0x3000\n\n  BR missing\n.end";
+
+    let diagnostic = Lc2AssemblerBuilder::default()
+        .build()
+        .assemble_with_diagnostic(assembly)
+        .unwrap_err();
+
+    assert_eq!(diagnostic.error, ParseError::LabelNotDeclared);
+    assert_eq!(diagnostic.line, 3);
+    assert_eq!(&assembly[diagnostic.span], "missing");
+}
+
+#[test]
+fn reserved_label_names() {
+    for assembly in [
+        ".orig 0x3000\nIN ADD R0, R0, #1\n.end",
+        ".orig 0x3000\nOUT HALT\n.end",
+        ".orig 0x3000\nHALT .fill 1\n.end",
+    ] {
+        let diagnostic = assemble_strict(assembly).unwrap_err();
+        assert_eq!(diagnostic.error, ParseError::ReservedLabelName);
+        assert_eq!(diagnostic.line, 2);
+        assert_eq!(diagnostic.span.start, 13);
+    }
+
+    // Without strict lines they are two operations
+    let assembler = Lc2AssemblerBuilder::default().build();
+    assert_eq!(
+        assembler.assemble(".orig 0x3000\nOUT HALT\n.end"),
+        Ok((vec![0x30, 0x00, 0xf0, 0x21, 0xf0, 0x25], HashMap::new()))
+    );
+}
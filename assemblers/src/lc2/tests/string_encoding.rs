@@ -0,0 +1,111 @@
+use super::*;
+use crate::lc2::StringEncoding;
+
+fn assemble(encoding: StringEncoding, assembly: &str) -> Result<Vec<u8>, ParseError> {
+    Lc2AssemblerBuilder::default()
+        .optional_starting_orig(true)
+        .optional_end(true)
+        .enable_stringzp(true)
+        .string_encoding(encoding)
+        .build()
+        .assemble(assembly)
+        .map(|(binary, _symbol_table)| binary)
+}
+
+#[test]
+fn encodings() {
+    let assembly = ".stringz \"è\"";
+
+    assert_eq!(
+        assemble(StringEncoding::Ascii, assembly),
+        Err(ParseError::CharacterNotEncodable)
+    );
+    assert_eq!(
+        assemble(StringEncoding::Latin1, assembly),
+        Ok(vec![0x00, 0xe8, 0, 0])
+    );
+    assert_eq!(
+        assemble(StringEncoding::Utf8, assembly),
+        Ok(vec![0x00, 0xc3, 0x00, 0xa8, 0, 0])
+    );
+    assert_eq!(
+        assemble(StringEncoding::Utf16, assembly),
+        Ok(vec![0x00, 0xe8, 0, 0])
+    );
+
+    // Characters outside of the Basic Multilingual Plane
+    assert_eq!(
+        assemble(StringEncoding::Utf16, ".stringz \"😀\""),
+        Ok(vec![0xd8, 0x3d, 0xde, 0x00, 0, 0])
+    );
+    assert_eq!(
+        assemble(StringEncoding::Latin1, ".stringz \"😀\""),
+        Err(ParseError::CharacterNotEncodable)
+    );
+}
+
+#[test]
+fn override_encoding() {
+    assert_eq!(
+        assemble(StringEncoding::Ascii, ".stringz \"è\", latin1"),
+        Ok(vec![0x00, 0xe8, 0, 0])
+    );
+    assert_eq!(
+        assemble(StringEncoding::Ascii, ".stringz \"è\", UTF8"),
+        Ok(vec![0x00, 0xc3, 0x00, 0xa8, 0, 0])
+    );
+    assert_eq!(
+        assemble(StringEncoding::Utf8, ".stringz \"è\", ebcdic"),
+        Err(ParseError::UnknownEncoding)
+    );
+}
+
+#[test]
+fn packed() {
+    assert_eq!(
+        assemble(StringEncoding::Latin1, ".stringzp \"èa\""),
+        Ok(vec![b'a', 0xe8, 0, 0])
+    );
+    assert_eq!(
+        assemble(StringEncoding::Utf8, ".stringzp \"è\""),
+        Ok(vec![0xa8, 0xc3, 0, 0])
+    );
+    assert_eq!(
+        assemble(StringEncoding::Utf16, ".stringzp \"è\""),
+        Err(ParseError::EncodingNotPackable)
+    );
+}
+
+#[test]
+fn escaped_bytes() {
+    // The bytes written as escape sequences are not encoded, even in UTF-8
+    for (escape, byte) in [
+        (r"\x80", 0x80),
+        (r"\xA0", 0xa0),
+        (r"\xff", 0xff),
+        (r"\377", 0xff),
+    ] {
+        assert_eq!(
+            assemble(StringEncoding::Utf8, &format!(".stringz \"{escape}\"")),
+            Ok(vec![0x00, byte, 0, 0])
+        );
+    }
+    assert_eq!(
+        assemble(StringEncoding::Utf8, r#".stringz "è\xe8""#),
+        Ok(vec![0x00, 0xc3, 0x00, 0xa8, 0x00, 0xe8, 0, 0])
+    );
+    assert_eq!(
+        assemble(StringEncoding::Utf8, r#".stringzp "\xffa""#),
+        Ok(vec![b'a', 0xff, 0, 0])
+    );
+
+    // Nor checked by the other encodings
+    assert_eq!(
+        assemble(StringEncoding::Ascii, r#".stringz "\xff""#),
+        Ok(vec![0x00, 0xff, 0, 0])
+    );
+    assert_eq!(
+        assemble(StringEncoding::Utf16, r#".stringz "\xff""#),
+        Ok(vec![0x00, 0xff, 0, 0])
+    );
+}
@@ -0,0 +1,153 @@
+use super::*;
+use crate::lc2::testing::{Expectation, ExpectationKind, Machine, TestCase, TestFailure};
+use architectures::lc2::Gpr;
+
+const PROGRAM: &str = "\
+.orig x3000
+        add r1, r0, r0
+        getc
+        out
+        halt
+double  .fill 0
+
+.test doubles
+        .setreg r0, 5
+        .input \"a\"
+        .expect r1, 10
+        .expect_output \"a\"
+
+.test \"doubles a label\"
+        .setreg r0, double+1
+        .input \"bc\"
+        .expect r1, x6001
+        .expect_output \"x\"
+.end
+";
+
+#[test]
+fn manifest() {
+    let output = Lc2AssemblerBuilder::new()
+        .build()
+        .assemble_with_diagnostic(PROGRAM)
+        .unwrap();
+
+    // The test directives don't emit any word
+    assert_eq!(output.binary.len(), 6 * 2);
+    assert_eq!(
+        output.tests[0],
+        TestCase {
+            name: "doubles".to_owned(),
+            registers: vec![(Gpr::R0, 5)],
+            input: "a".to_owned(),
+            expectations: vec![
+                Expectation {
+                    kind: ExpectationKind::Register(Gpr::R1, 10),
+                    line: 11,
+                    span: 153..160,
+                },
+                Expectation {
+                    kind: ExpectationKind::Output("a".to_owned()),
+                    line: 12,
+                    span: 176..190,
+                },
+            ],
+            line: 8,
+            span: 90..95,
+        }
+    );
+    assert_eq!(output.tests[1].name, "doubles a label");
+    assert_eq!(output.tests[1].registers, [(Gpr::R0, 0x3005)]);
+}
+
+#[test]
+fn test_directives_errors() {
+    assert_eq!(
+        Lc2AssemblerBuilder::new()
+            .build()
+            .assemble(".orig x3000\n.expect r0, 1\n.end"),
+        Err(ParseError::TestDirectiveOutsideTest)
+    );
+    assert_eq!(
+        Lc2AssemblerBuilder::new()
+            .build()
+            .assemble(".orig x3000\n.test t\n.setreg 5\n.end"),
+        Err(ParseError::UnexpectedToken)
+    );
+    assert_eq!(
+        Lc2AssemblerBuilder::new()
+            .build()
+            .assemble(".orig x3000\n.test t\n.expect r0, nowhere\n.end"),
+        Err(ParseError::LabelNotDeclared)
+    );
+}
+
+/// Runs `PROGRAM` without decoding it: R1 is twice R0, and the first character
+/// of the input is written to the output
+#[derive(Default)]
+struct Doubler {
+    registers: [u16; 8],
+}
+
+impl Machine for Doubler {
+    type Error = &'static str;
+
+    fn load(&mut self, origin: u16, words: &[u16], entry_point: u16) {
+        assert_eq!(origin, 0x3000);
+        assert_eq!(words, [0x1200, 0xf020, 0xf021, 0xf025, 0]);
+        assert_eq!(entry_point, 0x3000);
+    }
+
+    fn set_register(&mut self, register: Gpr, value: u16) {
+        self.registers[usize::from(u8::from(register) & 0b111)] = value;
+    }
+
+    fn register(&self, register: Gpr) -> u16 {
+        self.registers[usize::from(u8::from(register) & 0b111)]
+    }
+
+    fn run(&mut self, input: &str) -> Result<String, Self::Error> {
+        self.registers[1] = self.registers[0].wrapping_add(self.registers[0]);
+        input
+            .chars()
+            .next()
+            .map(String::from)
+            .ok_or("The program read from the console, but there is no more input")
+    }
+}
+
+#[test]
+fn run_tests() {
+    let reports = Lc2AssemblerBuilder::new()
+        .build()
+        .run_tests(PROGRAM, Doubler::default)
+        .unwrap();
+
+    assert!(reports[0].passed());
+    assert!(!reports[1].passed());
+    assert_eq!(
+        reports[1]
+            .failures
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+        [
+            "Line 17: R1 is x600A, but x6001 was expected",
+            "Line 18: The output is \"b\", but \"x\" was expected",
+        ]
+    );
+
+    // An error stops the test case
+    let program = PROGRAM.replace(".input \"a\"", "");
+    let reports = Lc2AssemblerBuilder::new()
+        .build()
+        .run_tests(&program, Doubler::default)
+        .unwrap();
+    assert_eq!(
+        reports[0].failures,
+        [TestFailure {
+            message: "The program read from the console, but there is no more input".to_owned(),
+            line: 8,
+            span: 90..95,
+        }]
+    );
+}
@@ -1,6 +1,9 @@
+pub mod include;
 pub mod lc2;
+pub mod lint;
 
-use std::collections::HashMap;
+use lint::Lint;
+use std::{collections::HashMap, ops::Range};
 
 #[allow(clippy::type_complexity)]
 pub trait Assembler {
@@ -15,7 +18,7 @@ pub trait Assembler {
     ) -> Result<(Vec<u8>, HashMap<String, Self::Address>), ParseError>;
 }
 
-#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[derive(thiserror::Error, Debug, PartialEq, Eq, Clone)]
 pub enum ParseError {
     #[error("Token is not valid")]
     NonValidToken,
@@ -40,10 +43,53 @@ pub enum ParseError {
 
     #[error("The binary size exceeds the 65536 word limit")]
     BinaryTooBig,
+    #[error("The alignment must be greater than zero")]
+    InvalidAlignment,
+    #[error("The block must contain at least one word")]
+    EmptyBlock,
+    #[error("The block doesn't fit into the memory")]
+    BlockTooBig,
+    #[error("There can be only one entry point")]
+    TooManyEntryPoints,
     #[error("The number literal is too big")]
     NumberLiteralTooBig,
     #[error("The number literal must be positive")]
     NumberLiteralIsNegative,
+    #[error("The character literal must contain exactly one character that fits into a word")]
+    InvalidCharacterLiteral,
+    #[error("The escape sequence is not valid")]
+    InvalidEscapeSequence,
+
+    #[error("The encoding is not known")]
+    UnknownEncoding,
+    #[error("The byte packing is not known")]
+    UnknownBytePacking,
+    #[error("The character can't be represented in the chosen encoding")]
+    CharacterNotEncodable,
+    #[error("The chosen encoding can't be used for packed strings")]
+    EncodingNotPackable,
+
+    #[error("Including files is not enabled")]
+    IncludeNotEnabled,
+    #[error("The included file can't be read")]
+    IncludeNotFound,
+    #[error("The offset or the length exceed the size of the included file")]
+    IncludeOutOfRange,
+
+    #[error("The test directives must follow a \".test\" directive")]
+    TestDirectiveOutsideTest,
+
+    #[error("The assertion failed{}", .0.as_ref().map_or_else(String::new, |message| format!(": {message}")))]
+    AssertionFailed(Option<String>),
+    #[error("{0}")]
+    UserError(String),
+
+    #[error("The lint is not known")]
+    UnknownLint,
+    #[error("The lint level is not known")]
+    UnknownLintLevel,
+    #[error("{0} (denied by \"{}\")", .0.name())]
+    DeniedLint(Lint),
 
     #[error("The label was declarated more than once")]
     LabelRedeclaration,
@@ -51,4 +97,39 @@ pub enum ParseError {
     LabelNotDeclared,
     #[error("The label was found but is not on the same memory page")]
     LabelNotOnSamePage,
+    #[error("The local label was used before any global label")]
+    LocalLabelWithoutScope,
+    #[error("Operations, registers and pseudo-operations can't be used as label names")]
+    ReservedLabelName,
+
+    #[error("The line ended before all the operands were given")]
+    MissingOperand,
+    #[error("There are unexpected tokens at the end of the line")]
+    TrailingTokens,
+}
+
+/// A `ParseError` alongside the position in the assembly where it occurred
+#[derive(thiserror::Error, Debug, PartialEq, Eq, Clone)]
+#[error("Line {line}: {error}")]
+pub struct Diagnostic {
+    pub error: ParseError,
+    /// The byte range of the offending token
+    pub span: Range<usize>,
+    /// The line of the offending token, starting from 1
+    pub line: usize,
+}
+
+impl Diagnostic {
+    #[must_use]
+    pub fn new(assembly: &str, error: ParseError, span: Range<usize>) -> Self {
+        let line = line(assembly, span.start);
+
+        Self { error, span, line }
+    }
+}
+
+/// The line of a byte offset of the assembly, starting from 1
+pub(crate) fn line(assembly: &str, offset: usize) -> usize {
+    // Count the newlines before the offset
+    assembly[..offset].matches('\n').count() + 1
 }
@@ -0,0 +1,155 @@
+use std::{fmt::Display, ops::Range};
+
+use crate::ParseError;
+
+/// A check on a valid assembly that is probably a mistake
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    /// A label that is declared but never referenced
+    UnusedLabel,
+    /// An operation after an unconditional jump, a return or a `HALT`, without
+    /// a label that could make it reachable
+    UnreachableCode,
+    /// Data right after an operation that doesn't jump away, so the execution
+    /// falls through into it
+    DataFallthrough,
+    /// A branch that never jumps or that jumps to the next instruction
+    NopBranch,
+    /// A register added to its own negation, that always gives zero
+    SelfComparison,
+    /// A negative number literal that doesn't fit into 16 bits, that wraps
+    /// around to a positive number
+    WrappedNegation,
+    /// A register read before being written
+    UninitializedRegister,
+    /// An operation that overwrites R7 inside of a subroutine, without saving
+    /// and restoring it before the return
+    ClobberedReturnAddress,
+    /// An operation that overwrites a callee-saved register inside of a
+    /// subroutine, without saving and restoring it before the return
+    CalleeSavedRegister,
+    /// A `.warning` directive, reported with its message
+    UserWarning,
+}
+
+impl Lint {
+    pub const ALL: [Self; 10] = [
+        Self::UnusedLabel,
+        Self::UnreachableCode,
+        Self::DataFallthrough,
+        Self::NopBranch,
+        Self::SelfComparison,
+        Self::WrappedNegation,
+        Self::UninitializedRegister,
+        Self::ClobberedReturnAddress,
+        Self::CalleeSavedRegister,
+        Self::UserWarning,
+    ];
+
+    /// The name of the lint, like `unused_label`
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::UnusedLabel => "unused_label",
+            Self::UnreachableCode => "unreachable_code",
+            Self::DataFallthrough => "data_fallthrough",
+            Self::NopBranch => "nop_branch",
+            Self::SelfComparison => "self_comparison",
+            Self::WrappedNegation => "wrapped_negation",
+            Self::UninitializedRegister => "uninitialized_register",
+            Self::ClobberedReturnAddress => "clobbered_return_address",
+            Self::CalleeSavedRegister => "callee_saved_register",
+            Self::UserWarning => "user_warning",
+        }
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::UnusedLabel => "The label is never referenced",
+            Self::UnreachableCode => "The operation can't be reached",
+            Self::DataFallthrough => "The execution falls through into the data",
+            Self::NopBranch => "The branch doesn't change the execution flow",
+            Self::SelfComparison => "The register is compared against itself",
+            Self::WrappedNegation => "The negative number wrapped around",
+            Self::UninitializedRegister => "The register is read before being written",
+            Self::ClobberedReturnAddress => {
+                "The return address in R7 is overwritten before the return"
+            }
+            Self::CalleeSavedRegister => "The callee-saved register is not restored",
+            Self::UserWarning => "The assembly raised a warning",
+        })
+    }
+}
+
+impl TryFrom<&str> for Lint {
+    type Error = ParseError;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|lint| lint.name().eq_ignore_ascii_case(&name.replace('-', "_")))
+            .ok_or(ParseError::UnknownLint)
+    }
+}
+
+/// What to do when a lint is triggered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LintLevel {
+    /// Ignore it
+    Allow,
+    /// Report it alongside the binary
+    #[default]
+    Warn,
+    /// Fail the assembly
+    Deny,
+}
+
+impl TryFrom<&str> for LintLevel {
+    type Error = ParseError;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        Ok(match name.to_uppercase().as_str() {
+            "ALLOW" => Self::Allow,
+            "WARN" => Self::Warn,
+            "DENY" => Self::Deny,
+
+            _ => return Err(ParseError::UnknownLintLevel),
+        })
+    }
+}
+
+/// A triggered lint, with the location of the statement that triggered it
+#[derive(thiserror::Error, Debug, PartialEq, Eq, Clone)]
+#[error("Line {line}: {} ({})", .message.as_ref().map_or_else(|| lint.to_string(), Clone::clone), lint.name())]
+pub struct Warning {
+    pub lint: Lint,
+    /// The message of a `.warning` directive, that replaces the description
+    /// of the lint
+    pub message: Option<String>,
+    /// The byte range of the offending token
+    pub span: Range<usize>,
+    /// The line of the offending token, starting from 1
+    pub line: usize,
+}
+
+impl Warning {
+    #[must_use]
+    pub fn new(assembly: &str, lint: Lint, span: Range<usize>) -> Self {
+        Self {
+            lint,
+            message: None,
+            line: crate::line(assembly, span.start),
+            span,
+        }
+    }
+
+    /// The message of the warning, or the description of its lint
+    #[must_use]
+    pub fn message(&self) -> String {
+        self.message
+            .clone()
+            .unwrap_or_else(|| self.lint.to_string())
+    }
+}
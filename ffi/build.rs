@@ -0,0 +1,19 @@
+// Generate the C header from the exported functions and types. The header is
+// written into `OUT_DIR`, and the `header` test checks that the committed one in
+// `include/` is up to date
+fn main() {
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    let config =
+        cbindgen::Config::from_file("cbindgen.toml").expect("The cbindgen config is valid");
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src("src/lib.rs")
+        .generate()
+        .expect("The C API can be exported")
+        .write_to_file(
+            std::path::Path::new(&std::env::var("OUT_DIR").expect("Cargo sets OUT_DIR"))
+                .join("lc2_assembler.h"),
+        );
+}
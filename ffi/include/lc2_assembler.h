@@ -0,0 +1,216 @@
+#ifndef LC2_ASSEMBLER_H
+#define LC2_ASSEMBLER_H
+
+/* Generated by cbindgen from `src/lib.rs`, don't edit it by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// How the characters of the strings are converted into words
+typedef enum Lc2StringEncoding {
+  LC2_STRING_ENCODING_ASCII,
+  LC2_STRING_ENCODING_LATIN1,
+  LC2_STRING_ENCODING_UTF8,
+  LC2_STRING_ENCODING_UTF16,
+} Lc2StringEncoding;
+
+// How the bytes of `.INCBIN` are converted into words
+typedef enum Lc2BytePacking {
+  LC2_BYTE_PACKING_BIG_ENDIAN,
+  LC2_BYTE_PACKING_LITTLE_ENDIAN,
+  LC2_BYTE_PACKING_ONE_PER_WORD,
+} Lc2BytePacking;
+
+// What to do when a lint is triggered
+typedef enum Lc2LintLevel {
+  LC2_LINT_LEVEL_ALLOW,
+  LC2_LINT_LEVEL_WARN,
+  LC2_LINT_LEVEL_DENY,
+} Lc2LintLevel;
+
+typedef enum Lc2Severity {
+  LC2_SEVERITY_ERROR,
+  LC2_SEVERITY_WARNING,
+} Lc2Severity;
+
+// An LC2 assembler
+typedef struct Lc2Assembler Lc2Assembler;
+
+// The result of an assembly
+typedef struct Lc2Result Lc2Result;
+
+// The options of an assembler, with the same meaning of the ones of
+// `Lc2AssemblerBuilder`
+typedef struct Lc2Options {
+  bool optional_starting_orig;
+  bool multiple_origs;
+  bool optional_end;
+  bool nothing_after_end;
+  bool enable_stringzp;
+  bool prepend_start_address;
+  bool strict_lines;
+  bool single_pass;
+  enum Lc2StringEncoding string_encoding;
+  enum Lc2BytePacking incbin_packing;
+  uint16_t blkw_fill;
+  // The registers that the subroutines must save and restore, with the bit
+  // `n` set for `Rn`
+  uint8_t callee_saved_registers;
+} Lc2Options;
+
+// An error or a warning of an assembly
+typedef struct Lc2Diagnostic {
+  enum Lc2Severity severity;
+  const char *message;
+  // The name of the lint, or `NULL` for an error
+  const char *lint;
+  // The byte range of the offending token
+  size_t start;
+  size_t end;
+  // The line of the offending token, starting from 1
+  size_t line;
+} Lc2Diagnostic;
+
+// A label and its address
+typedef struct Lc2Symbol {
+  const char *name;
+  uint16_t address;
+} Lc2Symbol;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Return the options used by an assembler created without options
+struct Lc2Options lc2_options_default(void);
+
+// Create an assembler with the options, or with the default ones if
+// `options` is `NULL`
+//
+// # Safety
+//
+// `options` must be `NULL` or point to valid options
+struct Lc2Assembler *lc2_assembler_new(const struct Lc2Options *options);
+
+// Set the level of a lint, like `unused_label`. Return `false` if the lint
+// doesn't exist
+//
+// # Safety
+//
+// `assembler` must be a valid assembler and `lint` a null-terminated string
+bool lc2_assembler_set_lint(struct Lc2Assembler *assembler,
+                            const char *lint,
+                            enum Lc2LintLevel level);
+
+// Let `.INCBIN` include the files inside the directory `path`. Return `false`
+// if the path is not valid UTF-8
+//
+// # Safety
+//
+// `assembler` must be a valid assembler and `path` a null-terminated string
+bool lc2_assembler_set_include_dir(struct Lc2Assembler *assembler, const char *path);
+
+// Release an assembler. Nothing is done if `assembler` is `NULL`
+//
+// # Safety
+//
+// `assembler` must be `NULL` or an assembler not released yet
+void lc2_assembler_free(struct Lc2Assembler *assembler);
+
+// Assemble the `length` bytes of UTF-8 source pointed by `source`, that
+// don't need to be null-terminated. An error is returned in the diagnostics
+// of the result.
+//
+// A `NULL` source with a `length` of 0 is an empty source, while a `NULL`
+// source with any other `length` is an error
+//
+// # Safety
+//
+// `assembler` must be a valid assembler and `source` must be `NULL` or point
+// to at least `length` bytes
+struct Lc2Result *lc2_assemble(const struct Lc2Assembler *assembler,
+                               const char *source,
+                               size_t length);
+
+// Return `true` if the assembly was successful
+//
+// # Safety
+//
+// `result` must be a valid result
+bool lc2_result_ok(const struct Lc2Result *result);
+
+// Return the assembled binary and write its length into `length`. The binary
+// is empty if the assembly failed
+//
+// # Safety
+//
+// `result` must be a valid result and `length` must point to a `size_t`
+const uint8_t *lc2_result_binary(const struct Lc2Result *result, size_t *length);
+
+// Write the entry point set by `.entry` or `.end` into `address`, returning
+// `false` if there is none
+//
+// # Safety
+//
+// `result` must be a valid result and `address` must point to a `uint16_t`
+bool lc2_result_entry_point(const struct Lc2Result *result, uint16_t *address);
+
+// Return the number of diagnostics: the error that stopped the assembly or
+// the warnings
+//
+// # Safety
+//
+// `result` must be a valid result
+size_t lc2_result_diagnostic_count(const struct Lc2Result *result);
+
+// Write the diagnostic at `index` into `diagnostic`, returning `false` if the
+// index is out of bounds
+//
+// # Safety
+//
+// `result` must be a valid result and `diagnostic` must point to a
+// `Lc2Diagnostic`
+bool lc2_result_diagnostic(const struct Lc2Result *result,
+                           size_t index,
+                           struct Lc2Diagnostic *diagnostic);
+
+// Return the number of labels in the symbol table
+//
+// # Safety
+//
+// `result` must be a valid result
+size_t lc2_result_symbol_count(const struct Lc2Result *result);
+
+// Write the label at `index` into `symbol`, returning `false` if the index
+// is out of bounds. The labels are sorted by address
+//
+// # Safety
+//
+// `result` must be a valid result and `symbol` must point to a `Lc2Symbol`
+bool lc2_result_symbol(const struct Lc2Result *result, size_t index, struct Lc2Symbol *symbol);
+
+// Write the address of the label into `address`, returning `false` if the
+// label is not declared
+//
+// # Safety
+//
+// `result` must be a valid result, `name` a null-terminated string and
+// `address` must point to a `uint16_t`
+bool lc2_result_find_symbol(const struct Lc2Result *result, const char *name, uint16_t *address);
+
+// Release a result, with all of its strings and its binary. Nothing is done if
+// `result` is `NULL`
+//
+// # Safety
+//
+// `result` must be `NULL` or a result not released yet
+void lc2_result_free(struct Lc2Result *result);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* LC2_ASSEMBLER_H */
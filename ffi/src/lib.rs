@@ -0,0 +1,616 @@
+//! A C API of the LC2 assembler, declared in `include/lc2_assembler.h`.
+//!
+//! The assemblers and the results are owned by the caller, that must release
+//! them with `lc2_assembler_free()` and `lc2_result_free()`. The strings and
+//! the buffers returned by a result are borrowed from it, so they are valid
+//! until the result is released.
+
+use architectures::lc2::Gpr;
+use assemblers::{
+    include::FileSystemResolver,
+    lc2::{BytePacking, Lc2AssemblerBuilder, Lc2Output, StringEncoding},
+    lint::{Lint, LintLevel},
+    Diagnostic,
+};
+use std::{
+    ffi::{c_char, CStr, CString},
+    ops::Range,
+    ptr, slice,
+    sync::Arc,
+};
+
+/// How the characters of the strings are converted into words
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lc2StringEncoding {
+    Ascii,
+    Latin1,
+    Utf8,
+    Utf16,
+}
+
+impl From<StringEncoding> for Lc2StringEncoding {
+    fn from(encoding: StringEncoding) -> Self {
+        match encoding {
+            StringEncoding::Ascii => Self::Ascii,
+            StringEncoding::Latin1 => Self::Latin1,
+            StringEncoding::Utf8 => Self::Utf8,
+            StringEncoding::Utf16 => Self::Utf16,
+        }
+    }
+}
+
+impl From<Lc2StringEncoding> for StringEncoding {
+    fn from(encoding: Lc2StringEncoding) -> Self {
+        match encoding {
+            Lc2StringEncoding::Ascii => Self::Ascii,
+            Lc2StringEncoding::Latin1 => Self::Latin1,
+            Lc2StringEncoding::Utf8 => Self::Utf8,
+            Lc2StringEncoding::Utf16 => Self::Utf16,
+        }
+    }
+}
+
+/// How the bytes of `.INCBIN` are converted into words
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lc2BytePacking {
+    BigEndian,
+    LittleEndian,
+    OnePerWord,
+}
+
+impl From<BytePacking> for Lc2BytePacking {
+    fn from(packing: BytePacking) -> Self {
+        match packing {
+            BytePacking::BigEndian => Self::BigEndian,
+            BytePacking::LittleEndian => Self::LittleEndian,
+            BytePacking::OnePerWord => Self::OnePerWord,
+        }
+    }
+}
+
+impl From<Lc2BytePacking> for BytePacking {
+    fn from(packing: Lc2BytePacking) -> Self {
+        match packing {
+            Lc2BytePacking::BigEndian => Self::BigEndian,
+            Lc2BytePacking::LittleEndian => Self::LittleEndian,
+            Lc2BytePacking::OnePerWord => Self::OnePerWord,
+        }
+    }
+}
+
+/// What to do when a lint is triggered
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lc2LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lc2Severity {
+    Error,
+    Warning,
+}
+
+/// The options of an assembler, with the same meaning of the ones of
+/// `Lc2AssemblerBuilder`
+#[repr(C)]
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Copy, Debug)]
+pub struct Lc2Options {
+    pub optional_starting_orig: bool,
+    pub multiple_origs: bool,
+    pub optional_end: bool,
+    pub nothing_after_end: bool,
+    pub enable_stringzp: bool,
+    pub prepend_start_address: bool,
+    pub strict_lines: bool,
+    pub single_pass: bool,
+    pub string_encoding: Lc2StringEncoding,
+    pub incbin_packing: Lc2BytePacking,
+    pub blkw_fill: u16,
+    /// The registers that the subroutines must save and restore, with the bit
+    /// `n` set for `Rn`
+    pub callee_saved_registers: u8,
+}
+
+/// An error or a warning of an assembly
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct Lc2Diagnostic {
+    pub severity: Lc2Severity,
+    pub message: *const c_char,
+    /// The name of the lint, or `NULL` for an error
+    pub lint: *const c_char,
+    /// The byte range of the offending token
+    pub start: usize,
+    pub end: usize,
+    /// The line of the offending token, starting from 1
+    pub line: usize,
+}
+
+/// A label and its address
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct Lc2Symbol {
+    pub name: *const c_char,
+    pub address: u16,
+}
+
+/// An LC2 assembler
+pub struct Lc2Assembler {
+    builder: Lc2AssemblerBuilder,
+}
+
+/// The result of an assembly
+pub struct Lc2Result {
+    binary: Vec<u8>,
+    entry_point: Option<u16>,
+    symbols: Vec<(CString, u16)>,
+    diagnostics: Vec<OwnedDiagnostic>,
+}
+
+/// A diagnostic that owns its strings
+struct OwnedDiagnostic {
+    severity: Lc2Severity,
+    message: CString,
+    lint: Option<CString>,
+    start: usize,
+    end: usize,
+    line: usize,
+}
+
+impl Lc2Result {
+    fn new(output: Result<Lc2Output, Diagnostic>) -> Self {
+        let output = match output {
+            Ok(output) => output,
+            Err(diagnostic) => {
+                return Self::error(
+                    diagnostic.error.to_string(),
+                    diagnostic.span,
+                    diagnostic.line,
+                )
+            }
+        };
+
+        // Sort the symbols by address, like in a listing
+        let mut symbols: Vec<_> = output.symbol_table.into_iter().collect();
+        symbols.sort_by(|(a, a_address), (b, b_address)| (a_address, a).cmp(&(b_address, b)));
+
+        Self {
+            binary: output.binary,
+            entry_point: output.entry_point,
+            symbols: symbols
+                .into_iter()
+                .map(|(name, address)| (c_string(name), address))
+                .collect(),
+            diagnostics: output
+                .warnings
+                .into_iter()
+                .map(|warning| OwnedDiagnostic {
+                    severity: Lc2Severity::Warning,
+                    message: c_string(warning.message()),
+                    lint: Some(c_string(warning.lint.name())),
+                    start: warning.span.start,
+                    end: warning.span.end,
+                    line: warning.line,
+                })
+                .collect(),
+        }
+    }
+
+    /// A failed assembly, with a single error
+    fn error(message: impl Into<Vec<u8>>, span: Range<usize>, line: usize) -> Self {
+        Self {
+            binary: Vec::new(),
+            entry_point: None,
+            symbols: Vec::new(),
+            diagnostics: vec![OwnedDiagnostic {
+                severity: Lc2Severity::Error,
+                message: c_string(message),
+                lint: None,
+                start: span.start,
+                end: span.end,
+                line,
+            }],
+        }
+    }
+}
+
+/// Convert a string into a C string, cutting it at the first null character
+fn c_string(string: impl Into<Vec<u8>>) -> CString {
+    let mut bytes = string.into();
+    if let Some(null) = bytes.iter().position(|&byte| byte == 0) {
+        bytes.truncate(null);
+    }
+
+    CString::new(bytes).unwrap_or_default()
+}
+
+/// Return the options used by an assembler created without options
+#[must_use]
+#[no_mangle]
+pub extern "C" fn lc2_options_default() -> Lc2Options {
+    let defaults = Lc2AssemblerBuilder::new().build();
+
+    Lc2Options {
+        optional_starting_orig: defaults.optional_starting_orig(),
+        multiple_origs: defaults.multiple_origs(),
+        optional_end: defaults.optional_end(),
+        nothing_after_end: defaults.nothing_after_end(),
+        enable_stringzp: defaults.enable_stringzp(),
+        prepend_start_address: defaults.prepend_start_address(),
+        strict_lines: defaults.strict_lines(),
+        single_pass: defaults.single_pass(),
+        string_encoding: defaults.string_encoding().into(),
+        incbin_packing: defaults.incbin_packing().into(),
+        blkw_fill: defaults.blkw_fill(),
+        callee_saved_registers: defaults
+            .callee_saved_registers()
+            .fold(0, |mask, register| mask | 1 << (u8::from(register) & 0b111)),
+    }
+}
+
+/// Create an assembler with the options, or with the default ones if
+/// `options` is `NULL`
+///
+/// # Safety
+///
+/// `options` must be `NULL` or point to valid options
+#[must_use]
+#[no_mangle]
+pub unsafe extern "C" fn lc2_assembler_new(options: *const Lc2Options) -> *mut Lc2Assembler {
+    // SAFETY: the caller guarantees that the pointer is null or valid
+    let options = unsafe { options.as_ref() }
+        .copied()
+        .unwrap_or_else(|| lc2_options_default());
+
+    let mut builder = Lc2AssemblerBuilder::new();
+    builder
+        .optional_starting_orig(options.optional_starting_orig)
+        .multiple_origs(options.multiple_origs)
+        .optional_end(options.optional_end)
+        .nothing_after_end(options.nothing_after_end)
+        .enable_stringzp(options.enable_stringzp)
+        .prepend_start_address(options.prepend_start_address)
+        .strict_lines(options.strict_lines)
+        .single_pass(options.single_pass)
+        .string_encoding(options.string_encoding.into())
+        .incbin_packing(options.incbin_packing.into())
+        .blkw_fill(options.blkw_fill)
+        .callee_saved_registers(
+            (0..8_usize)
+                .filter(|register| options.callee_saved_registers & (1 << register) != 0)
+                .filter_map(|register| Gpr::try_from(register).ok()),
+        );
+
+    Box::into_raw(Box::new(Lc2Assembler { builder }))
+}
+
+/// Set the level of a lint, like `unused_label`. Return `false` if the lint
+/// doesn't exist
+///
+/// # Safety
+///
+/// `assembler` must be a valid assembler and `lint` a null-terminated string
+#[must_use]
+#[no_mangle]
+pub unsafe extern "C" fn lc2_assembler_set_lint(
+    assembler: *mut Lc2Assembler,
+    lint: *const c_char,
+    level: Lc2LintLevel,
+) -> bool {
+    // SAFETY: the caller guarantees that the pointers are valid
+    let (assembler, lint) = unsafe { (&mut *assembler, CStr::from_ptr(lint)) };
+    let Some(lint) = lint
+        .to_str()
+        .ok()
+        .and_then(|lint| Lint::try_from(lint).ok())
+    else {
+        return false;
+    };
+
+    assembler.builder.lint(
+        lint,
+        match level {
+            Lc2LintLevel::Allow => LintLevel::Allow,
+            Lc2LintLevel::Warn => LintLevel::Warn,
+            Lc2LintLevel::Deny => LintLevel::Deny,
+        },
+    );
+    true
+}
+
+/// Let `.INCBIN` include the files inside the directory `path`. Return `false`
+/// if the path is not valid UTF-8
+///
+/// # Safety
+///
+/// `assembler` must be a valid assembler and `path` a null-terminated string
+#[must_use]
+#[no_mangle]
+pub unsafe extern "C" fn lc2_assembler_set_include_dir(
+    assembler: *mut Lc2Assembler,
+    path: *const c_char,
+) -> bool {
+    // SAFETY: the caller guarantees that the pointers are valid
+    let (assembler, path) = unsafe { (&mut *assembler, CStr::from_ptr(path)) };
+    let Ok(path) = path.to_str() else {
+        return false;
+    };
+
+    assembler
+        .builder
+        .include_resolver(Arc::new(FileSystemResolver::new(path)));
+    true
+}
+
+/// Release an assembler. Nothing is done if `assembler` is `NULL`
+///
+/// # Safety
+///
+/// `assembler` must be `NULL` or an assembler not released yet
+#[no_mangle]
+pub unsafe extern "C" fn lc2_assembler_free(assembler: *mut Lc2Assembler) {
+    if !assembler.is_null() {
+        // SAFETY: the assembler was created by `lc2_assembler_new()`
+        drop(unsafe { Box::from_raw(assembler) });
+    }
+}
+
+/// Assemble the `length` bytes of UTF-8 source pointed by `source`, that
+/// don't need to be null-terminated. An error is returned in the diagnostics
+/// of the result.
+///
+/// A `NULL` source with a `length` of 0 is an empty source, while a `NULL`
+/// source with any other `length` is an error
+///
+/// # Safety
+///
+/// `assembler` must be a valid assembler and `source` must be `NULL` or point
+/// to at least `length` bytes
+#[must_use]
+#[no_mangle]
+pub unsafe extern "C" fn lc2_assemble(
+    assembler: *const Lc2Assembler,
+    source: *const c_char,
+    length: usize,
+) -> *mut Lc2Result {
+    // SAFETY: the caller guarantees that the pointer is valid
+    let assembler = unsafe { &*assembler };
+
+    if source.is_null() {
+        let result = if length == 0 {
+            Lc2Result::new(
+                assembler
+                    .builder
+                    .clone()
+                    .build()
+                    .assemble_with_diagnostic(""),
+            )
+        } else {
+            Lc2Result::error("The source is NULL", 0..0, 1)
+        };
+        return Box::into_raw(Box::new(result));
+    }
+
+    // SAFETY: the caller guarantees that the source points to `length` bytes
+    let source = unsafe { slice::from_raw_parts(source.cast::<u8>(), length) };
+
+    let result = match std::str::from_utf8(source) {
+        Ok(source) => Lc2Result::new(
+            assembler
+                .builder
+                .clone()
+                .build()
+                .assemble_with_diagnostic(source),
+        ),
+
+        // Point to the first byte that is not valid UTF-8
+        Err(error) => {
+            let start = error.valid_up_to();
+            let line = source[..start].split(|&byte| byte == b'\n').count();
+            let end = start + error.error_len().unwrap_or(length - start);
+
+            Lc2Result::error("The source is not valid UTF-8", start..end, line)
+        }
+    };
+
+    Box::into_raw(Box::new(result))
+}
+
+/// Return `true` if the assembly was successful
+///
+/// # Safety
+///
+/// `result` must be a valid result
+#[must_use]
+#[no_mangle]
+pub unsafe extern "C" fn lc2_result_ok(result: *const Lc2Result) -> bool {
+    // SAFETY: the caller guarantees that the pointer is valid
+    let result = unsafe { &*result };
+
+    result
+        .diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic.severity != Lc2Severity::Error)
+}
+
+/// Return the assembled binary and write its length into `length`. The binary
+/// is empty if the assembly failed
+///
+/// # Safety
+///
+/// `result` must be a valid result and `length` must point to a `size_t`
+#[must_use]
+#[no_mangle]
+pub unsafe extern "C" fn lc2_result_binary(
+    result: *const Lc2Result,
+    length: *mut usize,
+) -> *const u8 {
+    // SAFETY: the caller guarantees that the pointers are valid
+    let result = unsafe {
+        *length = (*result).binary.len();
+        &*result
+    };
+
+    result.binary.as_ptr()
+}
+
+/// Write the entry point set by `.entry` or `.end` into `address`, returning
+/// `false` if there is none
+///
+/// # Safety
+///
+/// `result` must be a valid result and `address` must point to a `uint16_t`
+#[must_use]
+#[no_mangle]
+pub unsafe extern "C" fn lc2_result_entry_point(
+    result: *const Lc2Result,
+    address: *mut u16,
+) -> bool {
+    // SAFETY: the caller guarantees that the pointer is valid
+    let Some(entry_point) = (unsafe { &*result }).entry_point else {
+        return false;
+    };
+
+    // SAFETY: the caller guarantees that the pointer is valid
+    unsafe { *address = entry_point };
+    true
+}
+
+/// Return the number of diagnostics: the error that stopped the assembly or
+/// the warnings
+///
+/// # Safety
+///
+/// `result` must be a valid result
+#[must_use]
+#[no_mangle]
+pub const unsafe extern "C" fn lc2_result_diagnostic_count(result: *const Lc2Result) -> usize {
+    // SAFETY: the caller guarantees that the pointer is valid
+    unsafe { &*result }.diagnostics.len()
+}
+
+/// Write the diagnostic at `index` into `diagnostic`, returning `false` if the
+/// index is out of bounds
+///
+/// # Safety
+///
+/// `result` must be a valid result and `diagnostic` must point to a
+/// `Lc2Diagnostic`
+#[must_use]
+#[no_mangle]
+pub unsafe extern "C" fn lc2_result_diagnostic(
+    result: *const Lc2Result,
+    index: usize,
+    diagnostic: *mut Lc2Diagnostic,
+) -> bool {
+    // SAFETY: the caller guarantees that the pointer is valid
+    let Some(message) = (unsafe { &*result }).diagnostics.get(index) else {
+        return false;
+    };
+
+    // SAFETY: the caller guarantees that the pointer is valid
+    unsafe {
+        *diagnostic = Lc2Diagnostic {
+            severity: message.severity,
+            message: message.message.as_ptr(),
+            lint: message
+                .lint
+                .as_ref()
+                .map_or(ptr::null(), |lint| lint.as_ptr()),
+            start: message.start,
+            end: message.end,
+            line: message.line,
+        };
+    }
+    true
+}
+
+/// Return the number of labels in the symbol table
+///
+/// # Safety
+///
+/// `result` must be a valid result
+#[must_use]
+#[no_mangle]
+pub const unsafe extern "C" fn lc2_result_symbol_count(result: *const Lc2Result) -> usize {
+    // SAFETY: the caller guarantees that the pointer is valid
+    unsafe { &*result }.symbols.len()
+}
+
+/// Write the label at `index` into `symbol`, returning `false` if the index
+/// is out of bounds. The labels are sorted by address
+///
+/// # Safety
+///
+/// `result` must be a valid result and `symbol` must point to a `Lc2Symbol`
+#[must_use]
+#[no_mangle]
+pub unsafe extern "C" fn lc2_result_symbol(
+    result: *const Lc2Result,
+    index: usize,
+    symbol: *mut Lc2Symbol,
+) -> bool {
+    // SAFETY: the caller guarantees that the pointer is valid
+    let Some((name, address)) = (unsafe { &*result }).symbols.get(index) else {
+        return false;
+    };
+
+    // SAFETY: the caller guarantees that the pointer is valid
+    unsafe {
+        *symbol = Lc2Symbol {
+            name: name.as_ptr(),
+            address: *address,
+        };
+    }
+    true
+}
+
+/// Write the address of the label into `address`, returning `false` if the
+/// label is not declared
+///
+/// # Safety
+///
+/// `result` must be a valid result, `name` a null-terminated string and
+/// `address` must point to a `uint16_t`
+#[must_use]
+#[no_mangle]
+pub unsafe extern "C" fn lc2_result_find_symbol(
+    result: *const Lc2Result,
+    name: *const c_char,
+    address: *mut u16,
+) -> bool {
+    // SAFETY: the caller guarantees that the pointers are valid
+    let (result, name) = unsafe { (&*result, CStr::from_ptr(name)) };
+    let Some((_, found)) = result
+        .symbols
+        .iter()
+        .find(|(symbol, _)| symbol.as_c_str() == name)
+    else {
+        return false;
+    };
+
+    // SAFETY: the caller guarantees that the pointer is valid
+    unsafe { *address = *found };
+    true
+}
+
+/// Release a result, with all of its strings and its binary. Nothing is done if
+/// `result` is `NULL`
+///
+/// # Safety
+///
+/// `result` must be `NULL` or a result not released yet
+#[no_mangle]
+pub unsafe extern "C" fn lc2_result_free(result: *mut Lc2Result) {
+    if !result.is_null() {
+        // SAFETY: the result was created by `lc2_assemble()`
+        drop(unsafe { Box::from_raw(result) });
+    }
+}
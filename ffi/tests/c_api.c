@@ -0,0 +1,152 @@
+/* Exercise the C API like an embedding program would */
+
+#include <assert.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "lc2_assembler.h"
+
+static const char PROGRAM[] =
+    ".orig x3000\n"
+    "main  lea r0, string\n"
+    "      puts\n"
+    "      halt\n"
+    "string .stringz \"Hi\"\n"
+    ".end\n";
+
+static void test_assemble(void) {
+    Lc2Assembler *assembler = lc2_assembler_new(NULL);
+    Lc2Result *result = lc2_assemble(assembler, PROGRAM, strlen(PROGRAM));
+    assert(lc2_result_ok(result));
+
+    size_t length;
+    const uint8_t *binary = lc2_result_binary(result, &length);
+    const uint8_t expected[] = {0x30, 0x00, 0xE0, 0x03, 0xF0, 0x22, 0xF0,
+                                0x25, 0x00, 0x48, 0x00, 0x69, 0x00, 0x00};
+    assert(length == sizeof(expected));
+    assert(memcmp(binary, expected, length) == 0);
+
+    uint16_t address;
+    assert(!lc2_result_entry_point(result, &address));
+
+    /* The symbols are sorted by address */
+    Lc2Symbol symbol;
+    assert(lc2_result_symbol_count(result) == 2);
+    assert(lc2_result_symbol(result, 0, &symbol));
+    assert(strcmp(symbol.name, "main") == 0 && symbol.address == 0x3000);
+    assert(lc2_result_symbol(result, 1, &symbol));
+    assert(strcmp(symbol.name, "string") == 0 && symbol.address == 0x3003);
+    assert(!lc2_result_symbol(result, 2, &symbol));
+
+    assert(lc2_result_find_symbol(result, "string", &address));
+    assert(address == 0x3003);
+    assert(!lc2_result_find_symbol(result, "nowhere", &address));
+
+    /* `main` is never referenced */
+    Lc2Diagnostic diagnostic;
+    assert(lc2_result_diagnostic_count(result) == 1);
+    assert(lc2_result_diagnostic(result, 0, &diagnostic));
+    assert(diagnostic.severity == LC2_SEVERITY_WARNING);
+    assert(strcmp(diagnostic.lint, "unused_label") == 0);
+    assert(diagnostic.start == 12 && diagnostic.end == 16 && diagnostic.line == 2);
+    assert(!lc2_result_diagnostic(result, 1, &diagnostic));
+
+    lc2_result_free(result);
+    lc2_assembler_free(assembler);
+}
+
+static void test_errors(void) {
+    Lc2Options options = lc2_options_default();
+    options.prepend_start_address = false;
+    options.optional_end = true;
+    Lc2Assembler *assembler = lc2_assembler_new(&options);
+    assert(lc2_assembler_set_lint(assembler, "unused_label", LC2_LINT_LEVEL_DENY));
+    assert(!lc2_assembler_set_lint(assembler, "unknown", LC2_LINT_LEVEL_DENY));
+
+    /* The source doesn't need to be null-terminated */
+    const char source[] = ".orig x3000\nld r0, nowhere\nhalt";
+    Lc2Result *result = lc2_assemble(assembler, source, 17);
+
+    size_t length;
+    lc2_result_binary(result, &length);
+    assert(!lc2_result_ok(result));
+    assert(length == 0);
+
+    Lc2Diagnostic diagnostic;
+    assert(lc2_result_diagnostic(result, 0, &diagnostic));
+    assert(diagnostic.severity == LC2_SEVERITY_ERROR);
+    assert(diagnostic.lint == NULL);
+    assert(strcmp(diagnostic.message, "I was expecting more tokens") == 0);
+    lc2_result_free(result);
+
+    result = lc2_assemble(assembler, source, strlen(source));
+    assert(lc2_result_diagnostic(result, 0, &diagnostic));
+    assert(strcmp(diagnostic.message, "The label was used but not declared") == 0);
+    assert(diagnostic.start == 19 && diagnostic.end == 26 && diagnostic.line == 2);
+    lc2_result_free(result);
+
+    /* The options are applied */
+    result = lc2_assemble(assembler, ".orig x3000\nhalt", 16);
+    const uint8_t *binary = lc2_result_binary(result, &length);
+    assert(length == 2 && binary[0] == 0xF0 && binary[1] == 0x25);
+    lc2_result_free(result);
+
+    /* An invalid UTF-8 byte is an error */
+    result = lc2_assemble(assembler, ".orig x3000\n\xff", 13);
+    assert(lc2_result_diagnostic(result, 0, &diagnostic));
+    assert(diagnostic.start == 12 && diagnostic.end == 13 && diagnostic.line == 2);
+    lc2_result_free(result);
+
+    /* A NULL source is empty only if its length is 0 */
+    result = lc2_assemble(assembler, NULL, 0);
+    assert(lc2_result_diagnostic(result, 0, &diagnostic));
+    assert(strcmp(diagnostic.message, "The source is NULL") != 0);
+    lc2_result_free(result);
+
+    result = lc2_assemble(assembler, NULL, 4);
+    assert(!lc2_result_ok(result));
+    assert(lc2_result_diagnostic(result, 0, &diagnostic));
+    assert(strcmp(diagnostic.message, "The source is NULL") == 0);
+    lc2_result_free(result);
+
+    lc2_assembler_free(assembler);
+    lc2_assembler_free(NULL);
+    lc2_result_free(NULL);
+}
+
+static void test_options(void) {
+    /* The defaults are the ones of the builder */
+    Lc2Options options = lc2_options_default();
+    assert(options.nothing_after_end && options.prepend_start_address);
+    assert(!options.single_pass);
+    assert(options.string_encoding == LC2_STRING_ENCODING_UTF8);
+    assert(options.incbin_packing == LC2_BYTE_PACKING_BIG_ENDIAN);
+    assert(options.callee_saved_registers == 0);
+
+    /* The test runs in the directory of the crate */
+    options.prepend_start_address = false;
+    options.single_pass = true;
+    options.incbin_packing = LC2_BYTE_PACKING_ONE_PER_WORD;
+    Lc2Assembler *assembler = lc2_assembler_new(&options);
+    assert(lc2_assembler_set_include_dir(assembler, "tests"));
+
+    const char source[] = ".orig x3000\n.incbin \"c_api.c\", 0, 2\n.end";
+    Lc2Result *result = lc2_assemble(assembler, source, strlen(source));
+    size_t length;
+    const uint8_t *binary = lc2_result_binary(result, &length);
+    const uint8_t expected[] = {0x00, '/', 0x00, '*'};
+    assert(length == sizeof(expected));
+    assert(memcmp(binary, expected, length) == 0);
+    lc2_result_free(result);
+
+    lc2_assembler_free(assembler);
+}
+
+int main(void) {
+    test_assemble();
+    test_errors();
+    test_options();
+
+    puts("ok");
+    return 0;
+}
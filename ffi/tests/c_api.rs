@@ -0,0 +1,52 @@
+use std::{path::Path, process::Command};
+
+/// Compile `c_api.c` against the library and the generated header, then run it
+#[test]
+fn c_program() {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let executable = Path::new(env!("CARGO_TARGET_TMPDIR")).join("c_api");
+
+    // The test executable is in `target/<profile>/deps`, while the library is
+    // in `target/<profile>`
+    let executable_dir = std::env::current_exe().unwrap();
+    let library_dir = executable_dir.parent().unwrap().parent().unwrap();
+
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".to_owned());
+    let status = Command::new(compiler)
+        .arg(crate_dir.join("tests/c_api.c"))
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(crate_dir.join("include"))
+        .arg("-L")
+        .arg(library_dir)
+        .arg(format!("-Wl,-rpath,{}", library_dir.display()))
+        .arg("-llc2_assembler")
+        .arg("-o")
+        .arg(&executable)
+        .status()
+        .expect("A C compiler is installed");
+    assert!(status.success());
+
+    let output = Command::new(&executable)
+        .current_dir(crate_dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(output.stdout, b"ok\n");
+}
+
+/// The committed header must be the one generated by the build script
+#[test]
+fn header() {
+    let generated = Path::new(env!("OUT_DIR")).join("lc2_assembler.h");
+    let committed = Path::new(env!("CARGO_MANIFEST_DIR")).join("include/lc2_assembler.h");
+
+    assert!(
+        std::fs::read_to_string(&generated).unwrap()
+            == std::fs::read_to_string(&committed).unwrap(),
+        "The header is outdated, copy {} into {}",
+        generated.display(),
+        committed.display()
+    );
+}
@@ -0,0 +1,206 @@
+use assemblers::{
+    lc2::{
+        ast::{self, LabelDeclaration, LabelReference, LabelScope, Program, Statement, Visitor},
+        incremental::{IncrementalAssembler, TextEdit},
+        Lc2AssemblerBuilder, Lc2Output,
+    },
+    Diagnostic,
+};
+use std::ops::Range;
+
+/// An open assembly, analyzed every time it changes. The assembly is updated
+/// incrementally, lexing again only the edited lines
+pub struct Document {
+    assembly: IncrementalAssembler,
+    /// The byte offset of the start of every line
+    line_starts: Vec<usize>,
+    program: Option<Program>,
+    labels: Vec<LabelDefinition>,
+    references: Vec<(String, Range<usize>)>,
+}
+
+/// A declared label, with its qualified name (like the ones in the symbol
+/// table)
+pub struct LabelDefinition {
+    pub name: String,
+    pub qualified: String,
+    pub span: Range<usize>,
+    /// `true` if the label is declared before an operation instead of some data
+    pub code: bool,
+    pub global: bool,
+}
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        let mut document = Self {
+            assembly: IncrementalAssembler::new(Lc2AssemblerBuilder::new().build(), text),
+            line_starts: Vec::new(),
+            program: None,
+            labels: Vec::new(),
+            references: Vec::new(),
+        };
+        document.analyze();
+
+        document
+    }
+
+    /// Replace the byte range of the text, or the whole text if there is no
+    /// range
+    pub fn edit(&mut self, range: Option<Range<usize>>, text: String) {
+        let range = range.unwrap_or_else(|| 0..self.text().len());
+        self.assembly.edit([TextEdit { range, text }]);
+        self.analyze();
+    }
+
+    /// Update the lines and the labels of the text
+    fn analyze(&mut self) {
+        let text = self.assembly.source();
+        self.line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        self.program = ast::parse(text).ok();
+
+        let mut qualifier = Qualifier::default();
+        if let Some(program) = &self.program {
+            program.accept(&mut qualifier);
+        }
+        self.labels = qualifier.labels;
+        self.references = qualifier.references;
+    }
+
+    pub fn text(&self) -> &str {
+        self.assembly.source()
+    }
+
+    /// The result of the assembly, with the errors and the warnings
+    pub const fn output(&self) -> &Result<Lc2Output, Diagnostic> {
+        self.assembly.output()
+    }
+
+    pub const fn program(&self) -> Option<&Program> {
+        self.program.as_ref()
+    }
+
+    pub fn labels(&self) -> &[LabelDefinition] {
+        &self.labels
+    }
+
+    /// Convert a byte offset into a line and a UTF-16 column
+    pub fn position(&self, offset: usize) -> (u32, u32) {
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let column = self.text()[self.line_starts[line]..offset]
+            .encode_utf16()
+            .count();
+
+        (
+            u32::try_from(line).unwrap_or(u32::MAX),
+            u32::try_from(column).unwrap_or(u32::MAX),
+        )
+    }
+
+    /// Convert a line and a UTF-16 column into a byte offset, clamping it to
+    /// the end of the line
+    pub fn offset(&self, line: u32, column: u32) -> usize {
+        let Some(&start) = usize::try_from(line)
+            .ok()
+            .and_then(|line| self.line_starts.get(line))
+        else {
+            return self.text().len();
+        };
+
+        let mut units = 0;
+        for (index, character) in self.text()[start..].char_indices() {
+            if character == '\n' || units >= column as usize {
+                return start + index;
+            }
+            units += character.len_utf16();
+        }
+
+        self.text().len()
+    }
+
+    /// Return the qualified name of the label declared or referenced at the
+    /// offset
+    pub fn label_at(&self, offset: usize) -> Option<&str> {
+        let contains = |span: &Range<usize>| span.start <= offset && offset <= span.end;
+
+        self.labels
+            .iter()
+            .find(|label| contains(&label.span))
+            .map(|label| label.qualified.as_str())
+            .or_else(|| {
+                self.references
+                    .iter()
+                    .find(|(_, span)| contains(span))
+                    .map(|(name, _)| name.as_str())
+            })
+    }
+
+    /// Return the declaration of the label
+    pub fn definition(&self, qualified: &str) -> Option<&LabelDefinition> {
+        self.labels
+            .iter()
+            .find(|label| label.qualified == qualified)
+    }
+
+    /// Return the spans of every reference to the label
+    pub fn references<'a>(
+        &'a self,
+        qualified: &'a str,
+    ) -> impl Iterator<Item = &'a Range<usize>> + 'a {
+        self.references
+            .iter()
+            .filter(move |(name, _)| name == qualified)
+            .map(|(_, span)| span)
+    }
+
+    /// Return the address of the label, if the assembly was successful
+    pub fn address(&self, qualified: &str) -> Option<u16> {
+        self.output()
+            .as_ref()
+            .ok()
+            .and_then(|output| output.symbol_table.get(qualified).copied())
+    }
+}
+
+/// Gives the labels the same qualified names used by the assembler, visiting
+/// them in the order of the program
+#[derive(Default)]
+struct Qualifier {
+    scope: LabelScope,
+    labels: Vec<LabelDefinition>,
+    references: Vec<(String, Range<usize>)>,
+}
+
+impl Visitor for Qualifier {
+    fn visit_line(&mut self, line: &ast::Line) {
+        if let Some(label) = &line.label {
+            let name = match &label.kind {
+                LabelDeclaration::Global(name) | LabelDeclaration::Numeric(name) => name.clone(),
+                LabelDeclaration::Local(name) => format!(".{name}"),
+                LabelDeclaration::Anonymous { forward } => {
+                    (if *forward { "+" } else { "-" }).to_owned()
+                }
+            };
+
+            // A local label without a global one can't be qualified
+            if let Ok(qualified) = self.scope.declaration(&label.kind) {
+                self.labels.push(LabelDefinition {
+                    name,
+                    qualified,
+                    span: label.span.clone(),
+                    code: matches!(line.statement, Some(Statement::Instruction(_))),
+                    global: matches!(label.kind, LabelDeclaration::Global(_)),
+                });
+            }
+        }
+
+        ast::walk_line(self, line);
+    }
+
+    fn visit_label_reference(&mut self, label: &LabelReference, span: &Range<usize>) {
+        if let Ok(qualified) = self.scope.reference(label) {
+            self.references.push((qualified, span.clone()));
+        }
+    }
+}
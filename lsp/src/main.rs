@@ -0,0 +1,47 @@
+//! A Language Server Protocol server for the LC2 assembly, that talks with the
+//! editor over the standard input and output
+
+#[cfg(test)]
+mod tests;
+
+mod document;
+mod server;
+
+use lsp_server::{Connection, Message};
+use server::Server;
+use std::error::Error;
+
+fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (connection, io_threads) = Connection::stdio();
+
+    // Tell the editor what the server can do
+    let capabilities = serde_json::to_value(server::capabilities())?;
+    connection.initialize(capabilities)?;
+
+    // Answer every message until the editor asks to shut down
+    let mut server = Server::default();
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    break;
+                }
+
+                connection
+                    .sender
+                    .send(Message::Response(server.request(request)))?;
+            }
+            Message::Notification(notification) => {
+                if let Some(notification) = server.notification(notification) {
+                    connection
+                        .sender
+                        .send(Message::Notification(notification))?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+
+    io_threads.join()?;
+    Ok(())
+}
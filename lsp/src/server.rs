@@ -0,0 +1,466 @@
+use crate::document::Document;
+use assemblers::lc2::{
+    ast::{Operation, PseudoOperation, Statement},
+    formatter::Lc2FormatterBuilder,
+    highlight::{classify, TokenKind},
+};
+use lsp_server::{ErrorCode, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+        PublishDiagnostics,
+    },
+    request::{
+        Completion, DocumentSymbolRequest, Formatting, GotoDefinition, HoverRequest, References,
+        Request as _, SemanticTokensFullRequest,
+    },
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentFormattingParams, DocumentSymbol, DocumentSymbolParams,
+    DocumentSymbolResponse, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents,
+    HoverParams, HoverProviderCapability, Location, MarkupContent, MarkupKind, NumberOrString,
+    OneOf, Position, PublishDiagnosticsParams, Range, ReferenceParams, SemanticToken,
+    SemanticTokenModifier, SemanticTokenType, SemanticTokens, SemanticTokensFullOptions,
+    SemanticTokensLegend, SemanticTokensOptions, SemanticTokensParams, SemanticTokensResult,
+    SemanticTokensServerCapabilities, ServerCapabilities, SymbolKind, TextDocumentPositionParams,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Uri,
+};
+use std::collections::HashMap;
+
+/// The semantic token types, in the order of `token_type`
+const TOKEN_TYPES: [SemanticTokenType; 7] = [
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::MACRO,
+    SemanticTokenType::FUNCTION,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::STRING,
+    SemanticTokenType::COMMENT,
+];
+
+/// The index of the semantic token type of a token class. The label
+/// definitions have the `declaration` modifier too
+const fn token_type(kind: TokenKind) -> u32 {
+    match kind {
+        TokenKind::Mnemonic => 0,
+        TokenKind::Register => 1,
+        TokenKind::Directive => 2,
+        TokenKind::LabelDefinition | TokenKind::LabelReference => 3,
+        TokenKind::Number => 4,
+        TokenKind::String => 5,
+        TokenKind::Comment => 6,
+    }
+}
+
+/// The features supported by the server
+pub fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(
+            TextDocumentSyncKind::INCREMENTAL,
+        )),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+            SemanticTokensOptions {
+                legend: SemanticTokensLegend {
+                    token_types: TOKEN_TYPES.to_vec(),
+                    token_modifiers: vec![SemanticTokenModifier::DECLARATION],
+                },
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..SemanticTokensOptions::default()
+            },
+        )),
+        ..ServerCapabilities::default()
+    }
+}
+
+/// Keeps the open documents and answers the requests about them
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<Uri, Document>,
+}
+
+impl Server {
+    /// Answer a request, returning an error for the unknown methods
+    pub fn request(&self, request: Request) -> Response {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            GotoDefinition::METHOD => self.handle::<GotoDefinition>(request, Self::definition),
+            References::METHOD => self.handle::<References>(request, Self::references),
+            HoverRequest::METHOD => self.handle::<HoverRequest>(request, Self::hover),
+            Completion::METHOD => self.handle::<Completion>(request, Self::completion),
+            DocumentSymbolRequest::METHOD => {
+                self.handle::<DocumentSymbolRequest>(request, Self::symbols)
+            }
+            Formatting::METHOD => self.handle::<Formatting>(request, Self::format),
+            SemanticTokensFullRequest::METHOD => {
+                self.handle::<SemanticTokensFullRequest>(request, Self::semantic_tokens)
+            }
+            method => {
+                return Response::new_err(
+                    id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("The method \"{method}\" is not supported"),
+                )
+            }
+        };
+
+        match result {
+            Ok(result) => Response {
+                id,
+                result: Some(result),
+                error: None,
+            },
+            Err(error) => Response::new_err(id, ErrorCode::InvalidParams as i32, error),
+        }
+    }
+
+    /// Keep the documents up to date, returning the notification with their
+    /// new diagnostics
+    pub fn notification(&mut self, notification: Notification) -> Option<Notification> {
+        let uri = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params).ok()?;
+                let uri = params.text_document.uri;
+                self.documents
+                    .insert(uri.clone(), Document::new(params.text_document.text));
+                uri
+            }
+
+            // The changes are applied in order, each one to the text left by
+            // the previous ones
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params).ok()?;
+                let uri = params.text_document.uri;
+                let document = self.documents.get_mut(&uri)?;
+                for change in params.content_changes {
+                    let range = change.range.map(|range| {
+                        document.offset(range.start.line, range.start.character)
+                            ..document.offset(range.end.line, range.end.character)
+                    });
+                    document.edit(range, change.text);
+                }
+                uri
+            }
+
+            // Clear the diagnostics of a closed document
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params).ok()?;
+                self.documents.remove(&params.text_document.uri);
+                return Some(publish(params.text_document.uri, Vec::new()));
+            }
+
+            _ => return None,
+        };
+
+        let diagnostics = self.diagnostics(&uri);
+        Some(publish(uri, diagnostics))
+    }
+
+    /// Deserialize the parameters of the request and serialize its result
+    fn handle<R: lsp_types::request::Request>(
+        &self,
+        request: Request,
+        handler: fn(&Self, &R::Params) -> R::Result,
+    ) -> Result<serde_json::Value, String> {
+        let params = serde_json::from_value(request.params).map_err(|error| error.to_string())?;
+        serde_json::to_value(handler(self, &params)).map_err(|error| error.to_string())
+    }
+
+    /// Return the document and the byte offset of the position
+    fn document_at(&self, position: &TextDocumentPositionParams) -> Option<(&Document, usize)> {
+        let document = self.documents.get(&position.text_document.uri)?;
+        let offset = document.offset(position.position.line, position.position.character);
+
+        Some((document, offset))
+    }
+
+    /// The error of the assembly or its warnings
+    fn diagnostics(&self, uri: &Uri) -> Vec<Diagnostic> {
+        let Some(document) = self.documents.get(uri) else {
+            return Vec::new();
+        };
+
+        match document.output() {
+            Err(error) => vec![Diagnostic {
+                range: range(document, &error.span),
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("lc2".to_owned()),
+                message: error.error.to_string(),
+                ..Diagnostic::default()
+            }],
+            Ok(output) => output
+                .warnings
+                .iter()
+                .map(|warning| Diagnostic {
+                    range: range(document, &warning.span),
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    code: Some(NumberOrString::String(warning.lint.name().to_owned())),
+                    source: Some("lc2".to_owned()),
+                    message: warning.message(),
+                    ..Diagnostic::default()
+                })
+                .collect(),
+        }
+    }
+
+    fn definition(&self, params: &GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let position = &params.text_document_position_params;
+        let (document, offset) = self.document_at(position)?;
+        let label = document.definition(document.label_at(offset)?)?;
+
+        Some(GotoDefinitionResponse::Scalar(Location {
+            uri: position.text_document.uri.clone(),
+            range: range(document, &label.span),
+        }))
+    }
+
+    fn references(&self, params: &ReferenceParams) -> Option<Vec<Location>> {
+        let position = &params.text_document_position;
+        let (document, offset) = self.document_at(position)?;
+        let name = document.label_at(offset)?;
+
+        let declaration = document
+            .definition(name)
+            .filter(|_| params.context.include_declaration)
+            .map(|label| &label.span);
+
+        Some(
+            declaration
+                .into_iter()
+                .chain(document.references(name))
+                .map(|span| Location {
+                    uri: position.text_document.uri.clone(),
+                    range: range(document, span),
+                })
+                .collect(),
+        )
+    }
+
+    /// Show the address and the page of a label, or the encoding of an
+    /// operation
+    fn hover(&self, params: &HoverParams) -> Option<Hover> {
+        let (document, offset) = self.document_at(&params.text_document_position_params)?;
+
+        let value = if let Some(name) = document.label_at(offset) {
+            let address = document.address(name)?;
+            let page = address >> 9;
+
+            format!(
+                "`{name}`: address `x{address:04X}`, page `x{page:02X}` (`x{:04X}`-`x{:04X}`)",
+                page << 9,
+                (page << 9) | 0x1ff
+            )
+        } else {
+            let operation = document
+                .output()
+                .as_ref()
+                .ok()?
+                .operations
+                .iter()
+                .find(|operation| operation.span.start <= offset && offset <= operation.span.end)?;
+
+            // Show the whole statement, as printed by the syntax tree
+            let statement = document
+                .program()
+                .into_iter()
+                .flat_map(|program| &program.lines)
+                .filter_map(|line| line.statement.as_ref())
+                .find(|statement| match statement {
+                    Statement::Instruction(instruction) => {
+                        instruction.span.start == operation.span.start
+                    }
+                    Statement::Directive(_) => false,
+                })
+                .map_or_else(String::new, ToString::to_string);
+
+            format!(
+                "```lc2\n{statement}\n```\nAddress `x{:04X}`, encoded as `x{:04X}` (`{:016b}`)",
+                operation.address, operation.word, operation.word
+            )
+        };
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: None,
+        })
+    }
+
+    /// Offer every mnemonic, register, pseudo-operation and named label. The
+    /// client filters them by what was typed
+    fn completion(&self, params: &CompletionParams) -> Option<CompletionResponse> {
+        let (document, _) = self.document_at(&params.text_document_position)?;
+
+        let item = |label: String, kind| CompletionItem {
+            label,
+            kind: Some(kind),
+            ..CompletionItem::default()
+        };
+        let mnemonics = Operation::MNEMONICS
+            .iter()
+            .map(|(mnemonic, _)| item((*mnemonic).to_owned(), CompletionItemKind::KEYWORD));
+        let directives = PseudoOperation::MNEMONICS
+            .iter()
+            .map(|(directive, _)| item((*directive).to_owned(), CompletionItemKind::KEYWORD));
+        let registers =
+            (0..8).map(|register| item(format!("R{register}"), CompletionItemKind::VARIABLE));
+        let labels = document
+            .labels()
+            .iter()
+            .filter(|label| !label.qualified.contains('@'))
+            .map(|label| {
+                let kind = if label.code {
+                    CompletionItemKind::FUNCTION
+                } else {
+                    CompletionItemKind::CONSTANT
+                };
+                CompletionItem {
+                    detail: document
+                        .address(&label.qualified)
+                        .map(|address| format!("x{address:04X}")),
+                    ..item(label.name.clone(), kind)
+                }
+            });
+
+        Some(CompletionResponse::Array(
+            mnemonics
+                .chain(directives)
+                .chain(registers)
+                .chain(labels)
+                .collect(),
+        ))
+    }
+
+    /// List the named labels, with the local ones inside of their global label
+    fn symbols(&self, params: &DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let document = self.documents.get(&params.text_document.uri)?;
+        let mut symbols: Vec<DocumentSymbol> = Vec::new();
+
+        for label in document.labels() {
+            if label.qualified.contains('@') {
+                continue;
+            }
+
+            #[allow(deprecated)]
+            let symbol = DocumentSymbol {
+                name: label.name.clone(),
+                detail: document
+                    .address(&label.qualified)
+                    .map(|address| format!("x{address:04X}")),
+                kind: if label.code {
+                    SymbolKind::FUNCTION
+                } else {
+                    SymbolKind::CONSTANT
+                },
+                tags: None,
+                deprecated: None,
+                range: range(document, &label.span),
+                selection_range: range(document, &label.span),
+                children: None,
+            };
+
+            match symbols.last_mut() {
+                Some(parent) if !label.global => {
+                    parent.children.get_or_insert_with(Vec::new).push(symbol);
+                }
+                _ => symbols.push(symbol),
+            }
+        }
+
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+
+    /// Replace the whole document with its formatted version
+    fn format(&self, params: &DocumentFormattingParams) -> Option<Vec<TextEdit>> {
+        let document = self.documents.get(&params.text_document.uri)?;
+        let formatted = Lc2FormatterBuilder::new()
+            .build()
+            .format(document.text())
+            .ok()?;
+
+        if formatted == document.text() {
+            return Some(Vec::new());
+        }
+
+        Some(vec![TextEdit {
+            range: range(document, &(0..document.text().len())),
+            new_text: formatted,
+        }])
+    }
+
+    /// Highlight the tokens like the lexer classifies them
+    fn semantic_tokens(&self, params: &SemanticTokensParams) -> Option<SemanticTokensResult> {
+        let document = self.documents.get(&params.text_document.uri)?;
+
+        // Every token is relative to the previous one
+        let mut previous = (0, 0);
+        let data = classify(document.text())
+            .into_iter()
+            .map(|token| {
+                let (line, start) = document.position(token.span.start);
+
+                // A token can't span many lines, so a string with a newline is
+                // cut at the end of its first line
+                let length = document.text()[token.span]
+                    .lines()
+                    .next()
+                    .map_or(0, |text| text.encode_utf16().count());
+
+                let semantic_token = SemanticToken {
+                    delta_line: line - previous.0,
+                    delta_start: if line == previous.0 {
+                        start - previous.1
+                    } else {
+                        start
+                    },
+                    length: u32::try_from(length).unwrap_or(u32::MAX),
+                    token_type: token_type(token.kind),
+                    token_modifiers_bitset: u32::from(token.kind == TokenKind::LabelDefinition),
+                };
+                previous = (line, start);
+
+                semantic_token
+            })
+            .collect();
+
+        Some(SemanticTokensResult::Tokens(SemanticTokens {
+            result_id: None,
+            data,
+        }))
+    }
+}
+
+/// Convert a byte range into an LSP range
+fn range(document: &Document, span: &std::ops::Range<usize>) -> Range {
+    let position = |offset| {
+        let (line, character) = document.position(offset);
+        Position { line, character }
+    };
+
+    Range {
+        start: position(span.start),
+        end: position(span.end),
+    }
+}
+
+/// Create the notification with the diagnostics of a document
+fn publish(uri: Uri, diagnostics: Vec<Diagnostic>) -> Notification {
+    Notification::new(
+        PublishDiagnostics::METHOD.to_owned(),
+        PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        },
+    )
+}
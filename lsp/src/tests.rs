@@ -0,0 +1,254 @@
+use crate::server::Server;
+use lsp_server::{Notification, Request, RequestId, Response};
+use serde_json::{json, Value};
+
+const URI: &str = "file:///program.asm";
+
+const PROGRAM: &str = "\
+.orig x3000
+main  lea r0, string
+      puts
+.loop brnp .loop
+      halt
+string .stringz \"Hi!\"
+unused .fill 1
+.end
+";
+
+/// Create a server with the program open
+fn server(text: &str) -> (Server, Value) {
+    let mut server = Server::default();
+    let diagnostics = server
+        .notification(Notification::new(
+            "textDocument/didOpen".to_owned(),
+            json!({
+                "textDocument": { "uri": URI, "languageId": "lc2", "version": 1, "text": text }
+            }),
+        ))
+        .unwrap()
+        .params;
+
+    (server, diagnostics)
+}
+
+fn request(server: &Server, method: &str, params: &Value) -> Value {
+    let Response { result, error, .. } = server.request(Request::new(
+        RequestId::from(1),
+        method.to_owned(),
+        params.clone(),
+    ));
+    assert!(error.is_none(), "{error:?}");
+
+    result.unwrap()
+}
+
+fn position(line: u32, character: u32) -> Value {
+    json!({ "textDocument": { "uri": URI }, "position": { "line": line, "character": character } })
+}
+
+fn range(start: (u32, u32), end: (u32, u32)) -> Value {
+    json!({
+        "start": { "line": start.0, "character": start.1 },
+        "end": { "line": end.0, "character": end.1 }
+    })
+}
+
+#[test]
+fn diagnostics() {
+    let (_, diagnostics) = server(PROGRAM);
+    assert_eq!(
+        diagnostics["diagnostics"],
+        json!([(1, 4), (6, 6)].map(|(line, end)| json!({
+            "range": range((line, 0), (line, end)),
+            "severity": 2,
+            "code": "unused_label",
+            "source": "lc2",
+            "message": "The label is never referenced"
+        })))
+    );
+
+    let (_, diagnostics) = server(".orig x3000\nld r0, nowhere\n.end");
+    assert_eq!(
+        diagnostics["diagnostics"],
+        json!([{
+            "range": range((1, 7), (1, 14)),
+            "severity": 1,
+            "source": "lc2",
+            "message": "The label was used but not declared"
+        }])
+    );
+}
+
+#[test]
+fn definition_and_references() {
+    let (server, _) = server(PROGRAM);
+
+    assert_eq!(
+        request(&server, "textDocument/definition", &position(1, 16)),
+        json!({ "uri": URI, "range": range((5, 0), (5, 6)) })
+    );
+    assert_eq!(
+        request(&server, "textDocument/definition", &position(3, 12)),
+        json!({ "uri": URI, "range": range((3, 0), (3, 5)) })
+    );
+
+    let mut params = position(3, 0);
+    params["context"] = json!({ "includeDeclaration": true });
+    assert_eq!(
+        request(&server, "textDocument/references", &params),
+        json!([
+            { "uri": URI, "range": range((3, 0), (3, 5)) },
+            { "uri": URI, "range": range((3, 11), (3, 16)) }
+        ])
+    );
+
+    // Nothing is found outside of the labels
+    assert_eq!(
+        request(&server, "textDocument/definition", &position(2, 7)),
+        Value::Null
+    );
+}
+
+#[test]
+fn hover() {
+    let (server, _) = server(PROGRAM);
+
+    assert_eq!(
+        request(&server, "textDocument/hover", &position(5, 2))["contents"]["value"],
+        "`string`: address `x3004`, page `x18` (`x3000`-`x31FF`)"
+    );
+    assert_eq!(
+        request(&server, "textDocument/hover", &position(1, 7))["contents"]["value"],
+        "```lc2\nLEA R0, string\n```\nAddress `x3000`, encoded as `xE004` (`1110000000000100`)"
+    );
+}
+
+#[test]
+fn completion() {
+    let (server, _) = server(PROGRAM);
+    let items = request(&server, "textDocument/completion", &position(4, 0));
+    let labels: Vec<_> = items
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap())
+        .collect();
+
+    for label in [
+        "ADD", "BRnzp", "BRLT", "HALT", ".STRINGZ", ".ASCII", "R7", "main", ".loop", "string",
+    ] {
+        assert!(labels.contains(&label), "{label}");
+    }
+}
+
+#[test]
+fn symbols() {
+    let (server, _) = server(PROGRAM);
+    let symbols = request(
+        &server,
+        "textDocument/documentSymbol",
+        &json!({ "textDocument": { "uri": URI } }),
+    );
+
+    let names = |symbols: &Value| -> Vec<String> {
+        symbols
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|symbol| format!("{} {}", symbol["name"], symbol["kind"]))
+            .collect()
+    };
+    assert_eq!(
+        names(&symbols),
+        ["\"main\" 12", "\"string\" 14", "\"unused\" 14"]
+    );
+    assert_eq!(names(&symbols[0]["children"]), ["\".loop\" 12"]);
+}
+
+#[test]
+fn formatting() {
+    let (server, _) = server(".orig x3000\nhalt\n.end\n");
+    let params = json!({
+        "textDocument": { "uri": URI },
+        "options": { "tabSize": 4, "insertSpaces": true }
+    });
+
+    assert_eq!(
+        request(&server, "textDocument/formatting", &params),
+        json!([{
+            "range": range((0, 0), (3, 0)),
+            "newText": "        .ORIG   x3000\n        HALT\n        .END\n"
+        }])
+    );
+}
+
+#[test]
+fn semantic_tokens() {
+    let (server, _) = server(".orig x3000\nmain: halt ; è\n.end");
+
+    assert_eq!(
+        request(
+            &server,
+            "textDocument/semanticTokens/full",
+            &json!({ "textDocument": { "uri": URI } })
+        ),
+        json!({
+            "data": [
+                0, 0, 5, 2, 0, // .orig
+                0, 6, 5, 4, 0, // x3000
+                1, 0, 4, 3, 1, // main
+                0, 6, 4, 0, 0, // halt
+                0, 5, 3, 6, 0, // ; è
+                1, 0, 4, 2, 0, // .end
+            ]
+        })
+    );
+}
+
+#[test]
+fn positions() {
+    // The columns are counted in UTF-16 code units
+    let (server, diagnostics) = server(".orig x3000\n.stringz \"𝄞\" ; è\nld r0, nowhere\n.end");
+    assert_eq!(
+        diagnostics["diagnostics"][0]["range"],
+        range((2, 7), (2, 14))
+    );
+
+    let response = server.request(Request::new(
+        RequestId::from(2),
+        "unknown/method".to_owned(),
+        Value::Null,
+    ));
+    assert!(response.error.is_some());
+}
+
+#[test]
+fn changes() {
+    let (mut server, _) = server(".orig x3000\nld r0, nowhere\n.end");
+    let mut change = |changes: Value| {
+        server
+            .notification(Notification::new(
+                "textDocument/didChange".to_owned(),
+                json!({
+                    "textDocument": { "uri": URI, "version": 2 },
+                    "contentChanges": changes
+                }),
+            ))
+            .unwrap()
+            .params
+    };
+
+    // The ranges of the changes refer to the text left by the previous ones
+    let diagnostics = change(json!([
+        { "range": range((1, 7), (1, 14)), "text": "value" },
+        { "range": range((2, 0), (2, 0)), "text": "halt\nvalue .fill 1\n" }
+    ]));
+    assert_eq!(diagnostics["diagnostics"], json!([]));
+
+    // A change without a range replaces the whole text
+    let diagnostics = change(json!([{ "text": ".orig x3000\nld r0, nowhere\n.end" }]));
+    assert_eq!(
+        diagnostics["diagnostics"][0]["range"],
+        range((1, 7), (1, 14))
+    );
+}
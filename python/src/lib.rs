@@ -0,0 +1,395 @@
+//! Python bindings of the LC2 assembler, importable as `little_assembler`.
+//!
+//! The results are returned as read-only Python classes, and the spans are
+//! converted into character offsets so they can index the Python strings.
+
+#[cfg(test)]
+mod tests;
+
+use architectures::lc2::Gpr;
+use assemblers::{
+    include::FileSystemResolver,
+    lc2::{disassembler, BytePacking, Lc2AssemblerBuilder, Lc2Output, StringEncoding},
+    lint::{Lint, LintLevel, Warning},
+    Diagnostic as AssemblerDiagnostic,
+};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+use std::{collections::HashMap, ops::Range, sync::Arc};
+
+/// An LC2 assembler. The options are keyword arguments, and the omitted ones
+/// keep the defaults of `Lc2AssemblerBuilder`
+#[pyclass(module = "little_assembler", frozen)]
+pub struct Assembler {
+    builder: Lc2AssemblerBuilder,
+}
+
+#[pymethods]
+impl Assembler {
+    #[new]
+    #[pyo3(signature = (
+        *,
+        optional_starting_orig = None,
+        multiple_origs = None,
+        optional_end = None,
+        nothing_after_end = None,
+        enable_stringzp = None,
+        prepend_start_address = None,
+        strict_lines = None,
+        single_pass = None,
+        string_encoding = None,
+        include_dir = None,
+        incbin_packing = None,
+        blkw_fill = None,
+        lints = None,
+        callee_saved_registers = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        optional_starting_orig: Option<bool>,
+        multiple_origs: Option<bool>,
+        optional_end: Option<bool>,
+        nothing_after_end: Option<bool>,
+        enable_stringzp: Option<bool>,
+        prepend_start_address: Option<bool>,
+        strict_lines: Option<bool>,
+        single_pass: Option<bool>,
+        string_encoding: Option<&str>,
+        include_dir: Option<String>,
+        incbin_packing: Option<&str>,
+        blkw_fill: Option<u16>,
+        lints: Option<HashMap<String, String>>,
+        callee_saved_registers: Option<Vec<u8>>,
+    ) -> PyResult<Self> {
+        // The omitted options keep the defaults of the builder
+        let mut builder = Lc2AssemblerBuilder::new();
+        if let Some(value) = optional_starting_orig {
+            builder.optional_starting_orig(value);
+        }
+        if let Some(value) = multiple_origs {
+            builder.multiple_origs(value);
+        }
+        if let Some(value) = optional_end {
+            builder.optional_end(value);
+        }
+        if let Some(value) = nothing_after_end {
+            builder.nothing_after_end(value);
+        }
+        if let Some(value) = enable_stringzp {
+            builder.enable_stringzp(value);
+        }
+        if let Some(value) = prepend_start_address {
+            builder.prepend_start_address(value);
+        }
+        if let Some(value) = strict_lines {
+            builder.strict_lines(value);
+        }
+        if let Some(value) = single_pass {
+            builder.single_pass(value);
+        }
+        if let Some(value) = blkw_fill {
+            builder.blkw_fill(value);
+        }
+        if let Some(encoding) = string_encoding {
+            builder.string_encoding(
+                StringEncoding::try_from(encoding)
+                    .map_err(|error| PyValueError::new_err(error.to_string()))?,
+            );
+        }
+        if let Some(packing) = incbin_packing {
+            builder.incbin_packing(
+                BytePacking::try_from(packing)
+                    .map_err(|error| PyValueError::new_err(error.to_string()))?,
+            );
+        }
+
+        if let Some(include_dir) = include_dir {
+            builder.include_resolver(Arc::new(FileSystemResolver::new(include_dir)));
+        }
+
+        for (lint, level) in lints.unwrap_or_default() {
+            let lint = Lint::try_from(lint.as_str())
+                .map_err(|error| PyValueError::new_err(error.to_string()))?;
+            let level = LintLevel::try_from(level.as_str())
+                .map_err(|error| PyValueError::new_err(error.to_string()))?;
+            builder.lint(lint, level);
+        }
+
+        if let Some(registers) = callee_saved_registers {
+            let registers = registers
+                .into_iter()
+                .map(|register| {
+                    Gpr::try_from(usize::from(register)).ok().ok_or_else(|| {
+                        PyValueError::new_err(format!("R{register} is not a register"))
+                    })
+                })
+                .collect::<PyResult<Vec<_>>>()?;
+            builder.callee_saved_registers(registers);
+        }
+
+        Ok(Self { builder })
+    }
+
+    /// Assemble the source. An error doesn't raise an exception, but it's
+    /// returned in the `error` attribute of the result
+    fn assemble(&self, source: &str) -> AssemblyResult {
+        let output = self
+            .builder
+            .clone()
+            .build()
+            .assemble_with_diagnostic(source);
+
+        AssemblyResult::new(source, output)
+    }
+}
+
+/// The result of an assembly
+#[pyclass(module = "little_assembler", name = "Result", frozen)]
+pub struct AssemblyResult {
+    binary: Vec<u8>,
+    /// The labels with their addresses
+    #[pyo3(get)]
+    symbols: HashMap<String, u16>,
+    /// The address set by the `.entry` directive or by the operand of the
+    /// `.end` directive, if any
+    #[pyo3(get)]
+    entry_point: Option<u16>,
+    /// Every assembled operation, with its address, its encoding and its line
+    #[pyo3(get)]
+    operations: Vec<Operation>,
+    #[pyo3(get)]
+    warnings: Vec<Diagnostic>,
+    /// The error that stopped the assembly, if any
+    #[pyo3(get)]
+    error: Option<Diagnostic>,
+}
+
+impl AssemblyResult {
+    fn new(source: &str, output: Result<Lc2Output, AssemblerDiagnostic>) -> Self {
+        let output = match output {
+            Ok(output) => output,
+            Err(diagnostic) => {
+                return Self {
+                    binary: Vec::new(),
+                    symbols: HashMap::new(),
+                    entry_point: None,
+                    operations: Vec::new(),
+                    warnings: Vec::new(),
+                    error: Some(Diagnostic::error(source, &diagnostic)),
+                }
+            }
+        };
+
+        let lines: Vec<_> = source.lines().collect();
+        let operations = output
+            .operations
+            .iter()
+            .map(|operation| {
+                let line = source[..operation.span.start].matches('\n').count();
+                Operation {
+                    address: operation.address,
+                    word: operation.word,
+                    line: line + 1,
+                    source: lines.get(line).map_or("", |line| line.trim()).to_owned(),
+                }
+            })
+            .collect();
+
+        Self {
+            binary: output.binary,
+            symbols: output.symbol_table,
+            entry_point: output.entry_point,
+            operations,
+            warnings: output
+                .warnings
+                .iter()
+                .map(|warning| Diagnostic::warning(source, warning))
+                .collect(),
+            error: None,
+        }
+    }
+}
+
+#[pymethods]
+impl AssemblyResult {
+    /// `True` if the assembly was successful
+    #[getter]
+    const fn ok(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// The assembled binary, empty if the assembly failed
+    #[getter]
+    fn binary<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.binary)
+    }
+
+    /// The assembled binary as a list of big-endian words
+    #[getter]
+    fn words(&self) -> Vec<u16> {
+        self.binary
+            .chunks(2)
+            .map(|word| u16::from_be_bytes([word[0], word.get(1).copied().unwrap_or(0)]))
+            .collect()
+    }
+
+    fn __repr__(&self) -> String {
+        self.error.as_ref().map_or_else(
+            || {
+                format!(
+                    "<Result words={} warnings={}>",
+                    self.binary.len() / 2,
+                    self.warnings.len()
+                )
+            },
+            |error| format!("<Result error={:?}>", error.message),
+        )
+    }
+}
+
+/// An assembled operation
+#[pyclass(module = "little_assembler", frozen, get_all, skip_from_py_object)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Operation {
+    address: u16,
+    word: u16,
+    /// The line of the operation, starting from 1
+    line: usize,
+    /// The source of the line, without the indentation
+    source: String,
+}
+
+#[pymethods]
+impl Operation {
+    fn __repr__(&self) -> String {
+        format!(
+            "<Operation x{:04X}: x{:04X} {:?}>",
+            self.address, self.word, self.source
+        )
+    }
+}
+
+/// An error or a warning, located by the character offsets of the offending
+/// token
+#[pyclass(module = "little_assembler", frozen, get_all, skip_from_py_object)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    message: String,
+    /// The name of the lint, only for the warnings
+    lint: Option<String>,
+    start: usize,
+    end: usize,
+    /// The line of the offending token, starting from 1
+    line: usize,
+}
+
+impl Diagnostic {
+    fn error(source: &str, diagnostic: &AssemblerDiagnostic) -> Self {
+        let (start, end) = char_span(source, &diagnostic.span);
+
+        Self {
+            message: diagnostic.error.to_string(),
+            lint: None,
+            start,
+            end,
+            line: diagnostic.line,
+        }
+    }
+
+    fn warning(source: &str, warning: &Warning) -> Self {
+        let (start, end) = char_span(source, &warning.span);
+
+        Self {
+            message: warning.message(),
+            lint: Some(warning.lint.name().to_owned()),
+            start,
+            end,
+            line: warning.line,
+        }
+    }
+}
+
+#[pymethods]
+impl Diagnostic {
+    fn __repr__(&self) -> String {
+        format!("<Diagnostic line {}: {:?}>", self.line, self.message)
+    }
+
+    fn __str__(&self) -> String {
+        format!("Line {}: {}", self.line, self.message)
+    }
+}
+
+/// A disassembled word
+#[pyclass(module = "little_assembler", frozen, get_all, skip_from_py_object)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisassembledWord {
+    address: u16,
+    word: u16,
+    /// The label declared at the address, if any
+    label: Option<String>,
+    /// The operation or the `.FILL` directive
+    instruction: String,
+    /// The address referenced by a `pgoffset9` operand, if any
+    target: Option<u16>,
+}
+
+#[pymethods]
+impl DisassembledWord {
+    fn __repr__(&self) -> String {
+        format!(
+            "<DisassembledWord x{:04X}: {:?}>",
+            self.address, self.instruction
+        )
+    }
+}
+
+/// Disassemble the words loaded at the origin. Only the words reachable from
+/// the entry point are decoded as operations, the other ones become `.FILL`
+/// directives
+#[pyfunction]
+#[pyo3(signature = (words, origin = 0x3000, symbols = None, entry_point = None))]
+#[allow(clippy::needless_pass_by_value)]
+fn disassemble(
+    words: Vec<u16>,
+    origin: u16,
+    symbols: Option<HashMap<String, u16>>,
+    entry_point: Option<u16>,
+) -> Vec<DisassembledWord> {
+    disassembler::disassemble(&words, origin, &symbols.unwrap_or_default(), entry_point)
+        .into_iter()
+        .map(|word| DisassembledWord {
+            address: word.address,
+            word: word.word,
+            label: word.label,
+            instruction: word.instruction,
+            target: word.target,
+        })
+        .collect()
+}
+
+/// Disassemble a single operation, returning `None` if the word is not a
+/// valid operation
+#[pyfunction]
+#[pyo3(signature = (word, address = 0x3000))]
+fn disassemble_word(word: u16, address: u16) -> Option<String> {
+    disassembler::disassemble_word(word, address)
+}
+
+#[pymodule]
+fn little_assembler(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<Assembler>()?;
+    module.add_class::<AssemblyResult>()?;
+    module.add_class::<Operation>()?;
+    module.add_class::<Diagnostic>()?;
+    module.add_class::<DisassembledWord>()?;
+    module.add_function(wrap_pyfunction!(disassemble, module)?)?;
+    module.add_function(wrap_pyfunction!(disassemble_word, module)?)?;
+    Ok(())
+}
+
+/// Convert a byte range into character offsets
+fn char_span(source: &str, span: &Range<usize>) -> (usize, usize) {
+    let offset = |offset: usize| source[..offset].chars().count();
+
+    (offset(span.start), offset(span.end))
+}
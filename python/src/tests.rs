@@ -0,0 +1,98 @@
+use pyo3::{prelude::*, types::PyDict};
+
+/// Run a Python script with the `little_assembler` module imported
+fn run(script: &std::ffi::CStr) {
+    Python::initialize();
+    Python::attach(|py| {
+        let module = PyModule::new(py, "little_assembler").unwrap();
+        crate::little_assembler(&module).unwrap();
+
+        let globals = PyDict::new(py);
+        globals.set_item("little_assembler", module).unwrap();
+        py.run(script, Some(&globals), None)
+            .unwrap_or_else(|error| panic!("{error}"));
+    });
+}
+
+#[test]
+fn assemble() {
+    run(cr#"
+result = little_assembler.Assembler().assemble(""".orig x3000
+main  lea r0, string
+      puts
+      halt
+string .stringz "Hi"
+.end
+""")
+
+assert result.ok and result.error is None
+assert result.binary == bytes.fromhex("3000E003F022F025004800690000")
+assert result.words[:3] == [0x3000, 0xE003, 0xF022]
+assert result.symbols == {"main": 0x3000, "string": 0x3003}
+assert result.entry_point is None
+assert [(o.address, o.word, o.line, o.source) for o in result.operations] == [
+    (0x3000, 0xE003, 2, "main  lea r0, string"),
+    (0x3001, 0xF022, 3, "puts"),
+    (0x3002, 0xF025, 4, "halt"),
+]
+
+[warning] = result.warnings
+assert (warning.lint, warning.line, warning.start, warning.end) == ("unused_label", 2, 12, 16)
+"#);
+}
+
+#[test]
+fn errors() {
+    run(cr#"
+# The offsets are counted in characters
+result = little_assembler.Assembler().assemble('.orig x3000\n.stringz "𝄞"\nld r0, nowhere\n.end')
+assert not result.ok and result.binary == b"" and result.symbols == {}
+assert result.error.message == "The label was used but not declared"
+assert (result.error.start, result.error.end, result.error.line) == (32, 39, 3)
+
+assembler = little_assembler.Assembler(
+    optional_end=True,
+    prepend_start_address=False,
+    lints={"unused-label": "deny"},
+    callee_saved_registers=[4, 5],
+)
+assert assembler.assemble(".orig x3000\nhalt").binary == b"\xf0\x25"
+assert "denied" in assembler.assemble(".orig x3000\nmain halt").error.message
+
+# The omitted options keep the defaults of the builder
+assembler = little_assembler.Assembler(single_pass=True, incbin_packing="little-endian")
+assert not assembler.assemble(".orig x3000\nhalt").ok
+
+for options in [
+    {"string_encoding": "ebcdic"},
+    {"incbin_packing": "middle_endian"},
+    {"lints": {"unknown": "deny"}},
+    {"lints": {"unused_label": "forbid"}},
+    {"callee_saved_registers": [8]},
+]:
+    try:
+        little_assembler.Assembler(**options)
+    except ValueError:
+        pass
+    else:
+        raise AssertionError(options)
+"#);
+}
+
+#[test]
+fn disassemble() {
+    run(cr#"
+result = little_assembler.Assembler(prepend_start_address=False).assemble(
+    ".orig x3000\nmain ld r0, value\nhalt\nvalue .fill 5\n.end main"
+)
+words = little_assembler.disassemble(result.words, 0x3000, result.symbols, result.entry_point)
+assert [(w.label, w.instruction, w.target) for w in words] == [
+    ("main", "LD R0, value", 0x3002),
+    (None, "HALT", None),
+    ("value", ".FILL x0005", None),
+]
+
+assert little_assembler.disassemble_word(0x1042) == "ADD R0, R1, R2"
+assert little_assembler.disassemble_word(0xD001) is None
+"#);
+}
@@ -0,0 +1,45 @@
+import little_assembler
+import pytest
+
+PROGRAM = """\
+.orig x3000
+main    lea r0, string
+        puts
+        halt
+string  .stringz "Hi"
+.end
+"""
+
+
+def test_assemble():
+    result = little_assembler.Assembler().assemble(PROGRAM)
+
+    assert result.ok
+    assert result.words == [0x3000, 0xE003, 0xF022, 0xF025, 0x0048, 0x0069, 0x0000]
+    assert result.symbols["string"] == 0x3003
+    assert [operation.word for operation in result.operations] == [0xE003, 0xF022, 0xF025]
+    assert [warning.lint for warning in result.warnings] == ["unused_label"]
+
+
+def test_error():
+    result = little_assembler.Assembler().assemble(".orig x3000\nld r0, nowhere\n.end")
+
+    assert not result.ok
+    assert result.binary == b""
+    assert str(result.error) == "Line 2: The label was used but not declared"
+
+
+def test_options():
+    assembler = little_assembler.Assembler(lints={"unused_label": "allow"}, optional_end=True)
+    assert assembler.assemble(".orig x3000\nmain halt").warnings == []
+
+    with pytest.raises(ValueError):
+        little_assembler.Assembler(string_encoding="ebcdic")
+
+
+def test_disassemble():
+    result = little_assembler.Assembler(prepend_start_address=False).assemble(PROGRAM)
+    words = little_assembler.disassemble(result.words, 0x3000, result.symbols)
+
+    assert [word.instruction for word in words[:3]] == ["LEA R0, string", "PUTS", "HALT"]
+    assert little_assembler.disassemble_word(0xF025) == "HALT"
@@ -0,0 +1,380 @@
+//! WebAssembly bindings of the LC2 assembler, for the browser.
+//!
+//! The results are returned as JavaScript classes with read-only properties,
+//! and the spans are converted into UTF-16 offsets so they can index the
+//! JavaScript strings.
+
+#[cfg(test)]
+mod tests;
+
+use architectures::lc2::Gpr;
+use assemblers::{
+    include::MemoryResolver,
+    lc2::{BytePacking, Lc2AssemblerBuilder, Lc2Output, StringEncoding},
+    lint::{Lint, LintLevel, Warning},
+    Diagnostic,
+};
+use std::{ops::Range, sync::Arc};
+use wasm_bindgen::prelude::*;
+
+/// An LC2 assembler. The options start from the defaults of
+/// `Lc2AssemblerBuilder`
+#[allow(clippy::struct_excessive_bools)]
+#[wasm_bindgen(js_name = Lc2Assembler)]
+pub struct Assembler {
+    #[wasm_bindgen(js_name = optionalStartingOrig)]
+    pub optional_starting_orig: bool,
+    #[wasm_bindgen(js_name = multipleOrigs)]
+    pub multiple_origs: bool,
+    #[wasm_bindgen(js_name = optionalEnd)]
+    pub optional_end: bool,
+    #[wasm_bindgen(js_name = nothingAfterEnd)]
+    pub nothing_after_end: bool,
+    #[wasm_bindgen(js_name = enableStringzp)]
+    pub enable_stringzp: bool,
+    #[wasm_bindgen(js_name = prependStartAddress)]
+    pub prepend_start_address: bool,
+    #[wasm_bindgen(js_name = strictLines)]
+    pub strict_lines: bool,
+    #[wasm_bindgen(js_name = singlePass)]
+    pub single_pass: bool,
+    #[wasm_bindgen(js_name = blkwFill)]
+    pub blkw_fill: u16,
+    string_encoding: StringEncoding,
+    incbin_packing: BytePacking,
+    /// The files that `.INCBIN` can include, if any was added
+    files: Option<MemoryResolver>,
+    lints: Vec<(Lint, LintLevel)>,
+    callee_saved_registers: Vec<Gpr>,
+}
+
+impl Default for Assembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen(js_class = Lc2Assembler)]
+impl Assembler {
+    #[must_use]
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        let defaults = Lc2AssemblerBuilder::new().build();
+
+        Self {
+            optional_starting_orig: defaults.optional_starting_orig(),
+            multiple_origs: defaults.multiple_origs(),
+            optional_end: defaults.optional_end(),
+            nothing_after_end: defaults.nothing_after_end(),
+            enable_stringzp: defaults.enable_stringzp(),
+            prepend_start_address: defaults.prepend_start_address(),
+            strict_lines: defaults.strict_lines(),
+            single_pass: defaults.single_pass(),
+            blkw_fill: defaults.blkw_fill(),
+            string_encoding: defaults.string_encoding(),
+            incbin_packing: defaults.incbin_packing(),
+            files: None,
+            lints: Vec::new(),
+            callee_saved_registers: defaults.callee_saved_registers().collect(),
+        }
+    }
+
+    /// Set the encoding of the strings: `ascii`, `latin1`, `utf8` or `utf16`
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the encoding is not known
+    #[wasm_bindgen(js_name = setStringEncoding)]
+    pub fn set_string_encoding(&mut self, encoding: &str) -> Result<(), String> {
+        self.string_encoding =
+            StringEncoding::try_from(encoding).map_err(|error| error.to_string())?;
+        Ok(())
+    }
+
+    /// Set how the bytes of `.INCBIN` are converted into words: `big_endian`,
+    /// `little_endian` or `one_per_word`
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the packing is not known
+    #[wasm_bindgen(js_name = setIncbinPacking)]
+    pub fn set_incbin_packing(&mut self, packing: &str) -> Result<(), String> {
+        self.incbin_packing = BytePacking::try_from(packing).map_err(|error| error.to_string())?;
+        Ok(())
+    }
+
+    /// Add a file that `.INCBIN "path"` can include. Without any file,
+    /// `.INCBIN` returns an error
+    #[wasm_bindgen(js_name = addFile)]
+    pub fn add_file(&mut self, path: &str, content: &[u8]) {
+        self.files = Some(
+            self.files
+                .take()
+                .unwrap_or_default()
+                .with_file(path, content),
+        );
+    }
+
+    /// Set the level of a lint, like `unused_label`, to `allow`, `warn` or
+    /// `deny`
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the lint or the level are not known
+    #[wasm_bindgen(js_name = setLint)]
+    pub fn set_lint(&mut self, lint: &str, level: &str) -> Result<(), String> {
+        let lint = Lint::try_from(lint).map_err(|error| error.to_string())?;
+        let level = LintLevel::try_from(level).map_err(|error| error.to_string())?;
+
+        self.lints.push((lint, level));
+        Ok(())
+    }
+
+    /// Set the registers that the subroutines must save and restore, by their
+    /// number
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if a number is not a register
+    #[wasm_bindgen(js_name = setCalleeSavedRegisters)]
+    pub fn set_callee_saved_registers(&mut self, registers: &[u8]) -> Result<(), String> {
+        self.callee_saved_registers = registers
+            .iter()
+            .map(|&register| {
+                Gpr::try_from(usize::from(register))
+                    .ok()
+                    .ok_or_else(|| format!("R{register} is not a register"))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(())
+    }
+
+    /// Assemble the source. An error doesn't throw, but it's returned in the
+    /// `error` property of the result
+    #[must_use]
+    pub fn assemble(&self, source: &str) -> Assembly {
+        let mut builder = Lc2AssemblerBuilder::new();
+        builder
+            .optional_starting_orig(self.optional_starting_orig)
+            .multiple_origs(self.multiple_origs)
+            .optional_end(self.optional_end)
+            .nothing_after_end(self.nothing_after_end)
+            .enable_stringzp(self.enable_stringzp)
+            .prepend_start_address(self.prepend_start_address)
+            .strict_lines(self.strict_lines)
+            .single_pass(self.single_pass)
+            .blkw_fill(self.blkw_fill)
+            .string_encoding(self.string_encoding)
+            .incbin_packing(self.incbin_packing)
+            .callee_saved_registers(self.callee_saved_registers.iter().copied());
+        if let Some(files) = &self.files {
+            builder.include_resolver(Arc::new(files.clone()));
+        }
+        for &(lint, level) in &self.lints {
+            builder.lint(lint, level);
+        }
+
+        Assembly::new(source, builder.build().assemble_with_diagnostic(source))
+    }
+}
+
+/// The result of an assembly
+#[wasm_bindgen]
+pub struct Assembly {
+    binary: Vec<u8>,
+    symbols: Vec<Symbol>,
+    entry_point: Option<u16>,
+    listing: Vec<ListingLine>,
+    warnings: Vec<Message>,
+    error: Option<Message>,
+}
+
+impl Assembly {
+    fn new(source: &str, output: Result<Lc2Output, Diagnostic>) -> Self {
+        let output = match output {
+            Ok(output) => output,
+            Err(diagnostic) => {
+                return Self {
+                    binary: Vec::new(),
+                    symbols: Vec::new(),
+                    entry_point: None,
+                    listing: Vec::new(),
+                    warnings: Vec::new(),
+                    error: Some(Message::error(source, &diagnostic)),
+                }
+            }
+        };
+
+        // Sort the symbols by address, like in a listing
+        let mut symbols: Vec<_> = output
+            .symbol_table
+            .into_iter()
+            .map(|(name, address)| Symbol { name, address })
+            .collect();
+        symbols.sort_by(|a, b| (a.address, &a.name).cmp(&(b.address, &b.name)));
+
+        // Every word of the operations and of the data directives, by address
+        let lines: Vec<_> = source.lines().collect();
+        let operations = output
+            .operations
+            .iter()
+            .map(|operation| (operation.address, operation.word, &operation.span));
+        let data = output.data.iter().flat_map(|data| {
+            (data.address..)
+                .zip(&data.words)
+                .map(|(address, &word)| (address, word, &data.span))
+        });
+        let mut listing: Vec<_> = operations
+            .chain(data)
+            .map(|(address, word, span)| {
+                let line = source[..span.start].matches('\n').count();
+                ListingLine {
+                    address,
+                    word,
+                    line: line + 1,
+                    source: lines.get(line).map_or("", |line| line.trim()).to_owned(),
+                }
+            })
+            .collect();
+        listing.sort_by_key(|line| line.address);
+
+        Self {
+            binary: output.binary,
+            symbols,
+            entry_point: output.entry_point,
+            listing,
+            warnings: output
+                .warnings
+                .iter()
+                .map(|warning| Message::warning(source, warning))
+                .collect(),
+            error: None,
+        }
+    }
+}
+
+// The exported functions can't be `const`
+#[allow(clippy::missing_const_for_fn)]
+#[wasm_bindgen]
+impl Assembly {
+    /// `true` if the assembly was successful
+    #[must_use]
+    #[wasm_bindgen(getter)]
+    pub fn ok(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// The assembled binary, empty if the assembly failed
+    #[must_use]
+    #[wasm_bindgen(getter)]
+    pub fn binary(&self) -> Vec<u8> {
+        self.binary.clone()
+    }
+
+    /// The labels with their addresses, sorted by address
+    #[must_use]
+    #[wasm_bindgen(getter)]
+    pub fn symbols(&self) -> Vec<Symbol> {
+        self.symbols.clone()
+    }
+
+    /// The address set by the `.entry` directive or by the operand of the
+    /// `.end` directive, if any
+    #[must_use]
+    #[wasm_bindgen(getter, js_name = entryPoint)]
+    pub fn entry_point(&self) -> Option<u16> {
+        self.entry_point
+    }
+
+    /// Every assembled word of the operations and of the data directives, with
+    /// its address and its line, sorted by address
+    #[must_use]
+    #[wasm_bindgen(getter)]
+    pub fn listing(&self) -> Vec<ListingLine> {
+        self.listing.clone()
+    }
+
+    #[must_use]
+    #[wasm_bindgen(getter)]
+    pub fn warnings(&self) -> Vec<Message> {
+        self.warnings.clone()
+    }
+
+    /// The error that stopped the assembly, if any
+    #[must_use]
+    #[wasm_bindgen(getter)]
+    pub fn error(&self) -> Option<Message> {
+        self.error.clone()
+    }
+}
+
+/// A label and its address
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    #[wasm_bindgen(getter_with_clone)]
+    pub name: String,
+    pub address: u16,
+}
+
+/// A word of an operation or of a data directive
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ListingLine {
+    pub address: u16,
+    pub word: u16,
+    /// The line of the statement, starting from 1
+    pub line: usize,
+    /// The source of the line, without the indentation
+    #[wasm_bindgen(getter_with_clone)]
+    pub source: String,
+}
+
+/// An error or a warning, located by the UTF-16 offsets of the offending
+/// token
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    #[wasm_bindgen(getter_with_clone)]
+    pub message: String,
+    /// The name of the lint, only for the warnings
+    #[wasm_bindgen(getter_with_clone)]
+    pub lint: Option<String>,
+    pub start: usize,
+    pub end: usize,
+    /// The line of the offending token, starting from 1
+    pub line: usize,
+}
+
+impl Message {
+    fn error(source: &str, diagnostic: &Diagnostic) -> Self {
+        let (start, end) = utf16_span(source, &diagnostic.span);
+
+        Self {
+            message: diagnostic.error.to_string(),
+            lint: None,
+            start,
+            end,
+            line: diagnostic.line,
+        }
+    }
+
+    fn warning(source: &str, warning: &Warning) -> Self {
+        let (start, end) = utf16_span(source, &warning.span);
+
+        Self {
+            message: warning.message(),
+            lint: Some(warning.lint.name().to_owned()),
+            start,
+            end,
+            line: warning.line,
+        }
+    }
+}
+
+/// Convert a byte range into UTF-16 offsets
+fn utf16_span(source: &str, span: &Range<usize>) -> (usize, usize) {
+    let offset = |offset: usize| source[..offset].encode_utf16().count();
+
+    (offset(span.start), offset(span.end))
+}
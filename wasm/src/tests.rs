@@ -0,0 +1,168 @@
+use crate::{Assembler, ListingLine, Message, Symbol};
+use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+const PROGRAM: &str = "\
+.orig x3000
+main  lea r0, string
+      puts
+      halt
+string .stringz \"Hi\"
+.end
+";
+
+#[wasm_bindgen_test(unsupported = test)]
+fn assemble() {
+    let assembly = Assembler::new().assemble(PROGRAM);
+
+    assert!(assembly.ok());
+    assert_eq!(assembly.error(), None);
+    assert_eq!(
+        assembly.binary(),
+        [0x30, 0x00, 0xE0, 0x03, 0xF0, 0x22, 0xF0, 0x25, 0x00, 0x48, 0x00, 0x69, 0x00, 0x00]
+    );
+    assert_eq!(
+        assembly.symbols(),
+        [
+            Symbol {
+                name: "main".to_owned(),
+                address: 0x3000
+            },
+            Symbol {
+                name: "string".to_owned(),
+                address: 0x3003
+            },
+        ]
+    );
+    assert_eq!(assembly.entry_point(), None);
+    assert_eq!(
+        assembly.listing(),
+        [
+            ListingLine {
+                address: 0x3000,
+                word: 0xE003,
+                line: 2,
+                source: "main  lea r0, string".to_owned()
+            },
+            ListingLine {
+                address: 0x3001,
+                word: 0xF022,
+                line: 3,
+                source: "puts".to_owned()
+            },
+            ListingLine {
+                address: 0x3002,
+                word: 0xF025,
+                line: 4,
+                source: "halt".to_owned()
+            },
+            ListingLine {
+                address: 0x3003,
+                word: 0x0048,
+                line: 5,
+                source: "string .stringz \"Hi\"".to_owned()
+            },
+            ListingLine {
+                address: 0x3004,
+                word: 0x0069,
+                line: 5,
+                source: "string .stringz \"Hi\"".to_owned()
+            },
+            ListingLine {
+                address: 0x3005,
+                word: 0x0000,
+                line: 5,
+                source: "string .stringz \"Hi\"".to_owned()
+            },
+        ]
+    );
+    assert_eq!(
+        assembly.warnings(),
+        [Message {
+            message: "The label is never referenced".to_owned(),
+            lint: Some("unused_label".to_owned()),
+            start: 12,
+            end: 16,
+            line: 2
+        }]
+    );
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn error() {
+    // The offsets are counted in UTF-16 code units
+    let assembly = Assembler::new().assemble(".orig x3000\n.stringz \"𝄞\"\nld r0, nowhere\n.end");
+
+    assert!(!assembly.ok());
+    assert!(assembly.binary().is_empty());
+    assert!(assembly.symbols().is_empty());
+    assert_eq!(
+        assembly.error(),
+        Some(Message {
+            message: "The label was used but not declared".to_owned(),
+            lint: None,
+            start: 33,
+            end: 40,
+            line: 3
+        })
+    );
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn options() {
+    let mut assembler = Assembler::new();
+    assembler.optional_end = true;
+    assembler.prepend_start_address = false;
+    assembler.set_lint("unused-label", "deny").unwrap();
+
+    let assembly = assembler.assemble(".orig x3000\nhalt");
+    assert_eq!(assembly.binary(), [0xF0, 0x25]);
+
+    let assembly = assembler.assemble(PROGRAM);
+    assert_eq!(
+        assembly.error().map(|error| error.message),
+        Some("The label is never referenced (denied by \"unused_label\")".to_owned())
+    );
+
+    assembler.set_string_encoding("ascii").unwrap();
+    assert!(!assembler.assemble(".orig x3000\n.stringz \"è\"\n.end").ok());
+
+    assert!(assembler.set_string_encoding("ebcdic").is_err());
+    assert!(assembler.set_lint("unknown", "deny").is_err());
+    assert!(assembler.set_lint("unused_label", "forbid").is_err());
+    assert!(assembler.set_callee_saved_registers(&[4, 5]).is_ok());
+    assert!(assembler.set_callee_saved_registers(&[8]).is_err());
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn defaults() {
+    let assembler = Assembler::new();
+
+    assert!(!assembler.optional_end);
+    assert!(assembler.nothing_after_end);
+    assert!(assembler.prepend_start_address);
+    assert!(!assembler.single_pass);
+    assert_eq!(assembler.blkw_fill, 0);
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn incbin() {
+    let mut assembler = Assembler::new();
+    assembler.prepend_start_address = false;
+    let program = ".orig x3000\n.incbin \"data.bin\"\n.end";
+
+    assert!(!assembler.assemble(program).ok());
+
+    assembler.add_file("data.bin", &[0x12, 0x34]);
+    assert_eq!(assembler.assemble(program).binary(), [0x12, 0x34]);
+
+    assembler.set_incbin_packing("one_per_word").unwrap();
+    assembler.single_pass = true;
+    assert_eq!(
+        assembler.assemble(program).binary(),
+        [0x00, 0x12, 0x00, 0x34]
+    );
+
+    assert!(assembler.set_incbin_packing("middle_endian").is_err());
+}